@@ -0,0 +1,153 @@
+//! Derive macro for the `InterpolationPrimitive` trait of `minterpolate`.
+//!
+//! Usually used through the `derive` feature of `minterpolate`, which re-exports the macro. The
+//! generated implementation works component-wise: `add`, `sub` and `mul` are applied to each
+//! field, and `dot` and `magnitude2` are the sums over all fields, so the struct behaves like a
+//! vector of the concatenated components of its fields. All fields must implement
+//! `InterpolationPrimitive`, except fields marked with `#[interpolate(skip)]`, which keep the value
+//! of the left hand side in `add`, `sub` and `mul`, and are set to `Default::default()` in
+//! `from_components`.
+//!
+//! ## Examples
+//!
+//! ```ignore
+//! #[derive(Clone, InterpolationPrimitive)]
+//! struct Transform {
+//!     translation: [f32; 3],
+//!     scale: f32,
+//!     #[interpolate(skip)]
+//!     name: String,
+//! }
+//! ```
+
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+extern crate syn;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use syn::{Data, DeriveInput, Field, Index, Member, Type};
+
+/// Derive `InterpolationPrimitive` for a struct, see the crate documentation.
+#[proc_macro_derive(InterpolationPrimitive, attributes(interpolate))]
+pub fn derive_interpolation_primitive(input: TokenStream) -> TokenStream {
+    let input = match syn::parse::<DeriveInput>(input) {
+        Ok(input) => input,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match input.data {
+        Data::Struct(ref data) => &data.fields,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "InterpolationPrimitive can only be derived for structs",
+            ))
+        }
+    };
+    let mut members: Vec<Member> = Vec::new();
+    let mut types: Vec<&Type> = Vec::new();
+    let mut skipped: Vec<Member> = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        let member = match field.ident {
+            Some(ref ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(Index::from(i)),
+        };
+        if is_skipped(field)? {
+            skipped.push(member);
+        } else {
+            members.push(member);
+            types.push(&field.ty);
+        }
+    }
+    let primitive = quote!(::minterpolate::InterpolationPrimitive);
+    // Offset of the components of each field, the sum of the component counts of the fields
+    // before it
+    let offsets = (0..types.len())
+        .map(|i| {
+            let before = &types[..i];
+            quote!(0 #(+ <#before as #primitive>::component_count())*)
+        })
+        .collect::<Vec<_>>();
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #primitive for #ident #ty_generics #where_clause {
+            fn add(&self, other: &Self) -> Self {
+                #ident {
+                    #(#members: #primitive::add(&self.#members, &other.#members),)*
+                    #(#skipped: ::std::clone::Clone::clone(&self.#skipped),)*
+                }
+            }
+
+            fn sub(&self, other: &Self) -> Self {
+                #ident {
+                    #(#members: #primitive::sub(&self.#members, &other.#members),)*
+                    #(#skipped: ::std::clone::Clone::clone(&self.#skipped),)*
+                }
+            }
+
+            fn mul(&self, scalar: f32) -> Self {
+                #ident {
+                    #(#members: #primitive::mul(&self.#members, scalar),)*
+                    #(#skipped: ::std::clone::Clone::clone(&self.#skipped),)*
+                }
+            }
+
+            fn dot(&self, other: &Self) -> f32 {
+                0. #(+ #primitive::dot(&self.#members, &other.#members))*
+            }
+
+            fn magnitude2(&self) -> f32 {
+                0. #(+ #primitive::magnitude2(&self.#members))*
+            }
+
+            fn component_count() -> usize {
+                0 #(+ <#types as #primitive>::component_count())*
+            }
+
+            fn write_components(&self, out: &mut [f32]) {
+                #(#primitive::write_components(&self.#members, &mut out[#offsets..]);)*
+            }
+
+            fn from_components(src: &[f32]) -> Self {
+                #ident {
+                    #(#members: <#types as #primitive>::from_components(&src[#offsets..]),)*
+                    #(#skipped: ::std::default::Default::default(),)*
+                }
+            }
+
+            fn antipodal_equivalent() -> bool {
+                false
+            }
+        }
+    })
+}
+
+// True if the field is marked with `#[interpolate(skip)]`.
+fn is_skipped(field: &Field) -> syn::Result<bool> {
+    let mut skip = false;
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("interpolate"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") {
+                skip = true;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported interpolate attribute"))
+            }
+        })?;
+    }
+    Ok(skip)
+}
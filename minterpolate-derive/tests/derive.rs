@@ -0,0 +1,50 @@
+extern crate minterpolate;
+extern crate minterpolate_derive;
+
+use minterpolate::{linear_interpolate, InterpolationPrimitive};
+
+#[derive(Clone, Debug, PartialEq, minterpolate_derive::InterpolationPrimitive)]
+struct Transform {
+    translation: [f32; 3],
+    scale: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, minterpolate_derive::InterpolationPrimitive)]
+struct Labeled(f32, #[interpolate(skip)] &'static str);
+
+#[test]
+fn test_derive_linear() {
+    let input = vec![0., 1.];
+    let output = vec![
+        Transform {
+            translation: [0., 0., 0.],
+            scale: 1.,
+        },
+        Transform {
+            translation: [2., 4., 0.],
+            scale: 3.,
+        },
+    ];
+    assert_eq!(
+        Transform {
+            translation: [1., 2., 0.],
+            scale: 2.,
+        },
+        linear_interpolate(0.5, &input, &output, false)
+    );
+    assert_eq!(4, Transform::component_count());
+    let mut components = [0.; 4];
+    output[1].write_components(&mut components);
+    assert_eq!([2., 4., 0., 3.], components);
+    assert_eq!(output[1], Transform::from_components(&components));
+}
+
+#[test]
+fn test_derive_skip() {
+    let a = Labeled(1., "a");
+    let b = Labeled(3., "b");
+    assert_eq!(Labeled(4., "a"), a.add(&b));
+    assert_eq!(Labeled(2., "a"), a.mul(2.));
+    assert_eq!(1, Labeled::component_count());
+    assert_eq!(Labeled(5., ""), Labeled::from_components(&[5.]));
+}
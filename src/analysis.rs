@@ -0,0 +1,855 @@
+use cubic_spline::{hermite_segment, spline};
+use curve::keyframe_output_index;
+use derivative::derivative;
+use error::InterpolationError;
+use geometry::curvature;
+use primitive::InterpolationPrimitive;
+use sample_range;
+use InterpolationFunction;
+
+// Number of samples per segment used when validating monotonicity.
+const MONOTONE_SAMPLES: usize = 32;
+
+// Number of samples per segment used when building arc length tables.
+const ARC_LENGTH_SAMPLES: usize = 32;
+
+// Relative error of the interpolation factor above which `precision_warning` flags a segment.
+const PRECISION_WARNING_ERROR: f32 = 1e-3;
+
+/// An `(input, value)` pair on a scalar curve.
+pub type CurvePoint = (f32, f32);
+
+/// Find the minimum and maximum values of a scalar curve, and the input values where they occur.
+///
+/// For spline interpolation the extrema between keyframes are found by solving for the roots of
+/// the derivative of each segment. For all other functions only the keyframes are checked, which
+/// is exact for linear and step interpolation.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+///
+/// ## Returns
+///
+/// `(min, max)`, where each is an `(input, value)` pair, or `None` if there is no data.
+pub fn extrema(
+    inputs: &[f32],
+    outputs: &[f32],
+    function: &InterpolationFunction<f32>,
+) -> (Option<CurvePoint>, Option<CurvePoint>) {
+    if inputs.is_empty() || outputs.is_empty() {
+        return (None, None);
+    }
+    let mut candidates = inputs
+        .iter()
+        .enumerate()
+        .map(|(i, &t)| (t, outputs[keyframe_output_index(function, i)]))
+        .collect::<Vec<_>>();
+    for index in 0..inputs.len() - 1 {
+        let (p0, p1, m0, m1) = match hermite_segment(index, inputs, outputs, function) {
+            Some(segment) => segment,
+            None => break,
+        };
+        // Derivative of the segment with regards to the local parameter: a*u^2 + b*u + c
+        let a = 6. * p0 + 3. * m0 - 6. * p1 + 3. * m1;
+        let b = -6. * p0 - 4. * m0 + 6. * p1 - 2. * m1;
+        let c = m0;
+        let t_diff = inputs[index + 1] - inputs[index];
+        for u in quadratic_roots(a, b, c) {
+            if u > 0. && u < 1. {
+                let t = inputs[index] + u * t_diff;
+                candidates.push((t, spline(t, inputs[index], t_diff, &p0, &p1, &m0, &m1)));
+            }
+        }
+    }
+    let min = candidates
+        .iter()
+        .cloned()
+        .fold(None, |min: Option<CurvePoint>, c| match min {
+            Some(m) if m.1 <= c.1 => Some(m),
+            _ => Some(c),
+        });
+    let max = candidates
+        .iter()
+        .cloned()
+        .fold(None, |max: Option<CurvePoint>, c| match max {
+            Some(m) if m.1 >= c.1 => Some(m),
+            _ => Some(c),
+        });
+    (min, max)
+}
+
+/// Build the inverse of a monotone scalar curve, mapping an output value back to an input value.
+///
+/// The curve is validated by sampling each segment, and must be either non-decreasing or
+/// non-increasing. The inverse finds the segment containing the value, and inverts it exactly for
+/// linear interpolation, or using bisection for all other functions. Values outside the range of
+/// the curve map to the first or last input value.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+///
+/// ## Returns
+///
+/// The inverse function, or `InterpolationError::NotMonotone` if the curve isn't monotone.
+pub fn invert_monotone(
+    inputs: &[f32],
+    outputs: &[f32],
+    function: &InterpolationFunction<f32>,
+) -> Result<impl Fn(f32) -> f32, InterpolationError> {
+    let samples = inputs
+        .windows(2)
+        .flat_map(|w| sample_range(w[0], w[1], MONOTONE_SAMPLES))
+        .map(|t| function.interpolate(t, inputs, outputs, false))
+        .collect::<Vec<_>>();
+    let increasing = samples.windows(2).all(|w| w[0] <= w[1]);
+    if !increasing && !samples.windows(2).all(|w| w[0] >= w[1]) {
+        return Err(InterpolationError::NotMonotone);
+    }
+    let inputs = inputs.to_vec();
+    let outputs = outputs.to_vec();
+    let function = function.clone();
+    Ok(move |value: f32| {
+        let key = |i: usize| outputs[keyframe_output_index(&function, i)];
+        // Work on increasing values, by flipping the sign of decreasing curves
+        let sign = if increasing { 1. } else { -1. };
+        let value = value * sign;
+        let last = inputs.len() - 1;
+        if value <= key(0) * sign {
+            return inputs[0];
+        }
+        if value >= key(last) * sign {
+            return inputs[last];
+        }
+        let index = (0..last)
+            .find(|&i| value <= key(i + 1) * sign)
+            .unwrap_or(last - 1);
+        let (left, right) = (inputs[index], inputs[index + 1]);
+        if function == InterpolationFunction::Linear {
+            let (v0, v1) = (key(index) * sign, key(index + 1) * sign);
+            return left + (right - left) * (value - v0) / (v1 - v0);
+        }
+        let (mut low, mut high) = (left, right);
+        for _ in 0..32 {
+            let middle = (low + high) / 2.;
+            if function.interpolate(middle, &inputs, &outputs, false) * sign < value {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+        (low + high) / 2.
+    })
+}
+
+// Real roots of a*x^2 + b*x + c, degrading to the linear case when a is zero.
+fn quadratic_roots(a: f32, b: f32, c: f32) -> Vec<f32> {
+    if a.abs() < 1e-12 {
+        if b.abs() < 1e-12 {
+            vec![]
+        } else {
+            vec![-c / b]
+        }
+    } else {
+        let discriminant = b * b - 4. * a * c;
+        if discriminant < 0. {
+            vec![]
+        } else {
+            let sqrt = discriminant.sqrt();
+            vec![(-b - sqrt) / (2. * a), (-b + sqrt) / (2. * a)]
+        }
+    }
+}
+
+/// Calculate the total variation of a curve, i.e. the length of the path it traces in value space.
+///
+/// The curve is sampled at `samples` evenly spaced input values covering the range of `inputs`,
+/// and the distances between consecutive samples are summed, using `sub().magnitude2().sqrt()`.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `samples`: number of samples
+pub fn total_variation<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    samples: usize,
+) -> f32
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let values = sample_range(inputs[0], inputs[inputs.len() - 1], samples)
+        .into_iter()
+        .map(|t| function.interpolate(t, inputs, outputs, false))
+        .collect::<Vec<_>>();
+    values
+        .windows(2)
+        .map(|w| w[1].sub(&w[0]).magnitude2().sqrt())
+        .sum()
+}
+
+/// Calculate the total curvature of a curve, i.e. the integral of the curvature over the length
+/// of the curve, which is the total angle the direction of the curve turns.
+///
+/// The integral is approximated with the midpoint rule over `samples` equally sized intervals
+/// covering the range of `inputs`, see `curvature`. A straight curve has a total curvature of
+/// zero.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `samples`: number of intervals
+pub fn total_curvature<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    samples: usize,
+) -> f32
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let start = inputs[0];
+    let step = (inputs[inputs.len() - 1] - start) / samples as f32;
+    (0..samples)
+        .map(|i| {
+            let t = start + (i as f32 + 0.5) * step;
+            let speed = derivative(t, inputs, outputs, function).magnitude2().sqrt();
+            curvature(t, inputs, outputs, function) * speed * step
+        })
+        .sum()
+}
+
+/// Calculate the maximum difference between two interpolation functions applied to the same
+/// keyframes.
+///
+/// Both curves are sampled at `samples` evenly spaced input values covering the range of
+/// `inputs`, and the largest distance between them is returned, using `sub().magnitude2().sqrt()`.
+/// The output data for each function is laid out as expected by that function, e.g. the same
+/// positions with or without tangents.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs_a`: list of output values, laid out as expected by `function_a`
+/// - `outputs_b`: list of output values, laid out as expected by `function_b`
+/// - `function_a`: the interpolation function used for `outputs_a`
+/// - `function_b`: the interpolation function used for `outputs_b`
+/// - `samples`: number of samples
+pub fn compare_functions<T>(
+    inputs: &[f32],
+    outputs_a: &[T],
+    outputs_b: &[T],
+    function_a: &InterpolationFunction<T>,
+    function_b: &InterpolationFunction<T>,
+    samples: usize,
+) -> f32
+where
+    T: InterpolationPrimitive + Clone,
+{
+    sample_range(inputs[0], inputs[inputs.len() - 1], samples)
+        .into_iter()
+        .map(|t| {
+            let a = function_a.interpolate(t, inputs, outputs_a, false);
+            let b = function_b.interpolate(t, inputs, outputs_b, false);
+            a.sub(&b).magnitude2().sqrt()
+        })
+        .fold(0., f32::max)
+}
+
+/// Find the lowest and highest speed along a curve, e.g. to warn about sudden moves.
+///
+/// The magnitude of the `derivative` is sampled at `samples` evenly spaced input values covering
+/// the range of `inputs`, so short peaks between samples can be missed.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `samples`: number of samples
+///
+/// ## Returns
+///
+/// `(min_speed, max_speed)`
+pub fn speed_extrema<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    samples: usize,
+) -> (f32, f32)
+where
+    T: InterpolationPrimitive + Clone,
+{
+    sample_range(inputs[0], inputs[inputs.len() - 1], samples)
+        .into_iter()
+        .map(|t| derivative(t, inputs, outputs, function).magnitude2().sqrt())
+        .fold((f32::INFINITY, 0.), |(min, max), speed| {
+            (min.min(speed), max.max(speed))
+        })
+}
+
+/// Calculate the length of the curve between two input values.
+///
+/// The range is split at the keyframes inside it, and each part is approximated by a polyline
+/// with `subdivisions` segments, so parts of segments where `t0` or `t1` fall inside a segment are
+/// handled with the same accuracy as whole segments. Distances are measured using
+/// `sub().magnitude2().sqrt()`. The order of `t0` and `t1` doesn't matter, and the curve is
+/// constant outside of the range of `inputs`, so parts of the range outside of it have zero length.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `t0`, `t1`: input values the length is measured between
+/// - `subdivisions`: number of polyline segments per part of a segment
+pub fn arc_length_between<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    t0: f32,
+    t1: f32,
+    subdivisions: usize,
+) -> f32
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let start = t0.min(t1).max(inputs[0]);
+    let end = t0.max(t1).min(inputs[inputs.len() - 1]);
+    if start >= end {
+        return 0.;
+    }
+    let mut bounds = vec![start];
+    bounds.extend(inputs.iter().filter(|&&t| t > start && t < end));
+    bounds.push(end);
+    bounds
+        .windows(2)
+        .map(|w| {
+            let values = sample_range(w[0], w[1], subdivisions.max(1) + 1)
+                .into_iter()
+                .map(|t| function.interpolate(t, inputs, outputs, false))
+                .collect::<Vec<_>>();
+            values
+                .windows(2)
+                .map(|v| v[1].sub(&v[0]).magnitude2().sqrt())
+                .sum::<f32>()
+        })
+        .sum()
+}
+
+/// Find the input value where a given fraction of the length of a curve has been covered.
+///
+/// The curve is approximated by a polyline with a fixed number of segments per keyframe interval,
+/// building a table of the accumulated length at each sample. The input value is then found by
+/// linear interpolation in that table. Distances are measured using `sub().magnitude2().sqrt()`.
+/// This is useful for distributing objects evenly along a path, regardless of how fast the curve
+/// moves.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `fraction`: fraction of the total length, values outside of `[0, 1]` are clamped
+pub fn time_at_length_fraction<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    fraction: f32,
+) -> f32
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let (times, lengths) = arc_length_table(inputs, outputs, function);
+    let target = fraction.clamp(0., 1.) * lengths[lengths.len() - 1];
+    time_at_length(&times, &lengths, target)
+}
+
+/// Place points at regular distances along a curve, e.g. for drawing many instances of an object
+/// along a path.
+///
+/// Point `i` is placed where the length of the curve from its start is `i * spacing`, using the
+/// same arc length table as `time_at_length_fraction`. Placement stops early when the curve runs
+/// out of length, or when `out` is full.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `count`: maximum number of points to place
+/// - `spacing`: distance along the curve between consecutive points
+/// - `out`: buffer the points are written to
+///
+/// ## Returns
+///
+/// The number of points written to `out`.
+pub fn instance_transforms_along<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    count: usize,
+    spacing: f32,
+    out: &mut [T],
+) -> usize
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let (times, lengths) = arc_length_table(inputs, outputs, function);
+    let total = lengths[lengths.len() - 1];
+    let mut written = 0;
+    for (i, slot) in out.iter_mut().take(count).enumerate() {
+        let target = i as f32 * spacing;
+        if target > total {
+            break;
+        }
+        let t = time_at_length(&times, &lengths, target);
+        *slot = function.interpolate(t, inputs, outputs, false);
+        written += 1;
+    }
+    written
+}
+
+// Input value where the accumulated length in an arc length table reaches `target`.
+fn time_at_length(times: &[f32], lengths: &[f32], target: f32) -> f32 {
+    let index = lengths
+        .iter()
+        .position(|&length| length >= target)
+        .unwrap_or(lengths.len() - 1);
+    if index == 0 {
+        return times[0];
+    }
+    let d = (target - lengths[index - 1]) / (lengths[index] - lengths[index - 1]);
+    times[index - 1] + (times[index] - times[index - 1]) * d
+}
+
+// Sample input values covering the range of `inputs`, and the accumulated length of the curve at
+// each of them.
+fn arc_length_table<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+) -> (Vec<f32>, Vec<f32>)
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let mut times = vec![inputs[0]];
+    for w in inputs.windows(2) {
+        times.extend(
+            sample_range(w[0], w[1], ARC_LENGTH_SAMPLES + 1)
+                .into_iter()
+                .skip(1),
+        );
+    }
+    let values = times
+        .iter()
+        .map(|&t| function.interpolate(t, inputs, outputs, false))
+        .collect::<Vec<_>>();
+    let mut length = 0.;
+    let mut lengths = vec![0.];
+    lengths.extend(values.windows(2).map(|v| {
+        length += v[1].sub(&v[0]).magnitude2().sqrt();
+        length
+    }));
+    (times, lengths)
+}
+
+/// Estimate how far each segment of a curve is from a straight line.
+///
+/// Each segment is sampled at `samples` evenly spaced input values between its keyframes, and the
+/// distance from the curve to the straight line between the values at the keyframes is measured
+/// at each of them, using `sub().magnitude2().sqrt()`. Segments with high values need more
+/// keyframes if the curve is converted to linear interpolation.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `samples`: number of test points inside each segment
+///
+/// ## Returns
+///
+/// The maximum distance for each segment, one less than the number of keyframes.
+pub fn segment_nonlinearity<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    samples: usize,
+) -> Vec<f32>
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let sample = |t: f32| function.interpolate(t, inputs, outputs, false);
+    inputs
+        .windows(2)
+        .enumerate()
+        .map(|(i, w)| {
+            let start = outputs[keyframe_output_index(function, i)].clone();
+            let end = outputs[keyframe_output_index(function, i + 1)].clone();
+            (1..=samples)
+                .map(|j| {
+                    let d = j as f32 / (samples + 1) as f32;
+                    let line = start.add(&end.sub(&start).mul(d));
+                    sample(w[0] + (w[1] - w[0]) * d)
+                        .sub(&line)
+                        .magnitude2()
+                        .sqrt()
+                })
+                .fold(0., f32::max)
+        })
+        .collect()
+}
+
+/// Calculate the average value of a curve over a range of input values.
+///
+/// The curve is integrated over the range with the trapezoidal rule, using `samples` evenly spaced
+/// input values, and the result is divided by the length of the range. For a zero length range,
+/// the value of the curve at that input value is returned.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `range`: `(start, end)` input values to average over
+/// - `samples`: number of samples, at least 2 are always used
+pub fn average_over<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    range: (f32, f32),
+    samples: usize,
+) -> T
+where
+    T: InterpolationPrimitive + Clone,
+{
+    if range.0 == range.1 {
+        return function.interpolate(range.0, inputs, outputs, false);
+    }
+    let values = sample_range(range.0, range.1, samples.max(2))
+        .into_iter()
+        .map(|t| function.interpolate(t, inputs, outputs, false))
+        .collect::<Vec<_>>();
+    // With equal intervals, the trapezoidal integral divided by the range length is the mean of
+    // the interval averages
+    let last = values.len() - 1;
+    values[1..last]
+        .iter()
+        .fold(values[0].add(&values[last]).mul(0.5), |sum, v| sum.add(v))
+        .mul(1. / last as f32)
+}
+
+/// Find segments where the interpolation factor loses precision because of large input values.
+///
+/// The interpolation factor is computed as `(input - inputs[i]) / (inputs[i + 1] - inputs[i])`.
+/// When the input values are large compared to the length of the segment, the subtractions cancel
+/// most significant digits, and the factor jumps in coarse steps, or the segment collapses
+/// entirely. A segment is flagged when the rounding error of its input values, relative to its
+/// length, is more than `0.1%`. Such curves should be rebased to start at zero, see `rebase_time`.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+///
+/// ## Returns
+///
+/// The index of the first keyframe of each flagged segment, in order.
+pub fn precision_warning(inputs: &[f32]) -> Vec<usize> {
+    inputs
+        .windows(2)
+        .enumerate()
+        .filter(|&(_, w)| {
+            let rounding = w[0].abs().max(w[1].abs()) * f32::EPSILON;
+            rounding > (w[1] - w[0]).abs() * PRECISION_WARNING_ERROR
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Shift input values so the first keyframe is at zero, see `precision_warning`.
+///
+/// Note that this only restores precision if the input values still differ after the shift, so
+/// input values should be rebased before they are accumulated to large values where possible.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe, modified in place
+///
+/// ## Returns
+///
+/// The original input value of the first keyframe, to subtract from query inputs.
+pub fn rebase_time(inputs: &mut [f32]) -> f32 {
+    let start = inputs.first().cloned().unwrap_or(0.);
+    for input in inputs.iter_mut() {
+        *input -= start;
+    }
+    start
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mint::Vector3;
+
+    #[test]
+    fn test_extrema_catmull_rom_apex() {
+        let input = vec![0., 1., 2., 3.];
+        let output = vec![1., 0., 1., 1., 0., -1.];
+        let (min, max) = extrema(&input, &output, &InterpolationFunction::CatmullRomSpline);
+        assert_eq!(Some((0., 0.)), min);
+        let (t, value) = max.unwrap();
+        assert!((t - 1.5).abs() < 1e-5);
+        assert!((value - 1.125).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_extrema_linear() {
+        let input = vec![0., 1., 2.];
+        let output = vec![0., 2., -1.];
+        assert_eq!(
+            (Some((2., -1.)), Some((1., 2.))),
+            extrema(&input, &output, &InterpolationFunction::Linear)
+        );
+        assert_eq!(
+            (None, None),
+            extrema(&[], &[], &InterpolationFunction::Linear)
+        );
+    }
+
+    #[test]
+    fn test_invert_monotone() {
+        let input = vec![0., 1., 2., 4.];
+        let output = vec![1., 0., 0.5, 2., 3., 0.5];
+        let function = InterpolationFunction::CatmullRomSpline;
+        let invert = invert_monotone(&input, &output, &function).unwrap();
+        for i in 0..=40 {
+            let t = i as f32 / 10.;
+            let value = function.interpolate(t, &input, &output, false);
+            assert!((invert(value) - t).abs() < 1e-3);
+        }
+        let linear = invert_monotone(&[0., 2.], &[4., 0.], &InterpolationFunction::Linear).unwrap();
+        assert_eq!(0.5, linear(3.));
+    }
+
+    #[test]
+    fn test_invert_not_monotone() {
+        let input = vec![0., 1., 2.];
+        let output = vec![0., 1., 0.];
+        assert_eq!(
+            Some(InterpolationError::NotMonotone),
+            invert_monotone(&input, &output, &InterpolationFunction::Linear).err()
+        );
+    }
+
+    #[test]
+    fn test_total_curvature() {
+        let input = vec![0., 1., 2., 3.];
+        let straight = vec![[0., 0.], [1., 1.], [3., 3.], [4., 4.]];
+        let linear = InterpolationFunction::Linear;
+        assert_eq!(0., total_curvature(&input, &straight, &linear, 100));
+        let length = total_variation(&input, &straight, &linear, 100);
+        assert!((length - 32f32.sqrt()).abs() < 1e-4);
+
+        let wiggly = vec![[1., 0.], [0., 0.], [1., 1.], [2., -1.], [3., 1.], [1., 0.]];
+        let catmull_rom = InterpolationFunction::CatmullRomSpline;
+        assert!(total_curvature(&input, &wiggly, &catmull_rom, 100) > 3.);
+        assert!(total_variation(&input, &wiggly, &catmull_rom, 100) > 3.);
+
+        let scalar = vec![0f32, 1., 0.];
+        let linear = InterpolationFunction::Linear;
+        let variation = total_variation(&input[..3], &scalar, &linear, 101);
+        assert!((variation - 2.).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_compare_functions() {
+        let input = vec![0., 1., 2.];
+        let ramp = vec![0., 1., 2.];
+        let linear = InterpolationFunction::Linear;
+        let step = InterpolationFunction::Step;
+        assert_eq!(
+            0.,
+            compare_functions(&input, &ramp, &ramp, &linear, &linear, 101)
+        );
+        let difference = compare_functions(&input, &ramp, &ramp, &linear, &step, 101);
+        assert!(difference > 0.95 && difference < 1.);
+        assert_eq!(
+            difference,
+            compare_functions(&input, &ramp, &ramp, &step, &linear, 101)
+        );
+
+        let padded = vec![1., 0., 1., 2., 1.];
+        let catmull_rom = InterpolationFunction::CatmullRomSpline;
+        assert!(compare_functions(&input, &ramp, &padded, &linear, &catmull_rom, 101) < 1e-5);
+    }
+
+    #[test]
+    fn test_average_over_triangle_wave() {
+        let input = vec![0., 1., 2.];
+        let output = vec![0., 2., 0.];
+        let function = InterpolationFunction::Linear;
+        let average: f32 = average_over(&input, &output, &function, (0., 2.), 101);
+        assert!((average - 1.).abs() < 1e-5);
+        let average: f32 = average_over(&input, &output, &function, (0., 1.), 10);
+        assert!((average - 1.).abs() < 1e-5);
+        assert_eq!(2., average_over(&input, &output, &function, (1., 1.), 10));
+    }
+
+    #[test]
+    fn test_average_over_empty_range_spline() {
+        let input = vec![0., 1., 2.];
+        let output = vec![5., 0., 0., 0., 2., 0., 0., 0., 5.];
+        let function = InterpolationFunction::CubicSpline;
+        assert_eq!(0., average_over(&input, &output, &function, (0., 0.), 10));
+        assert_eq!(2., average_over(&input, &output, &function, (1., 1.), 10));
+        let output = vec![5., 0., 2., 0., 5.];
+        let function = InterpolationFunction::CatmullRomSpline;
+        assert_eq!(0., average_over(&input, &output, &function, (0., 0.), 10));
+    }
+
+    #[test]
+    fn test_arc_length_between() {
+        let input = vec![0., 1., 2., 4.];
+        let output = vec![[1., 0.], [0., 0.], [1., 1.], [2., 0.], [3., 2.], [1., 0.]];
+        let function = InterpolationFunction::CatmullRomSpline;
+        let total = arc_length_between(&input, &output, &function, 0., 4., 64);
+        let left = arc_length_between(&input, &output, &function, 0., 1.5, 64);
+        let right = arc_length_between(&input, &output, &function, 4., 1.5, 64);
+        assert!((left + right - total).abs() < 1e-4);
+        assert!(left > 0. && right > 0.);
+        assert_eq!(
+            total,
+            arc_length_between(&input, &output, &function, -1., 5., 64)
+        );
+
+        let input = vec![0., 4.];
+        let output = vec![[0., 0.], [3., 4.]];
+        let length = arc_length_between(&input, &output, &InterpolationFunction::Linear, 1., 3., 4);
+        assert!((length - 2.5).abs() < 1e-5);
+
+        let output = vec![4., 0.];
+        let length = arc_length_between(&input, &output, &InterpolationFunction::Linear, 0., 1., 4);
+        assert!((length - 1.).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_speed_extrema() {
+        let input = vec![0., 1., 3.];
+        let output = vec![[0., 0.], [3., 4.], [3., 5.]];
+        let (min, max) = speed_extrema(&input, &output, &InterpolationFunction::Linear, 31);
+        assert!((min - 0.5).abs() < 1e-5);
+        assert!((max - 5.).abs() < 1e-5);
+        let output = vec![1f32, 0., -2.];
+        let (min, max) = speed_extrema(&input, &output, &InterpolationFunction::Linear, 31);
+        assert!((min - 1.).abs() < 1e-5);
+        assert!((max - 1.).abs() < 1e-5);
+
+        let output = vec![[0., 0.], [0., 0.], [3., 4.], [3., 5.], [0., 0.]];
+        let function = InterpolationFunction::CatmullRomSpline;
+        let (min, max) = speed_extrema(&input, &output, &function, 31);
+        assert!(min < 0.5 && max > 5.);
+    }
+
+    #[test]
+    fn test_time_at_length_fraction() {
+        let input = vec![0., 1., 3.];
+        let output = vec![0., 10., 12.];
+        let function = InterpolationFunction::Linear;
+        assert_eq!(0., time_at_length_fraction(&input, &output, &function, 0.));
+        assert_eq!(3., time_at_length_fraction(&input, &output, &function, 1.));
+        assert!((time_at_length_fraction(&input, &output, &function, 0.5) - 0.6).abs() < 1e-5);
+        assert_eq!(0., time_at_length_fraction(&input, &output, &function, -1.));
+        assert_eq!(3., time_at_length_fraction(&input, &output, &function, 2.));
+        // Going back down counts towards the length as well
+        let output = vec![0., 10., 0.];
+        let input = vec![0., 1., 2.];
+        assert!((time_at_length_fraction(&input, &output, &function, 0.25) - 0.5).abs() < 1e-5);
+        assert!((time_at_length_fraction(&input, &output, &function, 0.5) - 1.).abs() < 1e-5);
+        assert!((time_at_length_fraction(&input, &output, &function, 0.75) - 1.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_segment_nonlinearity() {
+        let input = vec![0., 1., 2.];
+        let output = vec![0., 0., 1., 1., 1., 3., 2., 0., 0.];
+        let nonlinearity =
+            segment_nonlinearity(&input, &output, &InterpolationFunction::CubicSpline, 16);
+        assert_eq!(2, nonlinearity.len());
+        assert!(nonlinearity[0] < 1e-6);
+        assert!(nonlinearity[1] > 0.1);
+    }
+
+    #[test]
+    fn test_precision_warning() {
+        // Distinct in f32, but the segment is a single ulp long
+        let mut input = vec![1e7 - 5000., 1e7, 1e7 + 1., 1e7 + 5000.];
+        assert!(input[1] < input[2]);
+        assert_eq!(vec![1], precision_warning(&input));
+        assert!(precision_warning(&[0., 0.001, 1., 100.]).is_empty());
+        assert_eq!(1e7 - 5000., rebase_time(&mut input));
+        assert_eq!(vec![0., 5000., 5001., 10000.], input);
+    }
+
+    #[test]
+    fn test_instance_transforms_along() {
+        let input = vec![0., 1., 2.];
+        let output = vec![
+            Vector3::from([0., 0., 0.]),
+            Vector3::from([4., 0., 0.]),
+            Vector3::from([4., 3., 0.]),
+        ];
+        let function = InterpolationFunction::Linear;
+        let zero = Vector3::from([0., 0., 0.]);
+        let mut out = vec![zero; 6];
+        assert_eq!(
+            5,
+            instance_transforms_along(&input, &output, &function, 5, 1.5, &mut out)
+        );
+        let expected = [
+            [0., 0., 0.],
+            [1.5, 0., 0.],
+            [3., 0., 0.],
+            [4., 0.5, 0.],
+            [4., 2., 0.],
+        ];
+        for (point, expected) in out.iter().zip(&expected) {
+            assert!(point.sub(&Vector3::from(*expected)).magnitude() < 1e-4);
+        }
+        assert_eq!(zero, out[5]);
+        // The curve is 7 long, so only 4 points fit with a spacing of 2
+        assert_eq!(
+            4,
+            instance_transforms_along(&input, &output, &function, 5, 2., &mut out)
+        );
+
+        // Scalar curves going up and back down
+        let output = vec![0f32, 10., 0.];
+        let mut out = vec![0.; 12];
+        assert_eq!(
+            11,
+            instance_transforms_along(
+                &input,
+                &output,
+                &InterpolationFunction::Linear,
+                12,
+                2.,
+                &mut out
+            )
+        );
+        let expected = [0., 2., 4., 6., 8., 10., 8., 6., 4., 2., 0.];
+        for (point, expected) in out.iter().zip(&expected) {
+            assert!((point - expected).abs() < 1e-3);
+        }
+    }
+}
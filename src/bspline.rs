@@ -0,0 +1,198 @@
+use primitive::InterpolationPrimitive;
+
+/// Evaluate a B-spline with an arbitrary knot vector.
+///
+/// The value is the sum of the control points weighted by the B-spline basis functions of
+/// `degree`, which are computed from the knot vector using the Cox-de Boor recursion. With an
+/// open uniform knot vector, where the first and last knots are repeated `degree + 1` times, the
+/// curve starts at the first control point and ends at the last control point. The curve is
+/// defined for input values from `knots[degree]` to `knots[control_points.len()]`, input values
+/// outside of that range are clamped to it.
+///
+/// ## Parameters:
+///
+/// - `input`: the input value to the function
+/// - `knots`: non decreasing knot vector, of length `control_points.len() + degree + 1`
+/// - `control_points`: list of control points, at least `degree + 1`
+/// - `degree`: degree of the basis functions, e.g. 3 for a cubic B-spline
+/// - `normalize`: if true, normalize the interpolated value before returning it
+pub fn nurbs_like_interpolate<T>(
+    input: f32,
+    knots: &[f32],
+    control_points: &[T],
+    degree: usize,
+    normalize: bool,
+) -> T
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let (span, basis) = span_basis(input, knots, control_points.len(), degree);
+    let v = basis.iter().enumerate().skip(1).fold(
+        control_points[span - degree].mul(basis[0]),
+        |sum, (i, &b)| sum.add(&control_points[span - degree + i].mul(b)),
+    );
+    if normalize {
+        v.normalize()
+    } else {
+        v
+    }
+}
+
+/// Evaluate a rational B-spline (NURBS) with an arbitrary knot vector.
+///
+/// Like `nurbs_like_interpolate`, but each control point has a weight, and the weighted sum of
+/// the control points is divided by the weighted sum of the basis functions. This allows exact
+/// representation of circles and other conic sections. If the weighted sum of the basis functions
+/// is zero, which can only happen with non positive weights, the unweighted B-spline value is
+/// returned instead.
+///
+/// ## Parameters:
+///
+/// - `input`: the input value to the function
+/// - `knots`: non decreasing knot vector, of length `control_points.len() + degree + 1`
+/// - `control_points`: list of control points, at least `degree + 1`
+/// - `weights`: weight of each control point, the same size as `control_points`
+/// - `degree`: degree of the basis functions, e.g. 2 for conic sections
+/// - `normalize`: if true, normalize the interpolated value before returning it
+pub fn nurbs_interpolate<T>(
+    input: f32,
+    knots: &[f32],
+    control_points: &[T],
+    weights: &[f32],
+    degree: usize,
+    normalize: bool,
+) -> T
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let (span, basis) = span_basis(input, knots, control_points.len(), degree);
+    let first = span - degree;
+    let weight_sum: f32 = basis
+        .iter()
+        .enumerate()
+        .map(|(i, b)| b * weights[first + i])
+        .sum();
+    let weighted = if weight_sum.abs() > f32::EPSILON {
+        basis
+            .iter()
+            .map(|b| b / weight_sum)
+            .enumerate()
+            .map(|(i, b)| b * weights[first + i])
+            .collect::<Vec<_>>()
+    } else {
+        basis
+    };
+    let v = weighted
+        .iter()
+        .enumerate()
+        .skip(1)
+        .fold(control_points[first].mul(weighted[0]), |sum, (i, &b)| {
+            sum.add(&control_points[first + i].mul(b))
+        });
+    if normalize {
+        v.normalize()
+    } else {
+        v
+    }
+}
+
+// Index of the knot span containing `input`, clamped to the domain of the curve, and the values of
+// the `degree + 1` basis functions that are non zero in that span, starting with basis function
+// `span - degree`.
+pub(crate) fn span_basis(
+    input: f32,
+    knots: &[f32],
+    count: usize,
+    degree: usize,
+) -> (usize, Vec<f32>) {
+    let input = input.max(knots[degree]).min(knots[count]);
+    // Last non empty span starting at or before `input`
+    let span = (degree..count)
+        .rev()
+        .find(|&k| knots[k] <= input && knots[k] < knots[k + 1])
+        .unwrap_or(degree);
+    // Cox-de Boor recursion, computing the basis functions of increasing degree in place
+    let mut basis = vec![0.; degree + 1];
+    let mut left = vec![0.; degree + 1];
+    let mut right = vec![0.; degree + 1];
+    basis[0] = 1.;
+    for j in 1..=degree {
+        left[j] = input - knots[span + 1 - j];
+        right[j] = knots[span + j] - input;
+        let mut saved = 0.;
+        for r in 0..j {
+            let temp = basis[r] / (right[r + 1] + left[j - r]);
+            basis[r] = saved + right[r + 1] * temp;
+            saved = left[j - r] * temp;
+        }
+        basis[j] = saved;
+    }
+    (span, basis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mint::Vector2;
+
+    #[test]
+    fn test_open_uniform_endpoints() {
+        let knots = [0., 0., 0., 0., 1., 2., 2., 2., 2.];
+        let points = vec![
+            Vector2::from([0., 0.]),
+            Vector2::from([1., 2.]),
+            Vector2::from([2., -1.]),
+            Vector2::from([3., 3.]),
+            Vector2::from([4., 0.]),
+        ];
+        assert_eq!(
+            points[0],
+            nurbs_like_interpolate(0., &knots, &points, 3, false)
+        );
+        assert_eq!(
+            points[4],
+            nurbs_like_interpolate(2., &knots, &points, 3, false)
+        );
+        assert_eq!(
+            points[4],
+            nurbs_like_interpolate(3., &knots, &points, 3, false)
+        );
+        // The basis functions sum to one everywhere
+        for i in 0..=20 {
+            let (_, basis) = span_basis(i as f32 / 10., &knots, points.len(), 3);
+            assert!((basis.iter().sum::<f32>() - 1.).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_degree_one_is_linear() {
+        let knots = [0., 0., 1., 3., 3.];
+        let points = vec![0., 2., 1.];
+        assert_eq!(1., nurbs_like_interpolate(0.5, &knots, &points, 1, false));
+        assert_eq!(1.5, nurbs_like_interpolate(2., &knots, &points, 1, false));
+    }
+
+    #[test]
+    fn test_nurbs_circle() {
+        let s = ::std::f32::consts::FRAC_1_SQRT_2;
+        let knots = [0., 0., 0., 1., 1., 2., 2., 3., 3., 4., 4., 4.];
+        let points = vec![
+            Vector2::from([1., 0.]),
+            Vector2::from([1., 1.]),
+            Vector2::from([0., 1.]),
+            Vector2::from([-1., 1.]),
+            Vector2::from([-1., 0.]),
+            Vector2::from([-1., -1.]),
+            Vector2::from([0., -1.]),
+            Vector2::from([1., -1.]),
+            Vector2::from([1., 0.]),
+        ];
+        let weights = [1., s, 1., s, 1., s, 1., s, 1.];
+        for i in 0..=40 {
+            let p = nurbs_interpolate(i as f32 / 10., &knots, &points, &weights, 2, false);
+            assert!(((p.x * p.x + p.y * p.y).sqrt() - 1.).abs() < 1e-5);
+        }
+        let p = nurbs_interpolate(1., &knots, &points, &weights, 2, false);
+        assert!(p.x.abs() < 1e-6 && (p.y - 1.).abs() < 1e-6);
+    }
+}
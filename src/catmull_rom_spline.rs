@@ -1,6 +1,8 @@
 use cubic_spline::spline;
-use get_input_index;
+use easing::Easing;
+use get_uniform_input_index;
 use primitive::InterpolationPrimitive;
+use {resolve_segment, Segment};
 
 /// Catmull-Rom spline interpolation
 ///
@@ -10,18 +12,21 @@ use primitive::InterpolationPrimitive;
 /// `p1 = position at right keyframe`
 /// `k = left keyframe index`
 /// `k+1 = right keyframe index`
-/// `m0 = (p_k+1 - p_k-1) / (t_k+1 - t_k-1)`
-/// `m1 = (p_k+2 - p_k) / (t_k+2 - t_k)`
+/// `m0 = (p_k+1 - p_k-1) / (t_k+1 - t_k-1) * (t1 - t0)`
+/// `m1 = (p_k+2 - p_k) / (t_k+2 - t_k) * (t1 - t0)`
 /// `t0 = input at left keyframe`
 /// `t1 = input at right keyframe`
 ///
+/// Tangents are per-unit-time velocities, the same as for `cubic_spline_interpolate`, including
+/// `in_tangent_0` and `out_tangent_n`. They are scaled by the length of the segment before use.
+///
 /// ## Parameters:
 ///
 /// - `input`: the input value to the function
 /// - `inputs`: list of discrete input values for each keyframe
 /// - `outputs`: list of output values to interpolate between, for catmull rom spline interpolation
-///             this should be the size of `inputs` + 2
-///             `[ in_tangent_0, position_0, position_1, .., position_n, out_tangent_n ]`
+///   this should be the size of `inputs` + 2
+///   `[ in_tangent_0, position_0, position_1, .., position_n, out_tangent_n ]`
 /// - `normalize`: if true, normalize the interpolated value before returning it
 pub fn catmull_rom_spline_interpolate<T>(
     input: f32,
@@ -32,22 +37,179 @@ pub fn catmull_rom_spline_interpolate<T>(
 where
     T: InterpolationPrimitive + Clone,
 {
-    let input_index = match get_input_index(input, inputs) {
+    match resolve_segment(input, inputs) {
+        Segment::Before => outputs[1].clone(),
+        Segment::After => outputs[outputs.len() - 2].clone(),
+        Segment::In { index, t_local } => {
+            catmull_rom_spline_segment(t_local, index, inputs, outputs, normalize)
+        }
+    }
+}
+
+// Interpolate at `d` inside the segment starting at keyframe `index`.
+pub(crate) fn catmull_rom_spline_segment<T>(
+    d: f32,
+    index: usize,
+    inputs: &[f32],
+    outputs: &[T],
+    normalize: bool,
+) -> T
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let t_diff = inputs[index + 1] - inputs[index];
+    let v = spline(
+        d,
+        0.,
+        1.,
+        &outputs[index + 1],
+        &outputs[index + 2],
+        &catmull_tangent(index, inputs, outputs).mul(t_diff),
+        &catmull_tangent(index + 1, inputs, outputs).mul(t_diff),
+    );
+    if normalize {
+        v.normalize()
+    } else {
+        v
+    }
+}
+
+// Per-unit-time tangent at keyframe `index`.
+pub(crate) fn catmull_tangent<D>(index: usize, inputs: &[f32], outputs: &[D]) -> D
+where
+    D: InterpolationPrimitive + Clone,
+{
+    let output_index = index + 1;
+    if index == 0 {
+        outputs[0].clone()
+    } else if index == inputs.len() - 1 {
+        outputs[outputs.len() - 1].clone()
+    } else {
+        outputs[output_index + 1]
+            .sub(&outputs[output_index - 1])
+            .mul(1. / (inputs[index + 1] - inputs[index - 1]))
+    }
+}
+
+/// Catmull-Rom spline interpolation with adjustable smoothness.
+///
+/// The tangent of each segment is interpolated between the tangent that makes the segment a
+/// straight line, `p1 - p0`, and the Catmull-Rom tangent, by `strength`. A strength of 0 gives
+/// linear interpolation, and a strength of 1 gives Catmull-Rom spline interpolation. Unlike a
+/// cardinal spline with tension 1, which has zero tangents and eases in and out of every keyframe,
+/// the curve at strength 0 moves at constant speed inside each segment.
+///
+/// ## Parameters:
+///
+/// - `input`: the input value to the function
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values to interpolate between, laid out as for
+///   `catmull_rom_spline_interpolate`
+///   `[ in_tangent_0, position_0, position_1, .., position_n, out_tangent_n ]`
+/// - `strength`: smoothness of the curve, clamped to `[0, 1]`
+/// - `normalize`: if true, normalize the interpolated value before returning it
+pub fn auto_smooth_interpolate<T>(
+    input: f32,
+    inputs: &[f32],
+    outputs: &[T],
+    strength: f32,
+    normalize: bool,
+) -> T
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let (index, d) = match resolve_segment(input, inputs) {
+        Segment::Before => return outputs[1].clone(),
+        Segment::After => return outputs[outputs.len() - 2].clone(),
+        Segment::In { index, t_local } => (index, t_local),
+    };
+    let strength = strength.clamp(0., 1.);
+    let t_diff = inputs[index + 1] - inputs[index];
+    let (p0, p1) = (&outputs[index + 1], &outputs[index + 2]);
+    let linear = p1.sub(p0).mul(1. - strength);
+    let tangent = |i| linear.add(&catmull_tangent(i, inputs, outputs).mul(t_diff * strength));
+    let v = spline(d, 0., 1., p0, p1, &tangent(index), &tangent(index + 1));
+    if normalize {
+        v.normalize()
+    } else {
+        v
+    }
+}
+
+/// Catmull-Rom spline interpolation, with an easing applied inside each segment.
+///
+/// The position inside the segment, `d`, is passed through `ease` before the spline is evaluated,
+/// so the curve follows the same path as `catmull_rom_spline_interpolate`, with different timing
+/// between the keyframes, e.g. slowing down at every keyframe with `EaseKind::EaseInOut`. Any
+/// `Easing` can be used, including a `&LutEasing` for expensive easing functions.
+///
+/// ## Parameters:
+///
+/// - `input`: the input value to the function
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values to interpolate between, laid out as for
+///   `catmull_rom_spline_interpolate`
+///   `[ in_tangent_0, position_0, position_1, .., position_n, out_tangent_n ]`
+/// - `ease`: easing applied to the position inside each segment
+/// - `normalize`: if true, normalize the interpolated value before returning it
+pub fn eased_spline_interpolate<T, E>(
+    input: f32,
+    inputs: &[f32],
+    outputs: &[T],
+    ease: E,
+    normalize: bool,
+) -> T
+where
+    T: InterpolationPrimitive + Clone,
+    E: Easing,
+{
+    match resolve_segment(input, inputs) {
+        Segment::Before => outputs[1].clone(),
+        Segment::After => outputs[outputs.len() - 2].clone(),
+        Segment::In { index, t_local } => {
+            catmull_rom_spline_segment(ease.apply(t_local), index, inputs, outputs, normalize)
+        }
+    }
+}
+
+/// Catmull-Rom spline interpolation, for keyframes with uniformly spaced input values.
+///
+/// Same as `catmull_rom_spline_interpolate`, with the input value of keyframe `i` being
+/// `i * spacing`, without the need for an `inputs` list.
+///
+/// ## Parameters:
+///
+/// - `input`: the input value to the function
+/// - `outputs`: list of output values to interpolate between, laid out as for
+///   `catmull_rom_spline_interpolate`
+///   `[ in_tangent_0, position_0, position_1, .., position_n, out_tangent_n ]`
+/// - `spacing`: distance between the input values of two consecutive keyframes
+/// - `normalize`: if true, normalize the interpolated value before returning it
+pub fn catmull_rom_spline_interpolate_uniform<T>(
+    input: f32,
+    outputs: &[T],
+    spacing: f32,
+    normalize: bool,
+) -> T
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let count = outputs.len() - 2;
+    let input_index = match get_uniform_input_index(input, spacing, count) {
         Some(index) => index,
         None => return outputs[1].clone(),
     };
-    if input_index >= (inputs.len() - 1) {
+    if input_index >= (count - 1) {
         outputs[outputs.len() - 2].clone()
     } else {
-        let t_diff = inputs[input_index + 1] - inputs[input_index];
         let v = spline(
             input,
-            inputs[input_index],
-            t_diff,
+            input_index as f32 * spacing,
+            spacing,
             &outputs[input_index + 1],
             &outputs[input_index + 2],
-            &catmull_tangent(input_index, inputs, outputs),
-            &catmull_tangent(input_index + 1, inputs, outputs),
+            &uniform_catmull_tangent(input_index, spacing, outputs).mul(spacing),
+            &uniform_catmull_tangent(input_index + 1, spacing, outputs).mul(spacing),
         );
         if normalize {
             v.normalize()
@@ -57,25 +219,77 @@ where
     }
 }
 
-fn catmull_tangent<D>(index: usize, inputs: &[f32], outputs: &[D]) -> D
+fn uniform_catmull_tangent<D>(index: usize, spacing: f32, outputs: &[D]) -> D
 where
     D: InterpolationPrimitive + Clone,
 {
     let output_index = index + 1;
     if index == 0 {
         outputs[0].clone()
-    } else if index == inputs.len() - 1 {
+    } else if index == outputs.len() - 3 {
         outputs[outputs.len() - 1].clone()
     } else {
         outputs[output_index + 1]
             .sub(&outputs[output_index - 1])
-            .mul(1. / (inputs[index + 1] - inputs[index - 1]))
+            .mul(1. / (2. * spacing))
     }
 }
 
+/// Smooth interpolation, with tangents estimated from the keyframes.
+///
+/// Same as Catmull-Rom spline interpolation, but without the padding in `outputs`. The tangent at
+/// each keyframe is estimated using a centered finite difference of the neighbouring keyframes,
+/// and a one sided difference at the first and last keyframe.
+///
+/// ## Parameters:
+///
+/// - `input`: the input value to the function
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values to interpolate between, this should be the same size as
+///   `inputs`
+/// - `normalize`: if true, normalize the interpolated value before returning it
+pub fn smooth_interpolate<T>(input: f32, inputs: &[f32], outputs: &[T], normalize: bool) -> T
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let (index, d) = match resolve_segment(input, inputs) {
+        Segment::Before => return outputs[0].clone(),
+        Segment::After => return outputs[outputs.len() - 1].clone(),
+        Segment::In { index, t_local } => (index, t_local),
+    };
+    let t_diff = inputs[index + 1] - inputs[index];
+    let v = spline(
+        d,
+        0.,
+        1.,
+        &outputs[index],
+        &outputs[index + 1],
+        &estimated_tangent(index, inputs, outputs).mul(t_diff),
+        &estimated_tangent(index + 1, inputs, outputs).mul(t_diff),
+    );
+    if normalize {
+        v.normalize()
+    } else {
+        v
+    }
+}
+
+fn estimated_tangent<D>(index: usize, inputs: &[f32], outputs: &[D]) -> D
+where
+    D: InterpolationPrimitive,
+{
+    let left = index.saturating_sub(1);
+    let right = (index + 1).min(inputs.len() - 1);
+    outputs[right]
+        .sub(&outputs[left])
+        .mul(1. / (inputs[right] - inputs[left]))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use easing::{EaseKind, LutEasing};
+    use linear::linear_interpolate;
     use mint::{Quaternion, Vector3};
 
     #[test]
@@ -149,4 +363,167 @@ mod tests {
             catmull_rom_spline_interpolate(0.5, &input, &output, true)
         );
     }
+
+    #[test]
+    fn test_auto_smooth_strength() {
+        let input = vec![0., 1., 3., 4.];
+        let output = vec![[1., 0.], [0., 0.], [1., 2.], [3., 1.], [4., 4.], [0., 1.]];
+        let positions = &output[1..5];
+        for i in 0..=40 {
+            let t = i as f32 / 10.;
+            let linear = linear_interpolate(t, &input, positions, false);
+            let smooth = auto_smooth_interpolate(t, &input, &output, 0., false);
+            assert!(linear.sub(&smooth).magnitude() < 1e-5);
+            let catmull = catmull_rom_spline_interpolate(t, &input, &output, false);
+            let smooth = auto_smooth_interpolate(t, &input, &output, 1., false);
+            assert!(catmull.sub(&smooth).magnitude() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_eased_spline() {
+        let input = vec![0., 1., 3., 4.];
+        let output = vec![[1., 0.], [0., 0.], [1., 2.], [3., 1.], [4., 4.], [0., 1.]];
+        for i in 0..=40 {
+            let t = i as f32 / 10.;
+            assert_eq!(
+                catmull_rom_spline_interpolate(t, &input, &output, false),
+                eased_spline_interpolate(t, &input, &output, EaseKind::Linear, false)
+            );
+        }
+        // A quarter of the way into the segment from 1 to 3, ease in and out has moved 0.15625
+        let eased = eased_spline_interpolate(1.5, &input, &output, EaseKind::EaseInOut, false);
+        let expected = catmull_rom_spline_interpolate(1.3125, &input, &output, false);
+        assert!(eased.sub(&expected).magnitude() < 1e-5);
+        assert!(
+            eased
+                .sub(&catmull_rom_spline_interpolate(1.5, &input, &output, false))
+                .magnitude()
+                > 0.1
+        );
+        assert_eq!(
+            catmull_rom_spline_interpolate(2., &input, &output, false),
+            eased_spline_interpolate(2., &input, &output, EaseKind::EaseInOut, false)
+        );
+        let lut = LutEasing::new(|t| EaseKind::EaseInOut.apply(t), 64);
+        let lut_eased = eased_spline_interpolate(1.5, &input, &output, &lut, false);
+        assert!(eased.sub(&lut_eased).magnitude() < 1e-3);
+    }
+
+    #[test]
+    fn test_smooth_matches_catmull() {
+        let input = vec![0., 1., 2., 3., 4.];
+        let positions: Vec<f32> = vec![0., 1., 0., -1., 0.];
+        let mut padded = positions.clone();
+        padded.insert(0, positions[1] - positions[0]);
+        padded.push(positions[4] - positions[3]);
+        for i in 0..=40 {
+            let t = i as f32 / 10.;
+            assert!(
+                (smooth_interpolate(t, &input, &positions, false)
+                    - catmull_rom_spline_interpolate(t, &input, &padded, false))
+                .abs()
+                    < 1e-6
+            );
+        }
+    }
+
+    #[test]
+    fn test_smooth_keyframes() {
+        let input = vec![0., 0.5, 2., 2.25];
+        let output = vec![
+            Vector3::from([1., 0., 0.]),
+            Vector3::from([0., 2., 0.]),
+            Vector3::from([1., 0., -1.]),
+            Vector3::from([0., 0., 0.]),
+        ];
+        for (t, expected) in input.iter().zip(output.iter()) {
+            assert_eq!(*expected, smooth_interpolate(*t, &input, &output, false));
+        }
+    }
+
+    #[test]
+    fn test_catmull_uniform() {
+        let input = vec![0., 1., 2., 3., 4.];
+        let output = vec![
+            Vector3::from([1., 0., 0.]),
+            Vector3::from([0., 0., 0.]),
+            Vector3::from([1., 2., 0.]),
+            Vector3::from([0., 0., 1.]),
+            Vector3::from([-1., 0., 0.]),
+            Vector3::from([0., 1., 0.]),
+            Vector3::from([-1., 0., 0.]),
+        ];
+        for &t in &[-1., 0., 0.5, 1.25, 2., 2.7, 3.9, 4., 5.] {
+            let expected = catmull_rom_spline_interpolate(t, &input, &output, false);
+            let actual = catmull_rom_spline_interpolate_uniform(t, &output, 1., false);
+            assert!(expected.sub(&actual).magnitude() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_catmull_matches_cubic_tangents() {
+        use cubic_spline::cubic_spline_interpolate;
+
+        let input = vec![0., 0.5, 2., 2.25, 4.];
+        let output = vec![
+            Vector3::from([1., 0., 0.]),
+            Vector3::from([0., 0., 0.]),
+            Vector3::from([1., 2., 0.]),
+            Vector3::from([0., 0., 1.]),
+            Vector3::from([-1., 0., 0.]),
+            Vector3::from([0., 1., 0.]),
+            Vector3::from([0., -1., 2.]),
+        ];
+        let mut cubic = Vec::new();
+        for i in 0..input.len() {
+            let tangent = catmull_tangent(i, &input, &output);
+            cubic.push(tangent);
+            cubic.push(output[i + 1]);
+            cubic.push(tangent);
+        }
+        for i in 0..=40 {
+            let t = i as f32 / 10.;
+            let expected = cubic_spline_interpolate(t, &input, &cubic, false);
+            let actual = catmull_rom_spline_interpolate(t, &input, &output, false);
+            assert!(expected.sub(&actual).magnitude() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_sample_at_last_keyframe() {
+        use cubic_spline::cubic_spline_interpolate;
+
+        let input = vec![0., 1., 2.5];
+        let last = Vector3::from([3., -1., 2.]);
+        let output = vec![
+            Vector3::from([5., 5., 5.]),
+            Vector3::from([0., 0., 0.]),
+            Vector3::from([1., 2., 0.]),
+            last,
+            Vector3::from([7., 7., 7.]),
+        ];
+        assert_eq!(
+            last,
+            catmull_rom_spline_interpolate(2.5, &input, &output, false)
+        );
+        let near = catmull_rom_spline_interpolate(2.5 - 1e-4, &input, &output, false);
+        assert!(near.sub(&last).magnitude() < 1e-2);
+        assert_eq!(
+            last,
+            catmull_rom_spline_interpolate_uniform(2., &output, 1., false)
+        );
+        let cubic = vec![
+            Vector3::from([5., 5., 5.]),
+            Vector3::from([0., 0., 0.]),
+            Vector3::from([1., 0., 0.]),
+            Vector3::from([0., 1., 0.]),
+            last,
+            Vector3::from([7., 7., 7.]),
+        ];
+        assert_eq!(
+            last,
+            cubic_spline_interpolate(1., &input[..2], &cubic, false)
+        );
+    }
 }
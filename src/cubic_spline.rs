@@ -1,5 +1,10 @@
-use get_input_index;
+use catmull_rom_spline::catmull_tangent;
 use primitive::InterpolationPrimitive;
+use InterpolationFunction;
+use {resolve_segment, Segment};
+
+// Number of bisection steps used to find the curve parameter of weighted tangent segments.
+const WEIGHTED_TANGENT_ITERATIONS: usize = 32;
 
 /// Cubic Hermite spline interpolation
 ///
@@ -12,43 +17,244 @@ use primitive::InterpolationPrimitive;
 /// `t0 = input at left keyframe`
 /// `t1 = input at right keyframe`
 ///
+/// Tangents are per-unit-time velocities, they are scaled by the length of the segment before use.
+///
 /// ## Parameters:
 ///
 /// - `input`: the input value to the function
 /// - `inputs`: list of discrete input values for each keyframe
 /// - `outputs`: list of output values to interpolate between, for cubic spline interpolation this
-///             should three times the size of `inputs` and defined as
-///             `[ in_tangent_0, position_0, out_tangent_0, in_tangent_1, position_1, out_tangent_1, .. ]`
+///   should three times the size of `inputs` and defined as
+///   `[ in_tangent_0, position_0, out_tangent_0, in_tangent_1, position_1, out_tangent_1, .. ]`
 /// - `normalize`: if true, normalize the interpolated value before returning it
 pub fn cubic_spline_interpolate<T>(input: f32, inputs: &[f32], outputs: &[T], normalize: bool) -> T
 where
     T: InterpolationPrimitive + Clone,
 {
-    let input_index = match get_input_index(input, inputs) {
-        Some(index) => index,
-        None => return outputs[1].clone(),
-    };
-    if input_index >= (inputs.len() - 1) {
-        outputs[outputs.len() - 2].clone()
+    match resolve_segment(input, inputs) {
+        Segment::Before => outputs[1].clone(),
+        Segment::After => outputs[outputs.len() - 2].clone(),
+        Segment::In { index, t_local } => {
+            cubic_spline_segment(t_local, index, inputs, outputs, normalize)
+        }
+    }
+}
+
+// Interpolate at `d` inside the segment starting at keyframe `index`.
+pub(crate) fn cubic_spline_segment<T>(
+    d: f32,
+    index: usize,
+    inputs: &[f32],
+    outputs: &[T],
+    normalize: bool,
+) -> T
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let (p0, p1, m0, m1) = cubic_segment(index, inputs, outputs);
+    let v = spline(d, 0., 1., &p0, &p1, &m0, &m1);
+    if normalize {
+        v.normalize()
     } else {
-        let t_diff = inputs[input_index + 1] - inputs[input_index];
-        let left_index = input_index * 3;
-        let right_index = (input_index + 1) * 3;
-        let v = spline(
-            input,
-            inputs[input_index],
-            t_diff,
-            &outputs[left_index + 1],
-            &outputs[right_index + 1],
-            &outputs[left_index + 2].mul(t_diff),
-            &outputs[right_index].mul(t_diff),
+        v
+    }
+}
+
+/// Compute a periodic natural cubic spline through a closed sequence of points.
+///
+/// The spline is C2 continuous everywhere, including across the seam where the curve closes. The
+/// second derivatives at the keyframes are found by solving the cyclic tridiagonal system of the
+/// natural cubic spline, where the last point is adjacent to the first.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of points, the same size as `inputs`, the last point must be equal to the
+///   first point to close the curve
+///
+/// ## Returns
+///
+/// Output data for use with `cubic_spline_interpolate`, three times the size of `inputs`.
+pub fn periodic_cubic_spline<T>(inputs: &[f32], outputs: &[T]) -> Vec<T>
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let m = inputs.len() - 1;
+    if m == 0 {
+        return vec![outputs[0].mul(0.), outputs[0].clone(), outputs[0].mul(0.)];
+    }
+    let h = (0..m)
+        .map(|i| inputs[i + 1] - inputs[i])
+        .collect::<Vec<_>>();
+    let slope = |i: usize| outputs[i + 1].sub(&outputs[i]).mul(1. / h[i]);
+    let prev = |i: usize| (i + m - 1) % m;
+    let second_derivatives = if m == 1 {
+        vec![outputs[0].mul(0.)]
+    } else {
+        let lower = (0..m).map(|i| h[prev(i)]).collect::<Vec<_>>();
+        let diagonal = (0..m).map(|i| 2. * (h[prev(i)] + h[i])).collect::<Vec<_>>();
+        let upper = h.clone();
+        let rhs = (0..m)
+            .map(|i| slope(i).sub(&slope(prev(i))).mul(6.))
+            .collect::<Vec<_>>();
+        solve_cyclic_tridiagonal(&lower, &diagonal, &upper, rhs)
+    };
+    let mut cubic = Vec::with_capacity(inputs.len() * 3);
+    for (i, point) in outputs.iter().enumerate() {
+        let k = i % m;
+        let next = (k + 1) % m;
+        let tangent = slope(k).sub(
+            &second_derivatives[k]
+                .mul(2.)
+                .add(&second_derivatives[next])
+                .mul(h[k] / 6.),
         );
-        if normalize {
-            v.normalize()
+        cubic.push(tangent.clone());
+        cubic.push(point.clone());
+        cubic.push(tangent);
+    }
+    cubic
+}
+
+/// Convert linear interpolation data to cubic spline data, keeping the keyframe values.
+///
+/// Without smoothing, the tangents on either side of each keyframe are the slopes of the adjacent
+/// linear segments, so the cubic curve is exactly the same as the linear curve, and can be edited
+/// further as a cubic curve. With smoothing, both tangents of each keyframe are the average of the
+/// slopes of the segments before and after it, which makes the curve smooth through the keyframes.
+/// The first and last keyframes use the slope of their only segment in both cases.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, the same size as `inputs`
+/// - `smooth`: if true, average the tangents on either side of each keyframe
+///
+/// ## Returns
+///
+/// Output data for use with `cubic_spline_interpolate`, three times the size of `inputs`.
+pub fn elevate_to_cubic<T>(inputs: &[f32], outputs: &[T], smooth: bool) -> Vec<T>
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let slopes = (0..inputs.len().saturating_sub(1))
+        .map(|i| {
+            outputs[i + 1]
+                .sub(&outputs[i])
+                .mul(1. / (inputs[i + 1] - inputs[i]))
+        })
+        .collect::<Vec<_>>();
+    let zero = outputs[0].mul(0.);
+    let mut cubic = Vec::with_capacity(inputs.len() * 3);
+    for (i, point) in outputs.iter().enumerate() {
+        let incoming = slopes.get(i.wrapping_sub(1)).or_else(|| slopes.get(i));
+        let outgoing = slopes.get(i).or(incoming);
+        let incoming = incoming.unwrap_or(&zero);
+        let outgoing = outgoing.unwrap_or(&zero);
+        if smooth {
+            let tangent = incoming.add(outgoing).mul(0.5);
+            cubic.push(tangent.clone());
+            cubic.push(point.clone());
+            cubic.push(tangent);
         } else {
-            v
+            cubic.push(incoming.clone());
+            cubic.push(point.clone());
+            cubic.push(outgoing.clone());
         }
     }
+    cubic
+}
+
+/// Make cubic spline data C1 continuous, by giving each interior keyframe a single tangent.
+///
+/// Segments that are authored independently often have different in and out tangents at the
+/// keyframe they share, which makes the velocity jump there. The in and out tangents of each
+/// interior keyframe are replaced by their average, weighted by the length of the segment each
+/// tangent belongs to, so the tangent of the longer segment changes less. Note that this changes
+/// the shape of the curve near every keyframe where the tangents differed. The tangents of the
+/// first and last keyframes are not changed.
+///
+/// ## Parameters:
+///
+/// - `outputs`: cubic spline data, as expected by `cubic_spline_interpolate`, modified in place
+/// - `inputs`: list of discrete input values for each keyframe
+pub fn enforce_c1<T>(outputs: &mut [T], inputs: &[f32])
+where
+    T: InterpolationPrimitive + Clone,
+{
+    for i in 1..inputs.len().saturating_sub(1) {
+        let left = inputs[i] - inputs[i - 1];
+        let right = inputs[i + 1] - inputs[i];
+        let tangent = outputs[i * 3]
+            .mul(left)
+            .add(&outputs[i * 3 + 2].mul(right))
+            .mul(1. / (left + right));
+        outputs[i * 3] = tangent.clone();
+        outputs[i * 3 + 2] = tangent;
+    }
+}
+
+// Solve a cyclic tridiagonal system, where `lower[0]` is the top right corner and `upper[n - 1]`
+// is the bottom left corner, using the Sherman-Morrison formula.
+fn solve_cyclic_tridiagonal<T>(
+    lower: &[f32],
+    diagonal: &[f32],
+    upper: &[f32],
+    rhs: Vec<T>,
+) -> Vec<T>
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let n = diagonal.len();
+    let top_right = lower[0];
+    let bottom_left = upper[n - 1];
+    if n == 2 {
+        // Both neighbours of each row are the same unknown
+        let (a, b) = (diagonal[0], upper[0] + lower[0]);
+        let (c, d) = (upper[1] + lower[1], diagonal[1]);
+        let det = a * d - b * c;
+        return vec![
+            rhs[0].mul(d).sub(&rhs[1].mul(b)).mul(1. / det),
+            rhs[1].mul(a).sub(&rhs[0].mul(c)).mul(1. / det),
+        ];
+    }
+    let gamma = -diagonal[0];
+    let mut modified = diagonal.to_vec();
+    modified[0] -= gamma;
+    modified[n - 1] -= bottom_left * top_right / gamma;
+    let x = solve_tridiagonal(lower, &modified, upper, rhs);
+    let mut u = vec![0.; n];
+    u[0] = gamma;
+    u[n - 1] = bottom_left;
+    let z = solve_tridiagonal(lower, &modified, upper, u);
+    let factor = x[0]
+        .add(&x[n - 1].mul(top_right / gamma))
+        .mul(1. / (1. + z[0] + top_right * z[n - 1] / gamma));
+    x.iter()
+        .zip(z.iter())
+        .map(|(x, &z)| x.sub(&factor.mul(z)))
+        .collect()
+}
+
+// Solve a tridiagonal system using the Thomas algorithm, ignoring `lower[0]` and `upper[n - 1]`.
+fn solve_tridiagonal<T>(lower: &[f32], diagonal: &[f32], upper: &[f32], rhs: Vec<T>) -> Vec<T>
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let n = diagonal.len();
+    let mut c = vec![0.; n];
+    let mut d = rhs;
+    c[0] = upper[0] / diagonal[0];
+    d[0] = d[0].mul(1. / diagonal[0]);
+    for i in 1..n {
+        let denominator = diagonal[i] - lower[i] * c[i - 1];
+        c[i] = upper[i] / denominator;
+        d[i] = d[i].sub(&d[i - 1].mul(lower[i])).mul(1. / denominator);
+    }
+    for i in (0..n - 1).rev() {
+        d[i] = d[i].sub(&d[i + 1].mul(c[i]));
+    }
+    d
 }
 
 #[inline]
@@ -64,3 +270,408 @@ where
         .add(&p1.mul(-2. * t3 + 3. * t2))
         .add(&m1.mul(t3 - t2))
 }
+
+/// Derivative of `spline` with regards to `t`.
+#[inline]
+pub(crate) fn spline_derivative<D>(
+    t: f32,
+    left_t: f32,
+    t_diff: f32,
+    p0: &D,
+    p1: &D,
+    m0: &D,
+    m1: &D,
+) -> D
+where
+    D: InterpolationPrimitive,
+{
+    let t = (t - left_t) / t_diff;
+    let t2 = t * t;
+    p0.mul(6. * t2 - 6. * t)
+        .add(&m0.mul(3. * t2 - 4. * t + 1.))
+        .add(&p1.mul(-6. * t2 + 6. * t))
+        .add(&m1.mul(3. * t2 - 2. * t))
+        .mul(1. / t_diff)
+}
+
+/// Second derivative of `spline` with regards to `t`.
+#[inline]
+pub(crate) fn spline_second_derivative<D>(
+    t: f32,
+    left_t: f32,
+    t_diff: f32,
+    p0: &D,
+    p1: &D,
+    m0: &D,
+    m1: &D,
+) -> D
+where
+    D: InterpolationPrimitive,
+{
+    let t = (t - left_t) / t_diff;
+    p0.mul(12. * t - 6.)
+        .add(&m0.mul(6. * t - 4.))
+        .add(&p1.mul(-12. * t + 6.))
+        .add(&m1.mul(6. * t - 2.))
+        .mul(1. / (t_diff * t_diff))
+}
+
+/// Third derivative of `spline` with regards to `t`, which is constant inside the segment.
+#[inline]
+pub(crate) fn spline_third_derivative<D>(t_diff: f32, p0: &D, p1: &D, m0: &D, m1: &D) -> D
+where
+    D: InterpolationPrimitive,
+{
+    p0.mul(12.)
+        .add(&m0.mul(6.))
+        .add(&p1.mul(-12.))
+        .add(&m1.mul(6.))
+        .mul(1. / (t_diff * t_diff * t_diff))
+}
+
+/// Get the Hermite control data of a segment, for evaluating the curve elsewhere, e.g. on the GPU.
+///
+/// The segment is evaluated with the formula of `cubic_spline_interpolate`, using the returned
+/// `(p0, p1, m0, m1)`, where the tangents are already scaled by the length of the segment:
+///
+/// `f(t) = (2d^3 + 3d^2 + 1)p0 + (d^3 - 2d^2 + d)m0 + (-2d^3 + 3d^2)p1 + (d^3 - d^2)m1`
+/// `d = (t - t0) / (t1 - t0)`
+///
+/// For Catmull-Rom spline interpolation the tangents are computed from the neighbouring
+/// keyframes, for cubic spline interpolation the stored tangents are used. Linear and step
+/// interpolation are represented exactly, the remaining functions are approximated by linear
+/// interpolation.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `segment_index`: index of the segment, i.e. of the keyframe the segment starts at
+pub fn segment_data<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    segment_index: usize,
+) -> (T, T, T, T)
+where
+    T: InterpolationPrimitive + Clone,
+{
+    if let Some(data) = hermite_segment(segment_index, inputs, outputs, function) {
+        return data;
+    }
+    let p0 = outputs[segment_index].clone();
+    match *function {
+        InterpolationFunction::Step => (p0.clone(), p0.clone(), p0.mul(0.), p0.mul(0.)),
+        _ => {
+            let p1 = outputs[segment_index + 1].clone();
+            let m = p1.sub(&p0);
+            (p0, p1, m.clone(), m)
+        }
+    }
+}
+
+/// Evaluate a cubic Hermite segment with separately weighted tangents, as with weighted tangents
+/// in animation tools.
+///
+/// Same formula as `cubic_spline_interpolate`, with `m0` multiplied by `in_weight` and `m1` by
+/// `out_weight`. A weight above 1 makes the curve follow the tangent longer, so it bulges towards
+/// the tangent near that end of the segment, a weight below 1 flattens the curve there. Weights
+/// of 1 give the standard cubic Hermite segment.
+///
+/// `f(d) = (2d^3 - 3d^2 + 1)p0 + (d^3 - 2d^2 + d)w0m0 + (-2d^3 + 3d^2)p1 + (d^3 - d^2)w1m1`
+///
+/// ## Parameters:
+///
+/// - `d`: position inside the segment, from 0 at the start to 1 at the end
+/// - `p0`, `p1`: values at the start and end of the segment
+/// - `m0`, `m1`: tangents at the start and end of the segment, scaled by the length of the
+///   segment, as returned by `segment_data`
+/// - `in_weight`: weight `w0` of the tangent at the start of the segment
+/// - `out_weight`: weight `w1` of the tangent at the end of the segment
+pub fn weighted_spline<T>(
+    d: f32,
+    p0: &T,
+    p1: &T,
+    m0: &T,
+    m1: &T,
+    in_weight: f32,
+    out_weight: f32,
+) -> T
+where
+    T: InterpolationPrimitive,
+{
+    spline(d, 0., 1., p0, p1, &m0.mul(in_weight), &m1.mul(out_weight))
+}
+
+/// Cubic spline interpolation with weighted tangents, as in FBX and some glTF extensions.
+///
+/// Each segment is a cubic Bezier curve in (input, value) space. The control points are placed
+/// along the stored tangents, at a fraction of the length of the segment given by the weight of
+/// the tangent, so a tangent has both a direction and an extent in time. The curve is evaluated by
+/// solving for the Bezier parameter where the time of the curve equals `input`. Weights are
+/// clamped to `[0, 1]`, which keeps time monotonic inside each segment. Weights of `1 / 3` on both
+/// ends of a segment give the same curve as `cubic_spline_interpolate`.
+///
+/// ## Parameters:
+///
+/// - `input`: the input value to the function
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as for `cubic_spline_interpolate`
+/// - `weights`: `[in_weight, out_weight]` of the tangents of each keyframe, the same size as
+///   `inputs`
+/// - `normalize`: if true, normalize the interpolated value before returning it
+pub fn weighted_tangent_interpolate<T>(
+    input: f32,
+    inputs: &[f32],
+    outputs: &[T],
+    weights: &[[f32; 2]],
+    normalize: bool,
+) -> T
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let (index, d) = match resolve_segment(input, inputs) {
+        Segment::Before => return outputs[1].clone(),
+        Segment::After => return outputs[outputs.len() - 2].clone(),
+        Segment::In { index, t_local } => (index, t_local),
+    };
+    let (p0, p1, m0, m1) = cubic_segment(index, inputs, outputs);
+    let w0 = weights[index][1].clamp(0., 1.);
+    let w1 = weights[index + 1][0].clamp(0., 1.);
+    let bezier = |a: f32, b: f32, c: f32, d: f32, s: f32| {
+        let r = 1. - s;
+        r * r * r * a + 3. * r * r * s * b + 3. * r * s * s * c + s * s * s * d
+    };
+    // Bezier parameter where the time of the curve, relative to the segment, equals `d`
+    let (mut low, mut high) = (0., 1.);
+    for _ in 0..WEIGHTED_TANGENT_ITERATIONS {
+        let middle = (low + high) / 2.;
+        if bezier(0., w0, 1. - w1, 1., middle) < d {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    let s = (low + high) / 2.;
+    let r = 1. - s;
+    let c0 = p0.add(&m0.mul(w0));
+    let c1 = p1.sub(&m1.mul(w1));
+    let v = p0
+        .mul(r * r * r)
+        .add(&c0.mul(3. * r * r * s))
+        .add(&c1.mul(3. * r * s * s))
+        .add(&p1.mul(s * s * s));
+    if normalize {
+        v.normalize()
+    } else {
+        v
+    }
+}
+
+/// Hermite control data `(p0, p1, m0, m1)` for the segment starting at keyframe `index`, with
+/// tangents scaled to the segment, as expected by `spline`.
+///
+/// Returns `None` if `function` is not a spline function.
+pub(crate) fn hermite_segment<T>(
+    index: usize,
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+) -> Option<(T, T, T, T)>
+where
+    T: InterpolationPrimitive + Clone,
+{
+    match *function {
+        InterpolationFunction::CubicSpline => Some(cubic_segment(index, inputs, outputs)),
+        InterpolationFunction::CatmullRomSpline => {
+            let t_diff = inputs[index + 1] - inputs[index];
+            Some((
+                outputs[index + 1].clone(),
+                outputs[index + 2].clone(),
+                catmull_tangent(index, inputs, outputs).mul(t_diff),
+                catmull_tangent(index + 1, inputs, outputs).mul(t_diff),
+            ))
+        }
+        _ => None,
+    }
+}
+
+fn cubic_segment<T>(index: usize, inputs: &[f32], outputs: &[T]) -> (T, T, T, T)
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let t_diff = inputs[index + 1] - inputs[index];
+    let left_index = index * 3;
+    let right_index = (index + 1) * 3;
+    (
+        outputs[left_index + 1].clone(),
+        outputs[right_index + 1].clone(),
+        outputs[left_index + 2].mul(t_diff),
+        outputs[right_index].mul(t_diff),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use derivative::{derivative, second_derivative};
+    use mint::Vector2;
+
+    #[test]
+    fn test_periodic_seam_continuity() {
+        let input = vec![0., 1., 2.5, 3., 4.5];
+        let output = vec![
+            Vector2::from([1., 0.]),
+            Vector2::from([0., 1.]),
+            Vector2::from([-1., 0.5]),
+            Vector2::from([-0.5, -1.]),
+            Vector2::from([1., 0.]),
+        ];
+        let cubic = periodic_cubic_spline(&input, &output);
+        assert_eq!(15, cubic.len());
+        let function = InterpolationFunction::CubicSpline;
+        for (i, &t) in input.iter().enumerate() {
+            let value = cubic_spline_interpolate(t, &input, &cubic, false);
+            assert!(value.sub(&output[i]).magnitude() < 1e-5);
+        }
+        // Compare the start of the curve with the end of the curve
+        let (start, end) = (input[0], input[4] - 1e-6);
+        let d_start = derivative(start, &input, &cubic, &function);
+        let d_end = derivative(end, &input, &cubic, &function);
+        assert!(d_start.sub(&d_end).magnitude() < 1e-3);
+        let dd_start = second_derivative(start, &input, &cubic, &function);
+        let dd_end = second_derivative(end, &input, &cubic, &function);
+        assert!(dd_start.sub(&dd_end).magnitude() < 1e-3);
+        // Interior keyframes are C2 as well
+        for &t in &input[1..4] {
+            let left = second_derivative(t - 1e-6, &input, &cubic, &function);
+            let right = second_derivative(t, &input, &cubic, &function);
+            assert!(left.sub(&right).magnitude() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_segment_data_catmull_rom() {
+        use catmull_rom_spline::catmull_rom_spline_interpolate;
+
+        let input = vec![0., 0.5, 2., 3.];
+        let output = vec![
+            Vector2::from([1., 0.]),
+            Vector2::from([0., 0.]),
+            Vector2::from([1., 2.]),
+            Vector2::from([-1., 1.]),
+            Vector2::from([0., 0.]),
+            Vector2::from([0., 1.]),
+        ];
+        let function = InterpolationFunction::CatmullRomSpline;
+        for segment in 0..3 {
+            let (p0, p1, m0, m1) = segment_data(&input, &output, &function, segment);
+            let (t0, t1) = (input[segment], input[segment + 1]);
+            for i in 0..=10 {
+                let t = t0 + (t1 - t0) * i as f32 / 10.;
+                let value = spline(t, t0, t1 - t0, &p0, &p1, &m0, &m1);
+                let expected = catmull_rom_spline_interpolate(t, &input, &output, false);
+                assert!(value.sub(&expected).magnitude() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn test_segment_data_step() {
+        let input = vec![0., 1.];
+        let output = vec![2., 3.];
+        let (p0, p1, m0, m1) = segment_data(&input, &output, &InterpolationFunction::Step, 0);
+        assert_eq!(2., spline(0.7, 0., 1., &p0, &p1, &m0, &m1));
+    }
+
+    #[test]
+    fn test_weighted_spline() {
+        let input = vec![0., 2.];
+        let output = vec![0., 0., 1., -1., 1., 0.];
+        let (p0, p1, m0, m1) =
+            segment_data(&input, &output, &InterpolationFunction::CubicSpline, 0);
+        for i in 0..=10 {
+            let d = i as f32 / 10.;
+            let expected = cubic_spline_interpolate(d * 2., &input, &output, false);
+            let value: f32 = weighted_spline(d, &p0, &p1, &m0, &m1, 1., 1.);
+            assert!((value - expected).abs() < 1e-6);
+        }
+        // A heavier tangent at the start pulls the curve up, mostly near the start
+        let standard = |d| weighted_spline(d, &p0, &p1, &m0, &m1, 1., 1.);
+        let weighted = |d| weighted_spline(d, &p0, &p1, &m0, &m1, 2., 1.);
+        assert_eq!(standard(0.), weighted(0.));
+        assert_eq!(standard(1.), weighted(1.));
+        assert!(weighted(0.25) - standard(0.25) > weighted(0.75) - standard(0.75));
+        assert!(weighted(0.75) > standard(0.75));
+    }
+
+    #[test]
+    fn test_weighted_tangent_interpolate() {
+        let input = vec![0., 1., 3.];
+        let output = vec![0f32, 0., 2., -1., 1., 0.5, 1., 2., 0.];
+        let third = 1. / 3.;
+        let even = vec![[third, third]; 3];
+        let heavy = vec![[third, 0.8], [0.8, third], [third, third]];
+        for i in 0..=30 {
+            let t = i as f32 / 10.;
+            let standard = cubic_spline_interpolate(t, &input, &output, false);
+            let weighted = weighted_tangent_interpolate(t, &input, &output, &even, false);
+            assert!((standard - weighted).abs() < 1e-4);
+        }
+        // Heavier tangents pull the curve further along the out tangent of the first keyframe
+        let standard = cubic_spline_interpolate(0.25, &input, &output, false);
+        let weighted = weighted_tangent_interpolate(0.25, &input, &output, &heavy, false);
+        assert!(weighted > standard + 0.05);
+        assert_eq!(
+            1.,
+            weighted_tangent_interpolate(1., &input, &output, &heavy, false)
+        );
+    }
+
+    #[test]
+    fn test_enforce_c1() {
+        let input = vec![0., 1., 3., 4.];
+        let mut output = vec![0f32, 0., 1., -1., 1., 2., 0.5, 0., 0., 1., 2., 0.];
+        enforce_c1(&mut output, &input);
+        assert_eq!((1., 1.), (output[3], output[5]));
+        assert_eq!(output[6], output[8]);
+        assert!((output[6] - 1. / 3.).abs() < 1e-6);
+        assert_eq!((1., 1.), (output[2], output[9]));
+        let function = InterpolationFunction::CubicSpline;
+        for &t in &input[1..3] {
+            let left = derivative(t - 1e-4, &input, &output, &function);
+            let right = derivative(t + 1e-4, &input, &output, &function);
+            assert!((left - right).abs() < 1e-2);
+        }
+    }
+
+    #[test]
+    fn test_elevate_to_cubic() {
+        use linear::linear_interpolate;
+
+        let input = vec![0., 1., 3., 4.];
+        let output = vec![
+            Vector2::from([0., 0.]),
+            Vector2::from([1., 2.]),
+            Vector2::from([2., -1.]),
+            Vector2::from([0., 0.5]),
+        ];
+        let exact = elevate_to_cubic(&input, &output, false);
+        let smooth = elevate_to_cubic(&input, &output, true);
+        assert_eq!(12, smooth.len());
+        for (&t, value) in input.iter().zip(&output) {
+            assert_eq!(*value, cubic_spline_interpolate(t, &input, &smooth, false));
+            assert_eq!(*value, cubic_spline_interpolate(t, &input, &exact, false));
+        }
+        for i in 0..=40 {
+            let t = i as f32 / 10.;
+            let linear = linear_interpolate(t, &input, &output, false);
+            let cubic = cubic_spline_interpolate(t, &input, &exact, false);
+            assert!(linear.sub(&cubic).magnitude() < 1e-5);
+        }
+        // Smoothed tangents are continuous through the keyframes
+        assert_eq!(smooth[3], smooth[5]);
+        assert_eq!(Vector2::from([0.75, 0.25]), smooth[3]);
+    }
+}
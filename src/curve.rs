@@ -0,0 +1,1586 @@
+use cubic_spline::{hermite_segment, spline_derivative};
+use derivative::{derivative, one_sided_derivatives};
+use error::InterpolationError;
+use extrapolation::Extrapolation;
+use linear::lerp_pair;
+use mint::Vector3;
+use primitive::InterpolationPrimitive;
+use resample::resample_with_tolerance;
+use sample_range;
+use std::ops::Range;
+use time_remap::TimeRemap;
+use InterpolationFunction;
+
+/// A keyframed curve, bundling the keyframe data with the function used to interpolate it.
+///
+/// The layout of `outputs` depends on `function`, see the documentation of the individual
+/// interpolation functions.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Curve<T>
+where
+    T: InterpolationPrimitive,
+{
+    /// Input value of each keyframe
+    pub inputs: Vec<f32>,
+    /// Output values, laid out as expected by `function`
+    pub outputs: Vec<T>,
+    /// Interpolation function
+    pub function: InterpolationFunction<T>,
+    /// If true, normalize sampled values
+    pub normalize: bool,
+    /// Mapping applied to input values when sampling the curve, the identity by default
+    ///
+    /// Only sampling through the methods of `Curve` is affected, functions operating on the
+    /// keyframes, like `split_at` or `retime`, work on the input values of the keyframes.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub time_remap: TimeRemap,
+}
+
+impl<T> Curve<T>
+where
+    T: InterpolationPrimitive + Clone,
+{
+    /// Create a new curve
+    pub fn new(
+        inputs: Vec<f32>,
+        outputs: Vec<T>,
+        function: InterpolationFunction<T>,
+        normalize: bool,
+    ) -> Self {
+        Curve {
+            inputs,
+            outputs,
+            function,
+            normalize,
+            time_remap: TimeRemap::default(),
+        }
+    }
+
+    /// Sample the curve at the given input value
+    pub fn sample(&self, input: f32) -> T {
+        self.sample_with(input, None)
+    }
+
+    /// Sample the curve at the given input value, overriding the normalize flag of the curve with
+    /// `normalize` if it is `Some`
+    pub fn sample_with(&self, input: f32, normalize: Option<bool>) -> T {
+        self.function.interpolate(
+            self.time_remap.apply(input),
+            &self.inputs,
+            &self.outputs,
+            normalize.unwrap_or(self.normalize),
+        )
+    }
+
+    /// Sample the curve at the given input value, using `extrapolation` for input values outside
+    /// of the range of the keyframes
+    pub fn sample_extrapolated(&self, input: f32, extrapolation: Extrapolation) -> T {
+        let input = extrapolation.apply(
+            self.time_remap.apply(input),
+            self.inputs[0],
+            self.inputs[self.inputs.len() - 1],
+        );
+        self.function
+            .interpolate(input, &self.inputs, &self.outputs, self.normalize)
+    }
+
+    /// Split the curve in two at `input`, see `split_at`.
+    pub fn split_at(&self, input: f32) -> (Curve<T>, Curve<T>) {
+        let (mut left, mut right) = split_at(&self.inputs, &self.outputs, &self.function, input);
+        left.normalize = self.normalize;
+        right.normalize = self.normalize;
+        (left, right)
+    }
+
+    /// Iterate over the `(input, value)` pairs of the keyframes of the curve.
+    ///
+    /// Only the values of the keyframes are yielded, the tangents of cubic spline interpolation
+    /// and the extra control points of Catmull-Rom spline interpolation are skipped.
+    pub fn keyframes<'a>(&'a self) -> impl Iterator<Item = (f32, &'a T)> + 'a {
+        self.inputs.iter().enumerate().map(move |(i, &input)| {
+            (
+                input,
+                &self.outputs[keyframe_output_index(&self.function, i)],
+            )
+        })
+    }
+
+    /// Set the value of keyframe `index`, leaving its input and any tangents as they are.
+    ///
+    /// The value is written to the right place in `outputs` for the layout of the interpolation
+    /// function. Panics if `index` is out of bounds.
+    pub fn set_keyframe_value(&mut self, index: usize, value: T) {
+        assert!(index < self.inputs.len(), "keyframe index out of bounds");
+        let output_index = keyframe_output_index(&self.function, index);
+        self.outputs[output_index] = value;
+    }
+
+    /// Encode the curve in a compact binary format, that can be decoded with `from_bytes`.
+    ///
+    /// The data starts with a header of `BINARY_HEADER_LEN` bytes: the magic bytes `MNTC`, the
+    /// format version, the function tag, the normalize flag, the number of components per output
+    /// value, and the number of keyframes and output values as little endian `u32`. The inputs
+    /// and the components of the outputs follow as little endian `f32`. The time remap is not
+    /// encoded, decoded curves use the identity.
+    ///
+    /// Panics if the interpolation function is `Function`, which can not be encoded, or if `T` does
+    /// not expose its components, see `InterpolationPrimitive::component_count`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let tag = match self.function {
+            InterpolationFunction::Linear => 0,
+            InterpolationFunction::SphericalLinear => 1,
+            InterpolationFunction::QuasiSphericalLinear => 2,
+            InterpolationFunction::Step => 3,
+            InterpolationFunction::CatmullRomSpline => 4,
+            InterpolationFunction::CubicSpline => 5,
+            InterpolationFunction::Function(_) => panic!("Can not encode a generic function"),
+        };
+        let components = T::component_count();
+        assert!(components > 0, "Can not encode a type without components");
+        let mut bytes = Vec::with_capacity(
+            BINARY_HEADER_LEN + 4 * (self.inputs.len() + self.outputs.len() * components),
+        );
+        bytes.extend_from_slice(BINARY_MAGIC);
+        bytes.push(BINARY_VERSION);
+        bytes.push(tag);
+        bytes.push(self.normalize as u8);
+        bytes.push(components as u8);
+        bytes.extend_from_slice(&(self.inputs.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&(self.outputs.len() as u32).to_le_bytes());
+        for input in &self.inputs {
+            bytes.extend_from_slice(&input.to_le_bytes());
+        }
+        let mut buffer = vec![0.; components];
+        for output in &self.outputs {
+            output.write_components(&mut buffer);
+            for c in &buffer {
+                bytes.extend_from_slice(&c.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    /// Decode a curve encoded with `to_bytes`.
+    ///
+    /// Returns `InvalidBinaryData` if the header does not match the format or the output type, if
+    /// the length of the data does not match the header, or if `T` does not expose its components.
+    /// The decoded keyframes are checked with `InterpolationFunction::validate`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, InterpolationError> {
+        if bytes.len() < BINARY_HEADER_LEN
+            || &bytes[0..4] != BINARY_MAGIC
+            || bytes[4] != BINARY_VERSION
+            || bytes[6] > 1
+            || bytes[7] as usize != T::component_count()
+            || T::component_count() == 0
+        {
+            return Err(InterpolationError::InvalidBinaryData);
+        }
+        let function = match bytes[5] {
+            0 => InterpolationFunction::Linear,
+            1 => InterpolationFunction::SphericalLinear,
+            2 => InterpolationFunction::QuasiSphericalLinear,
+            3 => InterpolationFunction::Step,
+            4 => InterpolationFunction::CatmullRomSpline,
+            5 => InterpolationFunction::CubicSpline,
+            _ => return Err(InterpolationError::InvalidBinaryData),
+        };
+        let read_u32 = |offset: usize| {
+            let mut word = [0; 4];
+            word.copy_from_slice(&bytes[offset..offset + 4]);
+            u32::from_le_bytes(word) as usize
+        };
+        let input_count = read_u32(8);
+        let output_count = read_u32(12);
+        let components = T::component_count();
+        let expected_len = output_count
+            .checked_mul(components)
+            .and_then(|len| len.checked_add(input_count))
+            .and_then(|len| len.checked_mul(4))
+            .and_then(|len| len.checked_add(BINARY_HEADER_LEN));
+        if expected_len != Some(bytes.len()) {
+            return Err(InterpolationError::InvalidBinaryData);
+        }
+        let values = bytes[BINARY_HEADER_LEN..]
+            .chunks(4)
+            .map(|chunk| {
+                let mut word = [0; 4];
+                word.copy_from_slice(chunk);
+                f32::from_le_bytes(word)
+            })
+            .collect::<Vec<_>>();
+        let inputs = values[..input_count].to_vec();
+        let outputs = values[input_count..]
+            .chunks(components)
+            .map(T::from_components)
+            .collect::<Vec<_>>();
+        function.validate(&inputs, &outputs)?;
+        Ok(Curve::new(inputs, outputs, function, bytes[6] == 1))
+    }
+}
+
+// Binary format of `Curve::to_bytes`
+const BINARY_MAGIC: &[u8; 4] = b"MNTC";
+const BINARY_VERSION: u8 = 1;
+const BINARY_HEADER_LEN: usize = 16;
+
+// Fraction of each segment of a step curve used to ramp to the next value, when it is converted to
+// a linear curve by `concat`.
+const STEP_RAMP_FRACTION: f32 = 1e-4;
+
+// Maximum number of refinement passes of `convert_mode`.
+const CONVERT_PASSES: usize = 16;
+
+// Maximum distance between a curve and its resampled cubic spline in `retime`.
+const RETIME_TOLERANCE: f32 = 1e-4;
+
+/// Index into `outputs` of the value of keyframe `index`, for the output layout of `function`.
+pub(crate) fn keyframe_output_index<T>(function: &InterpolationFunction<T>, index: usize) -> usize
+where
+    T: InterpolationPrimitive,
+{
+    match *function {
+        InterpolationFunction::CubicSpline => index * 3 + 1,
+        InterpolationFunction::CatmullRomSpline => index + 1,
+        _ => index,
+    }
+}
+
+/// Insert a keyframe at `input`, with the value the curve has at that point.
+///
+/// For cubic spline interpolation the tangents of the new keyframe are set to the velocity of the
+/// curve at `input`, so the shape of the curve is preserved. For Catmull-Rom spline interpolation
+/// the tangents are implied by the neighbouring keyframes, so the shape of the two segments next
+/// to the new keyframe will change slightly.
+///
+/// ### Parameters
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `input`: input value of the new keyframe, must be inside the range of `inputs`
+///
+/// ### Returns
+///
+/// The index of the keyframe at `input`. If there already is a keyframe at `input`, nothing is
+/// inserted and the index of the existing keyframe is returned.
+///
+pub fn insert_keyframe<T>(
+    inputs: &mut Vec<f32>,
+    outputs: &mut Vec<T>,
+    function: &InterpolationFunction<T>,
+    input: f32,
+) -> usize
+where
+    T: InterpolationPrimitive + Clone,
+{
+    assert!(
+        input >= inputs[0] && input <= inputs[inputs.len() - 1],
+        "Keyframe input must be inside the range of the curve"
+    );
+    let index = match inputs.binary_search_by(|v| v.partial_cmp(&input).unwrap()) {
+        Ok(index) => return index,
+        Err(index) => index,
+    };
+    let value = function.interpolate(input, inputs, outputs, false);
+    match *function {
+        InterpolationFunction::CubicSpline => {
+            let left = index - 1;
+            let t_diff = inputs[index] - inputs[left];
+            let (p0, p1, m0, m1) = hermite_segment(left, inputs, outputs, function).unwrap();
+            let velocity = spline_derivative(input, inputs[left], t_diff, &p0, &p1, &m0, &m1);
+            let output_index = index * 3;
+            outputs.insert(output_index, velocity.clone());
+            outputs.insert(output_index, value);
+            outputs.insert(output_index, velocity);
+        }
+        _ => outputs.insert(keyframe_output_index(function, index), value),
+    }
+    inputs.insert(index, input);
+    index
+}
+
+/// Merge keyframes that are closer than `min_dt` together, to avoid numerical problems with
+/// segments of near zero length.
+///
+/// Each run of keyframes within `min_dt` of the first keyframe of the run is replaced by a single
+/// keyframe, with the input value and value of the first keyframe of the run. The last keyframe of
+/// the curve is kept instead if it is part of the run, so the range of the curve is unchanged. For
+/// cubic spline interpolation the merged keyframe takes the in tangent of the first keyframe and
+/// the out tangent of the last keyframe of the run.
+///
+/// ### Parameters
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `min_dt`: minimum distance between the input values of two keyframes
+///
+pub fn enforce_min_spacing<T>(
+    inputs: &mut Vec<f32>,
+    outputs: &mut Vec<T>,
+    function: &InterpolationFunction<T>,
+    min_dt: f32,
+) where
+    T: InterpolationPrimitive + Clone,
+{
+    merge_close_keyframes(inputs, outputs, function, min_dt, |outputs, _, kept| {
+        outputs[keyframe_output_index(function, kept)].clone()
+    });
+}
+
+/// Merge keyframes with nearly identical input values, e.g. after importing data where rounding
+/// errors split a single keyframe in two.
+///
+/// Runs of keyframes are found as for `enforce_min_spacing`, with `time_tolerance` as the minimum
+/// distance. If all values of a run are within `value_tolerance` of the value of its first
+/// keyframe, using `sub().magnitude2().sqrt()`, the merged keyframe gets the average value of the
+/// run. Otherwise the keyframes disagree, and the value of the first keyframe of the run is used.
+/// The input value and tangents of the merged keyframe are chosen as for `enforce_min_spacing`, so
+/// a run at the end of the curve keeps the input value of the last keyframe.
+///
+/// ### Parameters
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `time_tolerance`: keyframes closer than this are merged
+/// - `value_tolerance`: maximum distance between values that are averaged
+///
+pub fn dedup_close_keyframes<T>(
+    inputs: &mut Vec<f32>,
+    outputs: &mut Vec<T>,
+    function: &InterpolationFunction<T>,
+    time_tolerance: f32,
+    value_tolerance: f32,
+) where
+    T: InterpolationPrimitive + Clone,
+{
+    merge_close_keyframes(
+        inputs,
+        outputs,
+        function,
+        time_tolerance,
+        |outputs, run, _| {
+            let value = |i| &outputs[keyframe_output_index(function, i)];
+            let first = value(run.start);
+            let agree = run
+                .clone()
+                .all(|i| value(i).sub(first).magnitude2() <= value_tolerance * value_tolerance);
+            if agree {
+                let count = run.len();
+                run.skip(1)
+                    .fold(first.clone(), |sum, i| sum.add(value(i)))
+                    .mul(1. / count as f32)
+            } else {
+                first.clone()
+            }
+        },
+    );
+}
+
+// Replace each run of keyframes within `min_dt` of the first keyframe of the run by a single
+// keyframe, see `enforce_min_spacing`. `merge_values` is called with the outputs, the keyframe
+// indices of the run, and the index of the keyframe whose input value is kept, and returns the
+// value of the merged keyframe.
+fn merge_close_keyframes<T, F>(
+    inputs: &mut Vec<f32>,
+    outputs: &mut Vec<T>,
+    function: &InterpolationFunction<T>,
+    min_dt: f32,
+    merge_values: F,
+) where
+    T: InterpolationPrimitive + Clone,
+    F: Fn(&[T], Range<usize>, usize) -> T,
+{
+    let count = inputs.len();
+    let mut new_inputs = Vec::with_capacity(count);
+    let mut new_outputs = Vec::with_capacity(outputs.len());
+    if *function == InterpolationFunction::CatmullRomSpline {
+        new_outputs.push(outputs[0].clone());
+    }
+    let mut start = 0;
+    while start < count {
+        let mut end = start + 1;
+        while end < count && inputs[end] - inputs[start] < min_dt {
+            end += 1;
+        }
+        let kept = if end == count && start > 0 {
+            count - 1
+        } else {
+            start
+        };
+        new_inputs.push(inputs[kept]);
+        let value = merge_values(outputs, start..end, kept);
+        match *function {
+            InterpolationFunction::CubicSpline => {
+                new_outputs.push(outputs[start * 3].clone());
+                new_outputs.push(value);
+                new_outputs.push(outputs[(end - 1) * 3 + 2].clone());
+            }
+            _ => new_outputs.push(value),
+        }
+        start = end;
+    }
+    if *function == InterpolationFunction::CatmullRomSpline {
+        new_outputs.push(outputs[outputs.len() - 1].clone());
+    }
+    *inputs = new_inputs;
+    *outputs = new_outputs;
+}
+
+/// Scale all output values of a curve by `factor`, so the shape of the curve scales uniformly.
+///
+/// Tangents scale with the values, so for cubic spline interpolation the stored tangents are
+/// scaled as well, and for Catmull-Rom spline interpolation the padding tangents are scaled along
+/// with the values the other tangents are derived from. For linear, step and spline interpolation,
+/// sampling the scaled curve is the same as scaling the sampled values of the original curve,
+/// unless the curve normalizes its sampled values, which undoes the scaling.
+///
+/// Spherical linear interpolation depends on the dot product of the values, which scales by
+/// `factor²`, so it does not commute with scaling, and such curves are rejected. Generic functions
+/// are assumed to be linear in the output values.
+///
+/// Panics if `function` is `SphericalLinear` or `QuasiSphericalLinear`.
+///
+/// ### Parameters
+///
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `factor`: factor to scale the values by
+/// - `function`: the interpolation function used for the data
+///
+pub fn scale_outputs<T>(outputs: &mut [T], factor: f32, function: &InterpolationFunction<T>)
+where
+    T: InterpolationPrimitive + Clone,
+{
+    match *function {
+        InterpolationFunction::SphericalLinear | InterpolationFunction::QuasiSphericalLinear => {
+            panic!("Can not scale the values of a spherical linear curve")
+        }
+        // All layouts are scaled the same way, values and tangents alike
+        _ => {
+            for output in outputs.iter_mut() {
+                *output = output.mul(factor);
+            }
+        }
+    }
+}
+
+/// Add `delta` to all values of a curve, moving the curve without changing its shape.
+///
+/// Tangents are differences of values, so only the values of the keyframes are offset. The
+/// stored tangents of cubic spline interpolation and the padding tangents of Catmull-Rom spline
+/// interpolation are left unchanged. Sampling the offset curve is the same as adding `delta` to the
+/// sampled values of the original curve.
+///
+/// ### Parameters
+///
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `delta`: value to add to the keyframe values
+/// - `function`: the interpolation function used for the data
+///
+pub fn offset_outputs<T>(outputs: &mut [T], delta: &T, function: &InterpolationFunction<T>)
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let keyframes = match *function {
+        InterpolationFunction::CubicSpline => outputs.len() / 3,
+        InterpolationFunction::CatmullRomSpline => outputs.len().saturating_sub(2),
+        _ => outputs.len(),
+    };
+    for index in 0..keyframes {
+        let output = &mut outputs[keyframe_output_index(function, index)];
+        *output = output.add(delta);
+    }
+}
+
+/// Split a curve in two at `input`.
+///
+/// A keyframe is inserted at `input` (see `insert_keyframe`), and the data is partitioned at that
+/// keyframe, so the left curve covers the range `[inputs[0], input]` and the right curve covers
+/// `[input, inputs[n]]`. Both curves match the original curve on their respective range.
+///
+/// Catmull-Rom spline data can't be split without changing the implied tangents next to the
+/// split point, so it is converted to cubic spline data first, and both returned curves will use
+/// cubic spline interpolation.
+///
+/// The returned curves do not normalize, use `Curve::split_at` to keep the normalize flag.
+///
+/// ### Parameters
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `input`: input value to split at, must be inside the range of `inputs`
+///
+pub fn split_at<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    input: f32,
+) -> (Curve<T>, Curve<T>)
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let mut inputs = inputs.to_vec();
+    let (mut outputs, function) = match *function {
+        InterpolationFunction::CatmullRomSpline => (
+            catmull_rom_to_cubic(&inputs, outputs),
+            InterpolationFunction::CubicSpline,
+        ),
+        _ => (outputs.to_vec(), function.clone()),
+    };
+    let index = insert_keyframe(&mut inputs, &mut outputs, &function, input);
+    let (left_outputs, right_outputs) = match function {
+        InterpolationFunction::CubicSpline => (
+            outputs[..(index + 1) * 3].to_vec(),
+            outputs[index * 3..].to_vec(),
+        ),
+        _ => (outputs[..index + 1].to_vec(), outputs[index..].to_vec()),
+    };
+    (
+        Curve::new(
+            inputs[..index + 1].to_vec(),
+            left_outputs,
+            function.clone(),
+            false,
+        ),
+        Curve::new(inputs[index..].to_vec(), right_outputs, function, false),
+    )
+}
+
+/// Concatenate two curves, so `b` starts `blend_time` after the end of `a`.
+///
+/// The input values of `b` are offset to follow `a`, and a blending segment of length
+/// `blend_time` connects the last keyframe of `a` with the first keyframe of `b`. The result
+/// samples `a` and `b` unchanged on their respective ranges.
+///
+/// If both curves use spline interpolation, the result uses cubic spline interpolation (see
+/// `split_at` for why Catmull-Rom data is converted), and the tangents of the blending segment are
+/// set to the velocity at the end of `a` and the start of `b`, so the seam is C1 continuous.
+///
+/// If both curves use the same non spline function, the keyframes are simply joined, and the
+/// blending segment uses that function.
+///
+/// If the curves use different functions, both are converted to linear curves, and the result
+/// uses linear interpolation, including at the seam. Step curves are converted by holding each
+/// value until just before the next keyframe, where it ramps to the next value over
+/// `STEP_RAMP_FRACTION` of the segment. Other curves are resampled, see `resample_with_tolerance`,
+/// which is exact for linear curves.
+///
+/// The normalize flag of `a` is used for the result.
+///
+/// ### Parameters
+///
+/// - `a`: the first curve
+/// - `b`: the curve to append after `a`
+/// - `blend_time`: length of the blending segment between the curves, must be positive
+///
+pub fn concat<T>(a: Curve<T>, b: Curve<T>, blend_time: f32) -> Curve<T>
+where
+    T: InterpolationPrimitive + Clone,
+{
+    assert!(blend_time > 0., "Blend time must be positive");
+    let offset = a.inputs[a.inputs.len() - 1] + blend_time - b.inputs[0];
+    let mut inputs = a.inputs.clone();
+    inputs.extend(b.inputs.iter().map(|t| t + offset));
+    let (outputs, function) = match (to_cubic(&a), to_cubic(&b)) {
+        (Some(mut a_outputs), Some(b_outputs)) => {
+            // Keep the velocity at the seam, so the blending segment is C1 continuous
+            let last = a_outputs.len() - 1;
+            a_outputs[last] = a_outputs[last - 2].clone();
+            a_outputs.extend(b_outputs);
+            let first = last + 1;
+            a_outputs[first] = a_outputs[first + 2].clone();
+            (a_outputs, InterpolationFunction::CubicSpline)
+        }
+        _ if a.function == b.function => {
+            let mut outputs = a.outputs;
+            outputs.extend(b.outputs);
+            (outputs, a.function)
+        }
+        _ => {
+            let (a_inputs, mut a_outputs) = to_linear(&a);
+            let (b_inputs, b_outputs) = to_linear(&b);
+            inputs = a_inputs;
+            inputs.extend(b_inputs.iter().map(|t| t + offset));
+            a_outputs.extend(b_outputs);
+            (a_outputs, InterpolationFunction::Linear)
+        }
+    };
+    Curve::new(inputs, outputs, function, a.normalize)
+}
+
+/// Change the duration of a curve, by scaling the time axis around the first keyframe.
+///
+/// All keyframe input values are scaled by `new_duration / duration`, and the stored tangents of
+/// spline curves, which are velocities, are scaled by `duration / new_duration`: the tangents of
+/// cubic spline curves, and the padding tangents at both ends of Catmull-Rom curves. The result
+/// passes through the same values as the original curve at the corresponding scaled input values,
+/// so the speed of the curve, relative to its average speed, is the same at every point of the
+/// normalized timeline. Generic functions are called with the scaled input values.
+///
+/// If `preserve_velocity_profile` is set, the curve is first resampled to a cubic spline with
+/// `convert_mode`, with its tangents set to the velocity of the curve on either side of each
+/// keyframe, and keyframes added until it is within `1e-4` of the curve. The resampled curve is
+/// then retimed as above. Linear and spline curves are resampled exactly, spherical linear and
+/// generic functions are approximated, with velocities that match the original curve at the
+/// keyframes. Step curves have no velocity, and are never resampled.
+///
+/// Panics if `new_duration` is not positive.
+///
+/// ### Parameters
+///
+/// - `curve`: the curve to retime
+/// - `new_duration`: the duration of the result, i.e. the distance between the input values of the
+///   first and last keyframe
+/// - `preserve_velocity_profile`: if true, resample the curve to a cubic spline before retiming
+///
+pub fn retime<T>(curve: &Curve<T>, new_duration: f32, preserve_velocity_profile: bool) -> Curve<T>
+where
+    T: InterpolationPrimitive + Clone,
+{
+    assert!(new_duration > 0., "The new duration must be positive");
+    if preserve_velocity_profile && curve.function != InterpolationFunction::Step {
+        let resampled = convert_mode(curve, InterpolationFunction::CubicSpline, RETIME_TOLERANCE);
+        return retime(&resampled, new_duration, false);
+    }
+    let start = curve.inputs[0];
+    let duration = curve.inputs[curve.inputs.len() - 1] - start;
+    if duration <= 0. {
+        return curve.clone();
+    }
+    let scale = new_duration / duration;
+    let inputs = curve
+        .inputs
+        .iter()
+        .map(|t| start + (t - start) * scale)
+        .collect();
+    let mut outputs = curve.outputs.clone();
+    match curve.function {
+        InterpolationFunction::CubicSpline => {
+            for (i, output) in outputs.iter_mut().enumerate() {
+                if i % 3 != 1 {
+                    *output = output.mul(1. / scale);
+                }
+            }
+        }
+        InterpolationFunction::CatmullRomSpline => {
+            let last = outputs.len() - 1;
+            outputs[0] = outputs[0].mul(1. / scale);
+            outputs[last] = outputs[last].mul(1. / scale);
+        }
+        _ => (),
+    }
+    Curve::new(inputs, outputs, curve.function.clone(), curve.normalize)
+}
+
+/// Build a curve of the first derivative of a curve, i.e. its velocity.
+///
+/// The `derivative` is sampled at `samples` evenly spaced input values covering the range of the
+/// keyframes, and the result is a linear curve through those samples, so this is an
+/// approximation that gets better with more samples. At least 2 samples are always used.
+///
+/// ### Parameters
+///
+/// - `curve`: the curve to differentiate
+/// - `samples`: number of keyframes of the derivative curve
+///
+pub fn derivative_curve<T>(curve: &Curve<T>, samples: usize) -> Curve<T>
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let inputs = sample_range(
+        curve.inputs[0],
+        curve.inputs[curve.inputs.len() - 1],
+        samples.max(2),
+    );
+    let outputs = inputs
+        .iter()
+        .map(|&t| derivative(t, &curve.inputs, &curve.outputs, &curve.function))
+        .collect();
+    Curve::new(inputs, outputs, InterpolationFunction::Linear, false)
+}
+
+/// Check if two curves are equal within a tolerance, e.g. to test exported curves.
+///
+/// The curves are considered equal if the durations of their keyframes differ by at most
+/// `tolerance`, and the sampled values differ by at most `tolerance` at `samples` evenly spaced
+/// input values covering the range where both curves have keyframes. Values are sampled with
+/// `Curve::sample`, and distances are compared squared, using `sub().magnitude2()`. Unlike
+/// `PartialEq`, this doesn't depend on the keyframe layout, so curves with different functions or
+/// keyframes that describe the same shape compare equal.
+///
+/// ### Parameters
+///
+/// - `a`, `b`: the curves to compare
+/// - `tolerance`: maximum allowed difference
+/// - `samples`: number of input values the curves are compared at, at least 2 are always used
+///
+pub fn curves_approx_eq<T>(a: &Curve<T>, b: &Curve<T>, tolerance: f32, samples: usize) -> bool
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let range = |c: &Curve<T>| (c.inputs[0], c.inputs[c.inputs.len() - 1]);
+    let (a_start, a_end) = range(a);
+    let (b_start, b_end) = range(b);
+    if ((a_end - a_start) - (b_end - b_start)).abs() > tolerance {
+        return false;
+    }
+    let (start, end) = (a_start.max(b_start), a_end.min(b_end));
+    if start > end {
+        return false;
+    }
+    sample_range(start, end, samples.max(2))
+        .into_iter()
+        .all(|t| a.sample(t).sub(&b.sample(t)).magnitude2() <= tolerance * tolerance)
+}
+
+/// Blend two curves, with the blend factor given by a third curve.
+///
+/// `a`, `b` and `blend` are all sampled at `input`, and the values of `a` and `b` are linearly
+/// interpolated by the blend factor, clamped to `[0, 1]`, see `lerp_pair`. The result is
+/// normalized if `a` has the normalize flag set.
+///
+/// ### Parameters
+///
+/// - `a`: the curve used when the blend factor is 0
+/// - `b`: the curve used when the blend factor is 1
+/// - `blend`: curve of the blend factor
+/// - `input`: the input value to sample the curves at
+///
+pub fn blend_with_curve<T>(a: &Curve<T>, b: &Curve<T>, blend: &Curve<f32>, input: f32) -> T
+where
+    T: InterpolationPrimitive + Clone,
+{
+    lerp_pair(
+        &a.sample(input),
+        &b.sample(input),
+        blend.sample(input),
+        a.normalize,
+    )
+}
+
+/// Merge three scalar curves into a single `Vector3` curve, one curve per component.
+///
+/// The result has a keyframe at every input value of any of the channels, where each channel is
+/// sampled. Channels are constant outside of their own range, as when sampling them separately.
+///
+/// The function of the result depends on the channels:
+///
+/// - if all channels use step interpolation, or all use linear interpolation, the result uses the
+///   same function
+/// - if all channels use linear or spline interpolation, the result uses cubic spline
+///   interpolation, with the tangents set to the velocity of each channel on either side of the
+///   keyframe, so it matches the channels exactly
+/// - otherwise the result uses linear interpolation between the sampled keyframes
+///
+/// ### Parameters
+///
+/// - `x`: curve of the x component
+/// - `y`: curve of the y component
+/// - `z`: curve of the z component
+///
+pub fn merge_channels_vec3(x: &Curve<f32>, y: &Curve<f32>, z: &Curve<f32>) -> Curve<Vector3<f32>> {
+    let channels = [x, y, z];
+    let mut inputs = channels
+        .iter()
+        .flat_map(|c| c.inputs.iter().cloned())
+        .collect::<Vec<_>>();
+    inputs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    inputs.dedup();
+    let vector = |f: &dyn Fn(&Curve<f32>) -> f32| Vector3 {
+        x: f(x),
+        y: f(y),
+        z: f(z),
+    };
+    let is_spline = |c: &&Curve<f32>| {
+        matches!(
+            c.function,
+            InterpolationFunction::CubicSpline | InterpolationFunction::CatmullRomSpline
+        )
+    };
+    let function = if channels
+        .iter()
+        .all(|c| c.function == InterpolationFunction::Step)
+    {
+        InterpolationFunction::Step
+    } else if channels.iter().any(is_spline)
+        && channels
+            .iter()
+            .all(|c| c.function == InterpolationFunction::Linear || is_spline(c))
+    {
+        InterpolationFunction::CubicSpline
+    } else {
+        InterpolationFunction::Linear
+    };
+    let mut outputs = Vec::new();
+    for &t in &inputs {
+        let value = vector(&|c| c.sample(t));
+        if function == InterpolationFunction::CubicSpline {
+            outputs.push(vector(&|c| {
+                one_sided_derivatives(t, &c.inputs, &c.outputs, &c.function).0
+            }));
+            outputs.push(value);
+            outputs.push(vector(&|c| {
+                one_sided_derivatives(t, &c.inputs, &c.outputs, &c.function).1
+            }));
+        } else {
+            outputs.push(value);
+        }
+    }
+    Curve::new(inputs, outputs, function, false)
+}
+
+/// Convert a curve to a different interpolation function, adding keyframes where needed.
+///
+/// The result starts with keyframes at the input values of the keyframes of `curve`, with the
+/// values of `curve` there. Then each interval where the result deviates more than `tolerance`
+/// from `curve`, at a number of test points inside the interval, is split in two by a new
+/// keyframe, until the result is within `tolerance` everywhere or a maximum number of passes is
+/// reached. Distances are compared squared, using `sub().magnitude2()`, so deviations in either
+/// direction count for scalar curves as well.
+///
+/// The output data is built in the layout of `target`: for cubic spline interpolation the tangents
+/// are set to the velocity of `curve` on either side of each keyframe, and for Catmull-Rom spline
+/// interpolation the end tangents are set to the velocity of `curve` at its ends. The time remap
+/// and normalize flag of `curve` are kept.
+///
+/// ### Parameters
+///
+/// - `curve`: the curve to convert
+/// - `target`: the interpolation function of the result
+/// - `tolerance`: maximum allowed distance between the result and `curve`
+///
+pub fn convert_mode<T>(
+    curve: &Curve<T>,
+    target: InterpolationFunction<T>,
+    tolerance: f32,
+) -> Curve<T>
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let source = |t: f32| {
+        curve
+            .function
+            .interpolate(t, &curve.inputs, &curve.outputs, false)
+    };
+    let mut inputs = curve.inputs.clone();
+    let mut outputs = converted_outputs(curve, &inputs, &target);
+    for _ in 0..CONVERT_PASSES {
+        let mut refined = vec![inputs[0]];
+        for w in inputs.windows(2) {
+            let within_tolerance = (1..8).all(|i| {
+                let t = w[0] + (w[1] - w[0]) * i as f32 / 8.;
+                let converted = target.interpolate(t, &inputs, &outputs, false);
+                source(t).sub(&converted).magnitude2() <= tolerance * tolerance
+            });
+            if !within_tolerance {
+                refined.push((w[0] + w[1]) / 2.);
+            }
+            refined.push(w[1]);
+        }
+        if refined.len() == inputs.len() {
+            break;
+        }
+        inputs = refined;
+        outputs = converted_outputs(curve, &inputs, &target);
+    }
+    let mut converted = Curve::new(inputs, outputs, target, curve.normalize);
+    converted.time_remap = curve.time_remap;
+    converted
+}
+
+// Output data for `target`, with keyframes at `inputs` taking their values from `curve`.
+fn converted_outputs<T>(
+    curve: &Curve<T>,
+    inputs: &[f32],
+    target: &InterpolationFunction<T>,
+) -> Vec<T>
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let derivatives =
+        |t: f32| one_sided_derivatives(t, &curve.inputs, &curve.outputs, &curve.function);
+    let values = inputs.iter().map(|&t| {
+        curve
+            .function
+            .interpolate(t, &curve.inputs, &curve.outputs, false)
+    });
+    match *target {
+        InterpolationFunction::CubicSpline => inputs
+            .iter()
+            .zip(values)
+            .flat_map(|(&t, value)| {
+                let (left, right) = derivatives(t);
+                vec![left, value, right]
+            })
+            .collect(),
+        InterpolationFunction::CatmullRomSpline => {
+            let mut outputs = vec![derivatives(inputs[0]).1];
+            outputs.extend(values);
+            outputs.push(derivatives(inputs[inputs.len() - 1]).0);
+            outputs
+        }
+        _ => values.collect(),
+    }
+}
+
+// Keyframes of a linear curve matching `curve`, see `concat`.
+fn to_linear<T>(curve: &Curve<T>) -> (Vec<f32>, Vec<T>)
+where
+    T: InterpolationPrimitive + Clone,
+{
+    if curve.function != InterpolationFunction::Step {
+        return resample_with_tolerance(&curve.inputs, &curve.outputs, &curve.function, 1e-4);
+    }
+    let mut inputs = Vec::with_capacity(curve.inputs.len() * 2);
+    let mut outputs = Vec::with_capacity(curve.inputs.len() * 2);
+    for (i, value) in curve.outputs.iter().enumerate() {
+        inputs.push(curve.inputs[i]);
+        outputs.push(value.clone());
+        if let Some(&next) = curve.inputs.get(i + 1) {
+            inputs.push(next - (next - curve.inputs[i]) * STEP_RAMP_FRACTION);
+            outputs.push(value.clone());
+        }
+    }
+    (inputs, outputs)
+}
+
+// Output data of a spline curve in the cubic spline layout.
+fn to_cubic<T>(curve: &Curve<T>) -> Option<Vec<T>>
+where
+    T: InterpolationPrimitive + Clone,
+{
+    match curve.function {
+        InterpolationFunction::CubicSpline => Some(curve.outputs.clone()),
+        InterpolationFunction::CatmullRomSpline => {
+            Some(catmull_rom_to_cubic(&curve.inputs, &curve.outputs))
+        }
+        _ => None,
+    }
+}
+
+// Convert Catmull-Rom spline data to the equivalent cubic spline data, with explicit tangents.
+fn catmull_rom_to_cubic<T>(inputs: &[f32], outputs: &[T]) -> Vec<T>
+where
+    T: InterpolationPrimitive + Clone,
+{
+    if inputs.len() < 2 {
+        return vec![outputs[0].clone(), outputs[1].clone(), outputs[2].clone()];
+    }
+    let function = InterpolationFunction::CatmullRomSpline;
+    let segments = (0..inputs.len() - 1)
+        .map(|i| {
+            let t_diff = inputs[i + 1] - inputs[i];
+            let (_, _, m0, m1) = hermite_segment(i, inputs, outputs, &function).unwrap();
+            (m0.mul(1. / t_diff), m1.mul(1. / t_diff))
+        })
+        .collect::<Vec<_>>();
+    let mut cubic = Vec::with_capacity(inputs.len() * 3);
+    for i in 0..inputs.len() {
+        let in_tangent = if i == 0 {
+            segments[0].0.clone()
+        } else {
+            segments[i - 1].1.clone()
+        };
+        let out_tangent = if i == inputs.len() - 1 {
+            in_tangent.clone()
+        } else {
+            segments[i].0.clone()
+        };
+        cubic.push(in_tangent);
+        cubic.push(outputs[i + 1].clone());
+        cubic.push(out_tangent);
+    }
+    cubic
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use derivative::derivative;
+
+    fn assert_matches(curve: &Curve<f32>, original: &Curve<f32>, from: f32, to: f32) {
+        for i in 0..=20 {
+            let t = from + (to - from) * i as f32 / 20.;
+            assert!((curve.sample(t) - original.sample(t)).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_split_linear() {
+        let curve = Curve::new(
+            vec![0., 1., 2., 3.],
+            vec![0., 1., 0., -1.],
+            InterpolationFunction::Linear,
+            false,
+        );
+        let (left, right) = curve.split_at(1.5);
+        assert_eq!(vec![0., 1., 1.5], left.inputs);
+        assert_eq!(vec![1.5, 2., 3.], right.inputs);
+        assert_matches(&left, &curve, 0., 1.5);
+        assert_matches(&right, &curve, 1.5, 3.);
+    }
+
+    #[test]
+    fn test_split_cubic() {
+        let curve = Curve::new(
+            vec![0., 1., 3.],
+            vec![0., 0., 1., 0., 1., -2., 0., 0., 0.],
+            InterpolationFunction::CubicSpline,
+            false,
+        );
+        let (left, right) = curve.split_at(1.75);
+        assert_matches(&left, &curve, 0., 1.75);
+        assert_matches(&right, &curve, 1.75, 3.);
+    }
+
+    #[test]
+    fn test_split_catmull_rom() {
+        let curve = Curve::new(
+            vec![0., 1., 2., 3., 4.],
+            vec![1., 0., 1., 0., -1., 0., -1.],
+            InterpolationFunction::CatmullRomSpline,
+            false,
+        );
+        let (left, right) = curve.split_at(2.5);
+        assert_matches(&left, &curve, 0., 2.5);
+        assert_matches(&right, &curve, 2.5, 4.);
+    }
+
+    #[test]
+    fn test_scale_outputs_cubic() {
+        let input = vec![0., 1., 3.];
+        let output = vec![
+            Vector3::from([0., 0., 0.]),
+            Vector3::from([0., 0., 0.]),
+            Vector3::from([1., 2., 0.]),
+            Vector3::from([1., -1., 0.]),
+            Vector3::from([2., 1., 1.]),
+            Vector3::from([0., 3., 0.]),
+            Vector3::from([2., 0., 0.]),
+            Vector3::from([-1., 0., 4.]),
+            Vector3::from([0., 0., 0.]),
+        ];
+        let function = InterpolationFunction::CubicSpline;
+        let mut scaled = output.clone();
+        scale_outputs(&mut scaled, 2.5, &function);
+        for i in 0..=30 {
+            let t = i as f32 / 10.;
+            let expected = function.interpolate(t, &input, &output, false).mul(2.5);
+            let value = function.interpolate(t, &input, &scaled, false);
+            assert!(value.sub(&expected).magnitude() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_scale_outputs_catmull_rom() {
+        let input = vec![0., 1., 3.];
+        let output = vec![1f32, 0., 2., -1., 0.5];
+        let function = InterpolationFunction::CatmullRomSpline;
+        let mut scaled = output.clone();
+        scale_outputs(&mut scaled, -2., &function);
+        for i in 0..=30 {
+            let t = i as f32 / 10.;
+            let expected = function.interpolate(t, &input, &output, false) * -2.;
+            let value = function.interpolate(t, &input, &scaled, false);
+            assert!((value - expected).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_offset_outputs() {
+        let input = vec![0., 1., 3.];
+        let delta = Vector3::from([1., -2., 0.5]);
+        let cubic = vec![
+            Vector3::from([0., 0., 0.]),
+            Vector3::from([0., 0., 0.]),
+            Vector3::from([1., 2., 0.]),
+            Vector3::from([1., -1., 0.]),
+            Vector3::from([2., 1., 1.]),
+            Vector3::from([0., 3., 0.]),
+            Vector3::from([2., 0., 0.]),
+            Vector3::from([-1., 0., 4.]),
+            Vector3::from([0., 0., 0.]),
+        ];
+        let catmull_rom = cubic[1..6].to_vec();
+        for (function, output) in &[
+            (InterpolationFunction::CubicSpline, cubic),
+            (InterpolationFunction::CatmullRomSpline, catmull_rom),
+        ] {
+            let mut offset = output.clone();
+            offset_outputs(&mut offset, &delta, function);
+            for i in 0..=30 {
+                let t = i as f32 / 10.;
+                let expected = function.interpolate(t, &input, output, false).add(&delta);
+                let value = function.interpolate(t, &input, &offset, false);
+                assert!(value.sub(&expected).magnitude() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn test_enforce_min_spacing() {
+        let mut inputs = vec![0., 1., 1. + 1e-7, 2.];
+        let mut outputs: Vec<f32> = vec![0., 0., 1., -1., 2., 0.];
+        let function = InterpolationFunction::CatmullRomSpline;
+        enforce_min_spacing(&mut inputs, &mut outputs, &function, 1e-3);
+        assert_eq!(vec![0., 1., 2.], inputs);
+        assert_eq!(vec![0., 0., 1., 2., 0.], outputs);
+        for i in 0..=20 {
+            let t = i as f32 / 10.;
+            assert!(function
+                .interpolate(t, &inputs, &outputs, false)
+                .is_finite());
+        }
+
+        // The last keyframe is kept, to keep the range of the curve
+        let mut inputs = vec![0., 1., 1. + 1e-7];
+        let mut outputs = vec![1., 0., 2., 3., 1., 4., 5., 2., 6.];
+        let function = InterpolationFunction::CubicSpline;
+        enforce_min_spacing(&mut inputs, &mut outputs, &function, 1e-3);
+        assert_eq!(vec![0., 1. + 1e-7], inputs);
+        assert_eq!(vec![1., 0., 2., 3., 2., 6.], outputs);
+    }
+
+    #[test]
+    fn test_concat_splines() {
+        let a = Curve::new(
+            vec![0., 1., 2.],
+            vec![0., 0., 1., 0., 1., 0., 1., 2., 0.],
+            InterpolationFunction::CubicSpline,
+            false,
+        );
+        let b = Curve::new(
+            vec![5., 6., 7.],
+            vec![-1., 3., 0., 4., 0.5],
+            InterpolationFunction::CatmullRomSpline,
+            false,
+        );
+        let joined = concat(a.clone(), b.clone(), 0.5);
+        assert_eq!(vec![0., 1., 2., 2.5, 3.5, 4.5], joined.inputs);
+        assert_matches(&joined, &a, 0., 2.);
+        for i in 0..=20 {
+            let t = 5. + 2. * i as f32 / 20.;
+            assert!((joined.sample(t - 2.5) - b.sample(t)).abs() < 1e-4);
+        }
+        for &seam in &[2., 2.5] {
+            let left = derivative(
+                seam - 1e-4,
+                &joined.inputs,
+                &joined.outputs,
+                &joined.function,
+            );
+            let right = derivative(
+                seam + 1e-4,
+                &joined.inputs,
+                &joined.outputs,
+                &joined.function,
+            );
+            assert!((left - right).abs() < 1e-2);
+        }
+    }
+
+    #[test]
+    fn test_concat_mismatched() {
+        let a = Curve::new(
+            vec![0., 1.],
+            vec![0., 1.],
+            InterpolationFunction::Linear,
+            false,
+        );
+        let b = Curve::new(
+            vec![0., 1.],
+            vec![2., 3.],
+            InterpolationFunction::Step,
+            false,
+        );
+        let joined = concat(a, b, 1.);
+        assert_eq!(InterpolationFunction::Linear, joined.function);
+        assert_eq!(0., joined.inputs[0]);
+        assert_eq!(3., joined.inputs[joined.inputs.len() - 1]);
+        assert_eq!(0.5, joined.sample(0.5));
+        assert_eq!(1.5, joined.sample(1.5));
+        // The step part holds its value away from the join
+        assert_eq!(2., joined.sample(2.));
+        assert_eq!(2., joined.sample(2.5));
+        assert_eq!(2., joined.sample(2.99));
+        assert_eq!(3., joined.sample(3.));
+    }
+
+    #[test]
+    fn test_sample_ping_pong() {
+        let curve: Curve<f32> = Curve::new(
+            vec![0., 1., 2.],
+            vec![0., 0., 1., 3., 1.],
+            InterpolationFunction::CatmullRomSpline,
+            false,
+        );
+        let mode = Extrapolation::PingPong;
+        // 1.5 times the duration plays backwards to half the duration
+        assert_eq!(curve.sample(1.), curve.sample_extrapolated(3., mode));
+        assert_eq!(curve.sample(1.5), curve.sample_extrapolated(2.5, mode));
+        assert_eq!(curve.sample(0.5), curve.sample_extrapolated(4.5, mode));
+    }
+
+    #[test]
+    fn test_retime_uniform() {
+        let curve = Curve::new(
+            vec![0., 0.25, 0.5, 1.],
+            vec![0., 1., 0., 2.],
+            InterpolationFunction::Linear,
+            false,
+        );
+        let retimed = retime(&curve, 2., false);
+        assert_eq!(vec![0., 0.5, 1., 2.], retimed.inputs);
+        assert_eq!(curve.outputs, retimed.outputs);
+    }
+
+    #[test]
+    fn test_retime_uniform_cubic() {
+        let curve: Curve<f32> = Curve::new(
+            vec![0., 1., 2.],
+            vec![0., 0., 2., 1., 1., 1., 3., 2., 0.],
+            InterpolationFunction::CubicSpline,
+            false,
+        );
+        let retimed = retime(&curve, 4., false);
+        assert_eq!(vec![0., 2., 4.], retimed.inputs);
+        for i in 0..=20 {
+            let t = 2. * i as f32 / 20.;
+            let scaled = 2. * t;
+            assert!((retimed.sample(scaled) - curve.sample(t)).abs() < 1e-5);
+            let expected = derivative(t, &curve.inputs, &curve.outputs, &curve.function) / 2.;
+            let velocity = derivative(scaled, &retimed.inputs, &retimed.outputs, &retimed.function);
+            assert!((velocity - expected).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_retime_uniform_catmull_rom() {
+        let curve: Curve<f32> = Curve::new(
+            vec![1., 2., 3.],
+            vec![1., 0., 1., -1., 2.],
+            InterpolationFunction::CatmullRomSpline,
+            false,
+        );
+        let (start, s) = (1., 2.);
+        let retimed = retime(&curve, 4., false);
+        assert_eq!(vec![1., 3., 5.], retimed.inputs);
+        for i in 0..=20 {
+            let t = 1. + 2. * i as f32 / 20.;
+            assert!((retimed.sample(start + (t - start) * s) - curve.sample(t)).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_retime_preserve_velocity_profile() {
+        let curve: Curve<[f32; 2]> = Curve::new(
+            vec![1., 2., 4.],
+            vec![[1., 0.], [0., 1.], [-1., 0.]],
+            InterpolationFunction::SphericalLinear,
+            false,
+        );
+        let (start, s) = (1., 0.5);
+        let retimed = retime(&curve, 1.5, true);
+        assert_eq!(InterpolationFunction::CubicSpline, retimed.function);
+        assert_eq!(1., retimed.inputs[0]);
+        assert_eq!(2.5, retimed.inputs[retimed.inputs.len() - 1]);
+        for i in 1..30 {
+            let t = 1. + 3. * i as f32 / 30.;
+            let scaled = start + (t - start) * s;
+            assert!(retimed.sample(scaled).sub(&curve.sample(t)).magnitude() < 1e-3);
+            let expected =
+                derivative(t, &curve.inputs, &curve.outputs, &curve.function).mul(1. / s);
+            let velocity = derivative(scaled, &retimed.inputs, &retimed.outputs, &retimed.function);
+            assert!(velocity.sub(&expected).magnitude() < 1e-2);
+        }
+
+        let curve = Curve::new(
+            vec![0., 1.],
+            vec![0., 1.],
+            InterpolationFunction::Step,
+            false,
+        );
+        let retimed = retime(&curve, 2., true);
+        assert_eq!(InterpolationFunction::Step, retimed.function);
+        assert_eq!(vec![0., 2.], retimed.inputs);
+    }
+
+    #[test]
+    fn test_time_remap() {
+        let mut curve = Curve::new(
+            vec![0., 2.],
+            vec![0., 4.],
+            InterpolationFunction::Linear,
+            false,
+        );
+        curve.time_remap = TimeRemap::new(2., 0.);
+        assert_eq!(2., curve.sample(0.5));
+        assert_eq!(4., curve.sample(1.));
+        curve.time_remap = TimeRemap::new(1., 1.);
+        assert_eq!(2., curve.sample(0.));
+        assert_eq!(4., curve.sample(1.));
+        curve.time_remap = TimeRemap::new(2., 1.);
+        assert_eq!(3., curve.sample(0.25));
+        assert_eq!(2., curve.sample_extrapolated(1., Extrapolation::PingPong));
+    }
+
+    #[test]
+    fn test_sample_with_normalize_override() {
+        let curve = Curve::new(
+            vec![0., 1.],
+            vec![Vector3::from([2., 0., 0.]), Vector3::from([0., 2., 0.])],
+            InterpolationFunction::Linear,
+            true,
+        );
+        assert_eq!(curve.sample(0.5), curve.sample_with(0.5, None));
+        assert_eq!(curve.sample(0.5), curve.sample_with(0.5, Some(true)));
+        assert_eq!(
+            Vector3::from([1., 1., 0.]),
+            curve.sample_with(0.5, Some(false))
+        );
+        assert!((curve.sample(0.5).magnitude() - 1.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let curve = Curve::new(
+            vec![0., 1., 2.5],
+            vec![
+                Vector3::from([1., 0., 0.]),
+                Vector3::from([0., 0., 0.]),
+                Vector3::from([1., 2., 3.]),
+                Vector3::from([-4., 0.5, 1e-3]),
+                Vector3::from([0., 1., 0.]),
+            ],
+            InterpolationFunction::CatmullRomSpline,
+            true,
+        );
+        let bytes = curve.to_bytes();
+        assert_eq!(16 + 4 * (3 + 5 * 3), bytes.len());
+        assert_eq!(Ok(curve), Curve::from_bytes(&bytes));
+        assert_eq!(
+            Err(InterpolationError::InvalidBinaryData),
+            Curve::<Vector3<f32>>::from_bytes(&bytes[..bytes.len() - 1])
+        );
+        assert_eq!(
+            Err(InterpolationError::InvalidBinaryData),
+            Curve::<f32>::from_bytes(&bytes)
+        );
+    }
+
+    #[test]
+    fn test_derivative_curve() {
+        let ramp = Curve::new(
+            vec![0., 1., 4.],
+            vec![
+                Vector3::from([1., 0., 0.]),
+                Vector3::from([3., 1., 0.]),
+                Vector3::from([9., 4., 0.]),
+            ],
+            InterpolationFunction::Linear,
+            false,
+        );
+        let velocity = derivative_curve(&ramp, 9);
+        assert_eq!(9, velocity.inputs.len());
+        assert_eq!(4., velocity.inputs[8]);
+        for i in 0..=40 {
+            let v = velocity.sample(i as f32 / 10.);
+            assert!(v.sub(&Vector3::from([2., 1., 0.])).magnitude() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_blend_with_curve() {
+        let a = Curve::new(
+            vec![0., 2.],
+            vec![Vector3::from([0., 0., 0.]), Vector3::from([0., 2., 0.])],
+            InterpolationFunction::Linear,
+            false,
+        );
+        let b = Curve::new(
+            vec![0., 2.],
+            vec![Vector3::from([4., 0., 0.]), Vector3::from([4., 0., 2.])],
+            InterpolationFunction::Linear,
+            false,
+        );
+        let blend = Curve::new(
+            vec![0.5, 1.5],
+            vec![0., 1.],
+            InterpolationFunction::Linear,
+            false,
+        );
+        assert_eq!(a.sample(0.25), blend_with_curve(&a, &b, &blend, 0.25));
+        assert_eq!(b.sample(1.75), blend_with_curve(&a, &b, &blend, 1.75));
+        assert_eq!(
+            Vector3::from([2., 0.5, 0.5]),
+            blend_with_curve(&a, &b, &blend, 1.)
+        );
+    }
+
+    #[test]
+    fn test_merge_channels_ramps() {
+        let x = Curve::new(
+            vec![0., 2.],
+            vec![0., 2.],
+            InterpolationFunction::Linear,
+            false,
+        );
+        let y = Curve::new(
+            vec![0.5, 1., 3.],
+            vec![1., 0., 4.],
+            InterpolationFunction::Linear,
+            false,
+        );
+        let z = Curve::new(
+            vec![1., 1.5],
+            vec![-1., 1.],
+            InterpolationFunction::Linear,
+            false,
+        );
+        let merged = merge_channels_vec3(&x, &y, &z);
+        assert_eq!(InterpolationFunction::Linear, merged.function);
+        assert_eq!(vec![0., 0.5, 1., 1.5, 2., 3.], merged.inputs);
+        for i in 0..=40 {
+            let t = i as f32 / 10. - 0.5;
+            let expected = Vector3::from([x.sample(t), y.sample(t), z.sample(t)]);
+            assert!(merged.sample(t).sub(&expected).magnitude() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_merge_channels_splines() {
+        let x = Curve::new(
+            vec![0., 1., 3.],
+            vec![0., 0., 1., 0., 1., -2., 0., 0., 0.],
+            InterpolationFunction::CubicSpline,
+            false,
+        );
+        let y = Curve::new(
+            vec![0., 0.5, 2., 2.5],
+            vec![1., 0., 1., -1., 0., 2.],
+            InterpolationFunction::CatmullRomSpline,
+            false,
+        );
+        let z = Curve::new(
+            vec![1.5, 2.],
+            vec![0., 1.],
+            InterpolationFunction::Linear,
+            false,
+        );
+        let merged = merge_channels_vec3(&x, &y, &z);
+        assert_eq!(InterpolationFunction::CubicSpline, merged.function);
+        for i in 0..=40 {
+            let t = i as f32 / 10. - 0.5;
+            let expected = Vector3::from([x.sample(t), y.sample(t), z.sample(t)]);
+            assert!(merged.sample(t).sub(&expected).magnitude() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_convert_mode() {
+        let curve = Curve::new(
+            vec![0., 1., 3.],
+            vec![0f32, 0., 4., -2., 1., -1., 3., 2., 0.],
+            InterpolationFunction::CubicSpline,
+            false,
+        );
+        let tolerance = 0.01;
+        for target in &[
+            InterpolationFunction::Linear,
+            InterpolationFunction::CatmullRomSpline,
+        ] {
+            let converted = convert_mode(&curve, target.clone(), tolerance);
+            assert!(converted
+                .function
+                .validate(&converted.inputs, &converted.outputs)
+                .is_ok());
+            assert!(converted.inputs.len() > curve.inputs.len());
+            for i in 0..=300 {
+                let t = i as f32 / 100.;
+                assert!((curve.sample(t) - converted.sample(t)).abs() <= tolerance);
+            }
+        }
+    }
+
+    #[test]
+    fn test_curves_approx_eq() {
+        let a = Curve::new(
+            vec![0., 1., 2.],
+            vec![[0., 0.], [1., 2.], [3., 1.]],
+            InterpolationFunction::Linear,
+            false,
+        );
+        let mut b = a.clone();
+        b.outputs[1] = [1.001, 2.002];
+        assert!(curves_approx_eq(&a, &a, 0., 20));
+        assert!(curves_approx_eq(&a, &b, 0.01, 20));
+        assert!(!curves_approx_eq(&a, &b, 0.001, 20));
+        b.inputs[2] = 2.1;
+        assert!(!curves_approx_eq(&a, &b, 0.01, 20));
+        let cubic = convert_mode(&a, InterpolationFunction::CubicSpline, 1e-4);
+        assert!(curves_approx_eq(&a, &cubic, 1e-4, 50));
+    }
+
+    #[test]
+    fn test_keyframes() {
+        let curve = Curve::new(
+            vec![0., 1., 3.],
+            vec![0., 1., 0.5, 0.5, 2., 0., 0., 4., 1.],
+            InterpolationFunction::CubicSpline,
+            false,
+        );
+        assert_eq!(
+            vec![(0., &1.), (1., &2.), (3., &4.)],
+            curve.keyframes().collect::<Vec<_>>()
+        );
+        let curve = Curve::new(
+            vec![0., 1.],
+            vec![-1., 0., 1., 2.],
+            InterpolationFunction::CatmullRomSpline,
+            false,
+        );
+        assert_eq!(
+            vec![(0., &0.), (1., &1.)],
+            curve.keyframes().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_set_keyframe_value() {
+        let mut curve = Curve::new(
+            vec![0., 1., 3.],
+            vec![0., 1., 0.5, 0.5, 2., 0., 0., 4., 1.],
+            InterpolationFunction::CubicSpline,
+            false,
+        );
+        curve.set_keyframe_value(1, 5.);
+        assert_eq!(5., curve.sample(1.));
+        assert_eq!(vec![0., 1., 0.5, 0.5, 5., 0., 0., 4., 1.], curve.outputs);
+    }
+
+    #[test]
+    fn test_dedup_close_keyframes() {
+        let mut inputs = vec![0., 1., 1. + 1e-6, 2.];
+        let mut outputs = vec![-1., 0., 1., 1.2, 2., 3.];
+        let function = InterpolationFunction::CatmullRomSpline;
+        dedup_close_keyframes(&mut inputs, &mut outputs, &function, 1e-4, 0.5);
+        assert_eq!(vec![0., 1., 2.], inputs);
+        assert_eq!(vec![-1., 0., 1.1, 2., 3.], outputs);
+
+        let mut inputs = vec![0., 1., 1. + 1e-6, 2.];
+        let mut outputs = vec![0., 1., 5., 2.];
+        let function = InterpolationFunction::Linear;
+        dedup_close_keyframes(&mut inputs, &mut outputs, &function, 1e-4, 0.5);
+        assert_eq!(vec![0., 1., 2.], inputs);
+        assert_eq!(vec![0., 1., 2.], outputs);
+
+        let mut inputs = vec![0., 1., 2., 2. + 1e-6];
+        let mut outputs = vec![0., 1., 2., 9.];
+        dedup_close_keyframes(&mut inputs, &mut outputs, &function, 1e-4, 0.5);
+        assert_eq!(vec![0., 1., 2. + 1e-6], inputs);
+        assert_eq!(vec![0., 1., 2.], outputs);
+    }
+}
@@ -0,0 +1,406 @@
+use cubic_spline::{
+    hermite_segment, spline_derivative, spline_second_derivative, spline_third_derivative,
+};
+use get_input_index;
+use primitive::InterpolationPrimitive;
+use sample::for_each_input_index;
+use {resolve_segment, segment_at, InterpolationFunction, Segment};
+
+/// Calculate the first derivative of the interpolated curve, i.e. the velocity, with regards to
+/// the input value.
+///
+/// Spline and linear interpolation are differentiated analytically, step interpolation always has
+/// a zero derivative, and the remaining functions are approximated using a central difference
+/// inside the segment. Outside the range of `inputs` the curve is constant, so the derivative is
+/// zero. At the last keyframe, the derivative of the last segment is used.
+///
+/// ## Parameters:
+///
+/// - `input`: the input value to the function
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+pub fn derivative<T>(
+    input: f32,
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+) -> T
+where
+    T: InterpolationPrimitive + Clone,
+{
+    match segment_index(input, inputs) {
+        Some(index) => segment_derivative(index, input, inputs, outputs, function),
+        None => outputs[0].mul(0.),
+    }
+}
+
+/// Calculate the first derivative of a curve at many input values, writing the values to `out`.
+///
+/// Same as calling `derivative` for each query input, with the keyframe index tracked with a
+/// cursor for sorted queries, as for `sample_many_into`.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `query_inputs`: input values to differentiate the curve at
+/// - `out`: buffer for the derivatives, must be the same size as `query_inputs`
+pub fn derivative_many_into<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    query_inputs: &[f32],
+    out: &mut [T],
+) where
+    T: InterpolationPrimitive + Clone,
+{
+    assert_eq!(
+        query_inputs.len(),
+        out.len(),
+        "Output buffer must be the same size as the query inputs"
+    );
+    for_each_input_index(inputs, query_inputs, |i, index, input| {
+        out[i] = match segment_index_at(index, input, inputs) {
+            Some(index) => segment_derivative(index, input, inputs, outputs, function),
+            None => outputs[0].mul(0.),
+        };
+    });
+}
+
+// First derivative inside the segment starting at keyframe `index`.
+fn segment_derivative<T>(
+    index: usize,
+    input: f32,
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+) -> T
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let t_diff = inputs[index + 1] - inputs[index];
+    match *function {
+        InterpolationFunction::Step => outputs[0].mul(0.),
+        InterpolationFunction::Linear => outputs[index + 1].sub(&outputs[index]).mul(1. / t_diff),
+        InterpolationFunction::CubicSpline | InterpolationFunction::CatmullRomSpline => {
+            let (p0, p1, m0, m1) = hermite_segment(index, inputs, outputs, function).unwrap();
+            spline_derivative(input, inputs[index], t_diff, &p0, &p1, &m0, &m1)
+        }
+        _ => {
+            let h = t_diff * 1e-3;
+            let left = (input - h).max(inputs[index]);
+            let right = (input + h).min(inputs[index + 1]);
+            function
+                .interpolate_at(Some(index), right, inputs, outputs, false)
+                .sub(&function.interpolate_at(Some(index), left, inputs, outputs, false))
+                .mul(1. / (right - left))
+        }
+    }
+}
+
+/// Calculate the second derivative of the interpolated curve, i.e. the acceleration, with
+/// regards to the input value.
+///
+/// Spline interpolation is differentiated analytically, linear and step interpolation always have
+/// a zero second derivative, and the remaining functions are approximated using a central
+/// difference of `derivative` inside the segment. Outside the range of `inputs` the second
+/// derivative is zero.
+///
+/// ## Parameters:
+///
+/// - `input`: the input value to the function
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+pub fn second_derivative<T>(
+    input: f32,
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+) -> T
+where
+    T: InterpolationPrimitive + Clone,
+{
+    match segment_index(input, inputs) {
+        Some(index) => segment_second_derivative(index, input, inputs, outputs, function),
+        None => outputs[0].mul(0.),
+    }
+}
+
+// Second derivative inside the segment starting at keyframe `index`.
+fn segment_second_derivative<T>(
+    index: usize,
+    input: f32,
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+) -> T
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let t_diff = inputs[index + 1] - inputs[index];
+    match *function {
+        InterpolationFunction::Step | InterpolationFunction::Linear => outputs[0].mul(0.),
+        InterpolationFunction::CubicSpline | InterpolationFunction::CatmullRomSpline => {
+            let (p0, p1, m0, m1) = hermite_segment(index, inputs, outputs, function).unwrap();
+            spline_second_derivative(input, inputs[index], t_diff, &p0, &p1, &m0, &m1)
+        }
+        _ => {
+            let h = t_diff * 1e-2;
+            let left = (input - h).max(inputs[index]);
+            let right = (input + h).min(inputs[index + 1]);
+            segment_derivative(index, right, inputs, outputs, function)
+                .sub(&segment_derivative(index, left, inputs, outputs, function))
+                .mul(1. / (right - left))
+        }
+    }
+}
+
+/// Calculate the third derivative of the interpolated curve, i.e. the jerk, with regards to the
+/// input value.
+///
+/// Spline interpolation is differentiated analytically, which gives a constant jerk inside each
+/// segment, as the segments are cubic polynomials. Linear and step interpolation always have a
+/// zero third derivative, and the remaining functions are approximated using a central difference
+/// of `second_derivative` inside the segment. Outside the range of `inputs` the third derivative
+/// is zero.
+///
+/// ## Parameters:
+///
+/// - `input`: the input value to the function
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+pub fn third_derivative<T>(
+    input: f32,
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+) -> T
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let index = match segment_index(input, inputs) {
+        Some(index) => index,
+        None => return outputs[0].mul(0.),
+    };
+    let t_diff = inputs[index + 1] - inputs[index];
+    match *function {
+        InterpolationFunction::Step | InterpolationFunction::Linear => outputs[0].mul(0.),
+        InterpolationFunction::CubicSpline | InterpolationFunction::CatmullRomSpline => {
+            let (p0, p1, m0, m1) = hermite_segment(index, inputs, outputs, function).unwrap();
+            spline_third_derivative(t_diff, &p0, &p1, &m0, &m1)
+        }
+        _ => {
+            let h = t_diff * 1e-2;
+            let left = (input - h).max(inputs[index]);
+            let right = (input + h).min(inputs[index + 1]);
+            segment_second_derivative(index, right, inputs, outputs, function)
+                .sub(&segment_second_derivative(
+                    index, left, inputs, outputs, function,
+                ))
+                .mul(1. / (right - left))
+        }
+    }
+}
+
+/// Sample the position, velocity and acceleration of the curve at once.
+///
+/// Same as calling `InterpolationFunction::interpolate`, `derivative` and `second_derivative`,
+/// but the keyframe index is only looked up once. Step interpolation has zero velocity and
+/// acceleration.
+///
+/// ## Parameters:
+///
+/// - `input`: the input value to the function
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `normalize`: if true, normalize the interpolated position
+///
+/// ## Returns
+///
+/// `(position, velocity, acceleration)`
+pub fn sample_kinematics<T>(
+    input: f32,
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    normalize: bool,
+) -> (T, T, T)
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let index = get_input_index(input, inputs);
+    let position = function.interpolate_at(index, input, inputs, outputs, normalize);
+    let zero = outputs[0].mul(0.);
+    match segment_index_at(index, input, inputs) {
+        Some(index) => (
+            position,
+            segment_derivative(index, input, inputs, outputs, function),
+            segment_second_derivative(index, input, inputs, outputs, function),
+        ),
+        None => (position, zero.clone(), zero),
+    }
+}
+
+// Derivatives `(left, right)` of a curve on either side of `input`, zero outside of the curve.
+pub(crate) fn one_sided_derivatives<T>(
+    input: f32,
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+) -> (T, T)
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let derivative = |index: Option<usize>| match index {
+        Some(index) => segment_derivative(index, input, inputs, outputs, function),
+        None => outputs[0].mul(0.),
+    };
+    // At a keyframe, the segment on the left ends where the segment on the right starts
+    let (left, right) = match resolve_segment(input, inputs) {
+        Segment::In { index, t_local: 0. } => (index.checked_sub(1), Some(index)),
+        Segment::In { index, .. } => (Some(index), Some(index)),
+        Segment::After => (segment_index(input, inputs), None),
+        Segment::Before => (None, None),
+    };
+    (derivative(left), derivative(right))
+}
+
+// Index of the segment used for differentiation at `input`, if `input` is inside the curve.
+pub(crate) fn segment_index(input: f32, inputs: &[f32]) -> Option<usize> {
+    if inputs.len() < 2 {
+        return None;
+    }
+    segment_index_at(get_input_index(input, inputs), input, inputs)
+}
+
+// Same as `segment_index`, using an already known keyframe index, as returned by
+// `get_input_index`.
+fn segment_index_at(index: Option<usize>, input: f32, inputs: &[f32]) -> Option<usize> {
+    if inputs.len() < 2 {
+        return None;
+    }
+    match segment_at(index, input, inputs) {
+        Segment::In { index, .. } => Some(index),
+        // The last keyframe still belongs to the last segment
+        Segment::After if input == inputs[inputs.len() - 1] => Some(inputs.len() - 2),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derivative_linear() {
+        let input = vec![0., 1., 3.];
+        let output = vec![0., 2., 1.];
+        let function = InterpolationFunction::Linear;
+        assert_eq!(2., derivative(0.5, &input, &output, &function));
+        assert_eq!(-0.5, derivative(2., &input, &output, &function));
+        assert_eq!(-0.5, derivative(3., &input, &output, &function));
+        assert_eq!(0., derivative(4., &input, &output, &function));
+    }
+
+    #[test]
+    fn test_one_sided_derivatives() {
+        let input = vec![0., 1., 3.];
+        let output = vec![0., 2., 1.];
+        let function = InterpolationFunction::Linear;
+        let sides = |t| one_sided_derivatives(t, &input, &output, &function);
+        assert_eq!((0., 0.), sides(-1.));
+        assert_eq!((0., 2.), sides(0.));
+        assert_eq!((2., 2.), sides(0.5));
+        assert_eq!((2., -0.5), sides(1.));
+        assert_eq!((-0.5, 0.), sides(3.));
+        assert_eq!((0., 0.), sides(4.));
+    }
+
+    #[test]
+    fn test_derivative_catmull_rom() {
+        let input = vec![0., 1., 2., 3., 4.];
+        let output = vec![1., 0., 1., 0., -1., 0., -1.];
+        let function = InterpolationFunction::CatmullRomSpline;
+        let h = 1e-3;
+        let expected = (function.interpolate(0.5 + h, &input, &output, false)
+            - function.interpolate(0.5 - h, &input, &output, false))
+            / (2. * h);
+        assert!((derivative(0.5, &input, &output, &function) - expected).abs() < 1e-2);
+    }
+
+    #[test]
+    fn test_derivative_many_into() {
+        let input = vec![0., 1., 2., 3., 4.];
+        let output = vec![1., 0., 1., 0., -1., 0., -1.];
+        let function = InterpolationFunction::CatmullRomSpline;
+        let ascending = (-5..=45).map(|i| i as f32 / 10.).collect::<Vec<_>>();
+        let descending = ascending.iter().rev().cloned().collect::<Vec<_>>();
+        let unsorted = vec![2.5, 0.5, 4., -1., 1.];
+        for query in &[ascending, descending, unsorted] {
+            let mut out = vec![0.; query.len()];
+            derivative_many_into(&input, &output, &function, query, &mut out);
+            for (&t, &value) in query.iter().zip(out.iter()) {
+                assert_eq!(derivative(t, &input, &output, &function), value);
+            }
+        }
+    }
+
+    #[test]
+    fn test_second_derivative_cubic() {
+        let input = vec![0., 2.];
+        let output: Vec<f32> = vec![0., 0., 0., 0., 4., 0.];
+        let function = InterpolationFunction::CubicSpline;
+        // f(t) = 3t^2 - t^3, f''(t) = 6 - 6t
+        assert!((second_derivative(0.5, &input, &output, &function) - 3.).abs() < 1e-5);
+        assert!((second_derivative(1.5, &input, &output, &function) + 3.).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_third_derivative_cubic() {
+        let input = vec![0., 2., 3.];
+        let output: Vec<f32> = vec![0., 0., 0., 0., 4., 0., 1., 1., 1.];
+        let function = InterpolationFunction::CubicSpline;
+        // f(t) = 3t^2 - t^3, f'''(t) = -6
+        for &t in &[0., 0.5, 1.2, 1.9] {
+            assert!((third_derivative(t, &input, &output, &function) + 6.).abs() < 1e-5);
+        }
+        let h = 1e-2;
+        let acceleration = |t| second_derivative(t, &input, &output, &function);
+        let expected = (acceleration(2.5 + h) - acceleration(2.5 - h)) / (2. * h);
+        assert!((third_derivative(2.5, &input, &output, &function) - expected).abs() < 1e-2);
+        assert_eq!(0., third_derivative(4., &input, &output, &function));
+    }
+
+    #[test]
+    fn test_kinematics_catmull_rom() {
+        let input = vec![0., 1., 2., 3., 4.];
+        let output = vec![1., 0., 1., 0., -1., 0., -1.];
+        let function = InterpolationFunction::CatmullRomSpline;
+        let h = 1e-2;
+        let sample = |t| function.interpolate(t, &input, &output, false);
+        for &t in &[0.5, 1.3, 2.5, 3.7] {
+            let (position, velocity, acceleration) =
+                sample_kinematics(t, &input, &output, &function, false);
+            assert_eq!(sample(t), position);
+            let expected_velocity = (sample(t + h) - sample(t - h)) / (2. * h);
+            let expected_acceleration = (sample(t + h) - 2. * sample(t) + sample(t - h)) / (h * h);
+            assert!((velocity - expected_velocity).abs() < 1e-3);
+            assert!((acceleration - expected_acceleration).abs() < 1e-1);
+        }
+    }
+
+    #[test]
+    fn test_kinematics_step() {
+        let input = vec![0., 1., 2.];
+        let output = vec![0., 1., 2.];
+        assert_eq!(
+            (1., 0., 0.),
+            sample_kinematics(1.5, &input, &output, &InterpolationFunction::Step, false)
+        );
+    }
+}
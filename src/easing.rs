@@ -0,0 +1,125 @@
+/// Easing curve, mapping a parameter in `[0, 1]` to a parameter in `[0, 1]`, with the end points
+/// fixed.
+pub trait Easing {
+    /// Apply the easing to the parameter `t`
+    fn apply(&self, t: f32) -> f32;
+}
+
+/// Easing curve, mapping a parameter in `[0, 1]` to a parameter in `[0, 1]`, with the end points
+/// fixed.
+///
+/// Used to change the timing of an interpolation without changing its path.
+#[derive(Clone, Copy, Debug)]
+pub enum EaseKind {
+    /// No easing, `t`
+    Linear,
+    /// Quadratic ease in, starting slow, `t^2`
+    EaseIn,
+    /// Quadratic ease out, ending slow, `1 - (1 - t)^2`
+    EaseOut,
+    /// Cubic ease in and out, starting and ending slow, `3t^2 - 2t^3`
+    EaseInOut,
+    /// User provided easing function, which should map 0 to 0 and 1 to 1
+    Custom(fn(f32) -> f32),
+}
+
+impl EaseKind {
+    /// Apply the easing to the parameter `t`
+    pub fn apply(&self, t: f32) -> f32 {
+        match *self {
+            EaseKind::Linear => t,
+            EaseKind::EaseIn => t * t,
+            EaseKind::EaseOut => t * (2. - t),
+            EaseKind::EaseInOut => t * t * (3. - 2. * t),
+            EaseKind::Custom(f) => f(t),
+        }
+    }
+}
+
+impl Easing for EaseKind {
+    fn apply(&self, t: f32) -> f32 {
+        EaseKind::apply(self, t)
+    }
+}
+
+/// Easing curve precomputed into a lookup table, for easing functions that are expensive to
+/// evaluate.
+///
+/// The easing function is sampled at evenly spaced parameters covering `[0, 1]`, and evaluated by
+/// linear interpolation between the table entries. Parameters outside of `[0, 1]` are clamped.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LutEasing {
+    table: Vec<f32>,
+}
+
+impl LutEasing {
+    /// Build a lookup table with `entries` samples of `ease`, at least 2 samples are always taken
+    pub fn new<F>(ease: F, entries: usize) -> Self
+    where
+        F: Fn(f32) -> f32,
+    {
+        let last = entries.max(2) - 1;
+        LutEasing {
+            table: (0..=last).map(|i| ease(i as f32 / last as f32)).collect(),
+        }
+    }
+
+    /// Evaluate the easing at the parameter `t`
+    pub fn eval(&self, t: f32) -> f32 {
+        let last = self.table.len() - 1;
+        let position = t.clamp(0., 1.) * last as f32;
+        let index = (position as usize).min(last - 1);
+        let d = position - index as f32;
+        self.table[index] + (self.table[index + 1] - self.table[index]) * d
+    }
+}
+
+impl Easing for LutEasing {
+    fn apply(&self, t: f32) -> f32 {
+        self.eval(t)
+    }
+}
+
+impl<E> Easing for &E
+where
+    E: Easing + ?Sized,
+{
+    fn apply(&self, t: f32) -> f32 {
+        (**self).apply(t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ease_end_points() {
+        let eases = [
+            EaseKind::Linear,
+            EaseKind::EaseIn,
+            EaseKind::EaseOut,
+            EaseKind::EaseInOut,
+            EaseKind::Custom(|t| t * t * t),
+        ];
+        for ease in &eases {
+            assert_eq!(0., ease.apply(0.));
+            assert_eq!(1., ease.apply(1.));
+        }
+        assert_eq!(0.25, EaseKind::EaseIn.apply(0.5));
+        assert_eq!(0.75, EaseKind::EaseOut.apply(0.5));
+        assert_eq!(0.5, EaseKind::EaseInOut.apply(0.5));
+    }
+
+    #[test]
+    fn test_lut_easing() {
+        let smoothstep = |t: f32| t * t * (3. - 2. * t);
+        let lut = LutEasing::new(smoothstep, 256);
+        for i in 0..=1000 {
+            let t = i as f32 / 1000.;
+            assert!((lut.eval(t) - smoothstep(t)).abs() < 1e-4);
+        }
+        assert_eq!(0., lut.eval(-1.));
+        assert_eq!(1., lut.eval(2.));
+    }
+}
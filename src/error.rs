@@ -0,0 +1,48 @@
+use std::error::Error;
+use std::fmt;
+
+/// Errors returned by fallible operations on curves.
+#[derive(Clone, Debug, PartialEq)]
+pub enum InterpolationError {
+    /// The curve outputs are not monotone
+    NotMonotone,
+    /// There are no keyframes
+    EmptyInput,
+    /// The number of output values does not match the number of keyframes
+    OutputLengthMismatch {
+        /// Number of output values expected by the interpolation function
+        expected: usize,
+        /// Actual number of output values
+        found: usize,
+    },
+    /// The input values are not sorted in ascending order, or contain NaN
+    NotSorted,
+    /// A segment index is not smaller than the number of segments
+    SegmentOutOfRange,
+    /// A local segment parameter is outside of `[0, 1]`
+    ParameterOutOfRange,
+    /// Binary curve data has an invalid header, or its length does not match the header
+    InvalidBinaryData,
+}
+
+impl fmt::Display for InterpolationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            InterpolationError::NotMonotone => write!(f, "Curve outputs are not monotone"),
+            InterpolationError::EmptyInput => write!(f, "Curve has no keyframes"),
+            InterpolationError::OutputLengthMismatch { expected, found } => write!(
+                f,
+                "Expected {} output values for the keyframes, found {}",
+                expected, found
+            ),
+            InterpolationError::NotSorted => write!(f, "Curve inputs are not sorted"),
+            InterpolationError::SegmentOutOfRange => write!(f, "Segment index out of range"),
+            InterpolationError::ParameterOutOfRange => {
+                write!(f, "Segment parameter outside of [0, 1]")
+            }
+            InterpolationError::InvalidBinaryData => write!(f, "Invalid binary curve data"),
+        }
+    }
+}
+
+impl Error for InterpolationError {}
@@ -0,0 +1,62 @@
+/// How a curve is sampled outside of the range of its keyframes.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Extrapolation {
+    /// Hold the value of the first or last keyframe
+    #[default]
+    Clamp,
+    /// Repeat the curve, jumping back to the start at the end of the range
+    Repeat,
+    /// Repeat the curve, playing it backwards every other time, so the input value follows a
+    /// triangle wave over the range of the keyframes
+    PingPong,
+}
+
+impl Extrapolation {
+    /// Map an input value into the range `[start, end]` of the keyframes.
+    ///
+    /// Input values inside the range are returned unchanged. For `PingPong` the mapping is
+    /// continuous, including at the turnaround points.
+    pub fn apply(&self, input: f32, start: f32, end: f32) -> f32 {
+        let duration = end - start;
+        if duration <= 0. || (input >= start && input <= end) {
+            return input;
+        }
+        match *self {
+            Extrapolation::Clamp => input.clamp(start, end),
+            Extrapolation::Repeat => start + (input - start).rem_euclid(duration),
+            Extrapolation::PingPong => {
+                let t = (input - start).rem_euclid(2. * duration);
+                if t > duration {
+                    start + 2. * duration - t
+                } else {
+                    start + t
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ping_pong() {
+        let mode = Extrapolation::PingPong;
+        assert_eq!(1.5, mode.apply(1.5, 1., 3.));
+        assert_eq!(2.5, mode.apply(3.5, 1., 3.));
+        assert_eq!(1.5, mode.apply(4.5, 1., 3.));
+        assert_eq!(1.5, mode.apply(0.5, 1., 3.));
+        // Continuous at the turnaround points
+        assert!((mode.apply(5. - 1e-4, 1., 3.) - mode.apply(5. + 1e-4, 1., 3.)).abs() < 1e-3);
+        assert!((mode.apply(3. - 1e-4, 1., 3.) - mode.apply(3. + 1e-4, 1., 3.)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_repeat() {
+        let mode = Extrapolation::Repeat;
+        assert_eq!(1.5, mode.apply(3.5, 1., 3.));
+        assert_eq!(2.5, mode.apply(0.5, 1., 3.));
+    }
+}
@@ -0,0 +1,1201 @@
+use catmull_rom_spline::catmull_tangent;
+use curve::keyframe_output_index;
+use derivative::{derivative, one_sided_derivatives, second_derivative};
+use mint::{Quaternion, Vector3};
+use primitive::{CrossProduct, InterpolationPrimitive};
+use sample_range;
+use std::f32::consts::PI;
+use InterpolationFunction;
+
+/// Generate an offset curve for a 2D curve, i.e. a curve at a fixed perpendicular distance.
+///
+/// The curve is sampled at `samples` evenly spaced input values covering the range of `inputs`,
+/// and each sample is moved `distance` along the unit normal of the curve, which is the
+/// tangent rotated 90 degrees counter clockwise. Where the velocity of the curve is near zero the
+/// normal is undefined, and the previous normal is reused.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `distance`: offset distance, positive values offset to the left of the curve
+/// - `samples`: number of points to generate
+pub fn offset_curve_2d<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    distance: f32,
+    samples: usize,
+) -> Vec<[f32; 2]>
+where
+    T: InterpolationPrimitive + Clone + Into<[f32; 2]>,
+{
+    let points = sample_range(inputs[0], inputs[inputs.len() - 1], samples)
+        .into_iter()
+        .map(|t| {
+            let point: [f32; 2] = function.interpolate(t, inputs, outputs, false).into();
+            let velocity: [f32; 2] = derivative(t, inputs, outputs, function).into();
+            let speed = velocity.magnitude();
+            let normal = if speed > 1e-6 {
+                Some([-velocity[1] / speed, velocity[0] / speed])
+            } else {
+                None
+            };
+            (point, normal)
+        })
+        .collect::<Vec<_>>();
+    let mut previous = points
+        .iter()
+        .filter_map(|&(_, normal)| normal)
+        .next()
+        .unwrap_or([0., 0.]);
+    points
+        .into_iter()
+        .map(|(point, normal)| {
+            let normal = normal.unwrap_or(previous);
+            previous = normal;
+            point.add(&normal.mul(distance))
+        })
+        .collect()
+}
+
+/// Compute the heading of a 2D curve over time, e.g. for rotating a sprite to face along a path.
+///
+/// The curve is sampled at `samples` evenly spaced input values covering the range of `inputs`,
+/// and the heading is the angle of the velocity, `atan2(dy, dx)`. Where the velocity of the curve
+/// is near zero the heading is undefined, and the previous heading is reused. The angles are
+/// unwrapped, so consecutive headings never differ by more than `PI`, and a curve that keeps
+/// turning gives headings beyond `[-PI, PI]` instead of jumping.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `samples`: number of headings to generate
+///
+/// ## Returns
+///
+/// `(input, heading)` pairs, with the heading in radians.
+pub fn heading_track_2d<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    samples: usize,
+) -> Vec<(f32, f32)>
+where
+    T: InterpolationPrimitive + Clone + Into<[f32; 2]>,
+{
+    let headings = sample_range(inputs[0], inputs[inputs.len() - 1], samples)
+        .into_iter()
+        .map(|t| {
+            let velocity: [f32; 2] = derivative(t, inputs, outputs, function).into();
+            let heading = if velocity.magnitude() > 1e-6 {
+                Some(velocity[1].atan2(velocity[0]))
+            } else {
+                None
+            };
+            (t, heading)
+        })
+        .collect::<Vec<_>>();
+    let mut previous = headings
+        .iter()
+        .filter_map(|&(_, heading)| heading)
+        .next()
+        .unwrap_or(0.);
+    headings
+        .into_iter()
+        .map(|(t, heading)| {
+            let heading = heading.map_or(previous, |heading| {
+                // Add the multiple of a full turn closest to the previous heading
+                let turns = ((previous - heading) / (2. * PI)).round();
+                heading + turns * 2. * PI
+            });
+            previous = heading;
+            (t, heading)
+        })
+        .collect()
+}
+
+/// Compute the signed area enclosed by a closed 2D curve.
+///
+/// The curve is sampled at `samples` evenly spaced input values covering the range of `inputs`,
+/// and the area of the resulting polygon is computed with the shoelace formula. This assumes the
+/// curve is closed, i.e. that it ends where it starts; if it isn't, the end points are implicitly
+/// joined by a straight line. The result is positive if the curve winds counter clockwise, and
+/// negative if it winds clockwise.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `samples`: number of points of the polygon
+pub fn enclosed_area_2d<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    samples: usize,
+) -> f32
+where
+    T: InterpolationPrimitive + Clone + Into<[f32; 2]>,
+{
+    let points = sample_range(inputs[0], inputs[inputs.len() - 1], samples)
+        .into_iter()
+        .map(|t| -> [f32; 2] { function.interpolate(t, inputs, outputs, false).into() })
+        .collect::<Vec<_>>();
+    let twice_area: f32 = points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(a, b)| a[0] * b[1] - b[0] * a[1])
+        .sum();
+    twice_area / 2.
+}
+
+/// Compute the winding number of a point with regards to a closed 2D curve.
+///
+/// The curve is sampled at `samples` evenly spaced input values covering the range of `inputs`,
+/// and the winding number is computed for the resulting polygon, assuming the curve is closed as
+/// for `enclosed_area_2d`. The winding number counts how many times the curve goes around the
+/// point counter clockwise, minus the times it goes around clockwise, so a point is inside the
+/// region enclosed by the curve if the winding number is not zero.
+///
+/// ## Parameters:
+///
+/// - `point`: the point to test
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `samples`: number of points of the polygon
+pub fn winding_number_2d<T>(
+    point: [f32; 2],
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    samples: usize,
+) -> i32
+where
+    T: InterpolationPrimitive + Clone + Into<[f32; 2]>,
+{
+    let points = sample_range(inputs[0], inputs[inputs.len() - 1], samples)
+        .into_iter()
+        .map(|t| -> [f32; 2] { function.interpolate(t, inputs, outputs, false).into() })
+        .collect::<Vec<_>>();
+    // Twice the signed area of the triangle `a`, `b`, `point`, positive if `point` is to the left
+    // of the edge from `a` to `b`
+    let side = |a: &[f32; 2], b: &[f32; 2]| {
+        (b[0] - a[0]) * (point[1] - a[1]) - (point[0] - a[0]) * (b[1] - a[1])
+    };
+    points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(a, b)| {
+            if a[1] <= point[1] && b[1] > point[1] && side(a, b) > 0. {
+                1
+            } else if a[1] > point[1] && b[1] <= point[1] && side(a, b) < 0. {
+                -1
+            } else {
+                0
+            }
+        })
+        .sum()
+}
+
+/// Find the inflection points of a 2D curve, where the curve changes from turning left to turning
+/// right or vice versa.
+///
+/// The curve is sampled at `samples` evenly spaced input values covering the range of `inputs`,
+/// and the sign of the curvature is the sign of the cross product of the first and second
+/// derivatives. Where the sign flips between two samples, the input value of the inflection is
+/// estimated by linear interpolation of the cross product. Samples where the curve is straight are
+/// skipped. Note that Catmull-Rom and cubic splines are only C1 continuous, so the curvature can
+/// also flip sign at a keyframe.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `samples`: number of samples
+///
+/// ## Returns
+///
+/// The input values of the inflection points, in order.
+pub fn inflection_points_2d<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    samples: usize,
+) -> Vec<f32>
+where
+    T: InterpolationPrimitive + Clone + Into<[f32; 2]>,
+{
+    let mut inflections = Vec::new();
+    let mut previous: Option<(f32, f32)> = None;
+    for t in sample_range(inputs[0], inputs[inputs.len() - 1], samples) {
+        let v: [f32; 2] = derivative(t, inputs, outputs, function).into();
+        let a: [f32; 2] = second_derivative(t, inputs, outputs, function).into();
+        let cross = v[0] * a[1] - v[1] * a[0];
+        if cross.abs() <= f32::EPSILON {
+            continue;
+        }
+        if let Some((previous_t, previous_cross)) = previous {
+            if (previous_cross < 0.) != (cross < 0.) {
+                let d = previous_cross / (previous_cross - cross);
+                inflections.push(previous_t + (t - previous_t) * d);
+            }
+        }
+        previous = Some((t, cross));
+    }
+    inflections
+}
+
+/// Find the points where a 2D curve crosses itself.
+///
+/// This is an approximation: the curve is sampled at `samples` evenly spaced input values covering
+/// the range of `inputs`, and every pair of non adjacent segments of the resulting polyline is
+/// tested for intersection. More samples give more accurate input values, and find crossings of
+/// small loops that fewer samples would miss. If the curve is closed, the start and end points
+/// touch and are reported as well.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `samples`: number of points of the polyline
+///
+/// ## Returns
+///
+/// The pairs of input values `(a, b)`, with `a < b`, where the curve is at the same point.
+pub fn self_intersections_2d<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    samples: usize,
+) -> Vec<(f32, f32)>
+where
+    T: InterpolationPrimitive + Clone + Into<[f32; 2]>,
+{
+    let times = sample_range(inputs[0], inputs[inputs.len() - 1], samples);
+    let points = times
+        .iter()
+        .map(|&t| function.interpolate(t, inputs, outputs, false).into())
+        .collect::<Vec<[f32; 2]>>();
+    let mut intersections = Vec::new();
+    for i in 0..points.len().saturating_sub(1) {
+        for j in i + 2..points.len() - 1 {
+            if let Some((u, v)) =
+                segment_intersection(points[i], points[i + 1], points[j], points[j + 1])
+            {
+                intersections.push((
+                    times[i] + (times[i + 1] - times[i]) * u,
+                    times[j] + (times[j + 1] - times[j]) * v,
+                ));
+            }
+        }
+    }
+    intersections
+}
+
+/// Find the input values where a 3D curve crosses a plane.
+///
+/// The plane contains the points `P` where `dot(P, plane_normal) == plane_d`. The signed distance
+/// to the plane is sampled at `samples` evenly spaced input values covering the range of `inputs`,
+/// and every sign change between consecutive samples is refined with bisection. Crossings between
+/// two samples that cancel out, e.g. where the curve only just passes through the plane and back,
+/// are missed, so use more samples for curves that wiggle. Samples exactly on the plane are
+/// reported as well, even if the curve only touches the plane there.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `plane_normal`: normal of the plane, not necessarily unit length
+/// - `plane_d`: offset of the plane along the normal
+/// - `samples`: number of samples used to find the crossings
+///
+/// ## Returns
+///
+/// The input values of the crossings, in ascending order.
+pub fn plane_intersections<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    plane_normal: Vector3<f32>,
+    plane_d: f32,
+    samples: usize,
+) -> Vec<f32>
+where
+    T: InterpolationPrimitive + Clone + Into<Vector3<f32>>,
+{
+    let distance = |t: f32| {
+        let point: Vector3<f32> = function.interpolate(t, inputs, outputs, false).into();
+        point.dot(&plane_normal) - plane_d
+    };
+    let samples = sample_range(inputs[0], inputs[inputs.len() - 1], samples)
+        .into_iter()
+        .map(|t| (t, distance(t)))
+        .collect::<Vec<_>>();
+    let mut crossings = Vec::new();
+    for window in samples.windows(2) {
+        let ((t0, d0), (t1, d1)) = (window[0], window[1]);
+        if d0 == 0. {
+            crossings.push(t0);
+        } else if d0 * d1 < 0. {
+            let (mut low, mut high) = (t0, t1);
+            for _ in 0..BISECTION_ITERATIONS {
+                let middle = (low + high) / 2.;
+                if distance(middle) * d0 > 0. {
+                    low = middle;
+                } else {
+                    high = middle;
+                }
+            }
+            crossings.push((low + high) / 2.);
+        }
+    }
+    if let Some(&(t, 0.)) = samples.last() {
+        crossings.push(t);
+    }
+    crossings
+}
+
+// Number of bisection steps used to refine plane crossings.
+const BISECTION_ITERATIONS: usize = 32;
+
+// Local parameters along segments `a0 -> a1` and `b0 -> b1` where they intersect, if they do.
+fn segment_intersection(
+    a0: [f32; 2],
+    a1: [f32; 2],
+    b0: [f32; 2],
+    b1: [f32; 2],
+) -> Option<(f32, f32)> {
+    let cross = |a: [f32; 2], b: [f32; 2]| a[0] * b[1] - a[1] * b[0];
+    let da = a1.sub(&a0);
+    let db = b1.sub(&b0);
+    let denominator = cross(da, db);
+    if denominator.abs() < 1e-12 {
+        return None;
+    }
+    let offset = b0.sub(&a0);
+    let u = cross(offset, db) / denominator;
+    let v = cross(offset, da) / denominator;
+    if (0. ..=1.).contains(&u) && (0. ..=1.).contains(&v) {
+        Some((u, v))
+    } else {
+        None
+    }
+}
+
+/// Calculate the curvature of a curve, i.e. the inverse of the radius of the osculating circle.
+///
+/// `k = sqrt(|v|^2 |a|^2 - (v . a)^2) / |v|^3`, where `v` and `a` are the first and second
+/// derivatives of the curve, which works for curves of any dimension. Where the velocity of the
+/// curve is zero, the curvature is undefined, and zero is returned.
+///
+/// ## Parameters:
+///
+/// - `input`: the input value to the function
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+pub fn curvature<T>(
+    input: f32,
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+) -> f32
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let velocity = derivative(input, inputs, outputs, function);
+    let acceleration = second_derivative(input, inputs, outputs, function);
+    let speed2 = velocity.magnitude2();
+    if speed2 <= T::EPSILON * T::EPSILON {
+        return 0.;
+    }
+    let dot = velocity.dot(&acceleration);
+    let cross2 = (speed2 * acceleration.magnitude2() - dot * dot).max(0.);
+    cross2.sqrt() / (speed2 * speed2.sqrt())
+}
+
+/// Calculate the osculating circle of a curve, i.e. the circle that best fits the curve at a point.
+///
+/// The radius is the inverse of the `curvature`, and the center is that far from the point on the
+/// curve, along the normal, which is the second derivative made perpendicular to the tangent, as
+/// for the Frenet frame. This works for curves of any dimension. On straight parts of the curve,
+/// where the curvature is near zero, the circle is degenerate: the radius is infinite and the
+/// center is the point on the curve.
+///
+/// ## Parameters:
+///
+/// - `input`: the input value to the function
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+///
+/// ## Returns
+///
+/// `(center, radius)`, where the radius is infinite if the curvature is near zero.
+pub fn osculating_circle<T>(
+    input: f32,
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+) -> (T, f32)
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let point = function.interpolate(input, inputs, outputs, false);
+    let k = curvature(input, inputs, outputs, function);
+    if k <= T::EPSILON {
+        return (point, f32::INFINITY);
+    }
+    let velocity = derivative(input, inputs, outputs, function);
+    let acceleration = second_derivative(input, inputs, outputs, function);
+    let tangent = velocity.normalize();
+    let normal = acceleration
+        .sub(&tangent.mul(acceleration.dot(&tangent)))
+        .normalize();
+    (point.add(&normal.mul(1. / k)), 1. / k)
+}
+
+/// Calculate the end points of the tangent handles of each keyframe, for drawing in an editor.
+///
+/// The in handle is at `p - in_tangent * handle_length` and the out handle at
+/// `p + out_tangent * handle_length`, where `p` is the value of the keyframe. For cubic spline
+/// interpolation the stored tangents are used, for Catmull-Rom spline interpolation the tangents
+/// computed from the neighbouring keyframes, and for other functions the derivative of the curve
+/// on either side of the keyframe, which is zero outside of the curve.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `handle_length`: scale of the tangents
+///
+/// ## Returns
+///
+/// `(in_handle, out_handle)` for each keyframe.
+pub fn tangent_handles<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    handle_length: f32,
+) -> Vec<(T, T)>
+where
+    T: InterpolationPrimitive + Clone,
+{
+    (0..inputs.len())
+        .map(|i| {
+            let (in_tangent, out_tangent) = match *function {
+                InterpolationFunction::CubicSpline => {
+                    (outputs[i * 3].clone(), outputs[i * 3 + 2].clone())
+                }
+                InterpolationFunction::CatmullRomSpline => {
+                    let tangent = catmull_tangent(i, inputs, outputs);
+                    (tangent.clone(), tangent)
+                }
+                _ => one_sided_derivatives(inputs[i], inputs, outputs, function),
+            };
+            let p = &outputs[keyframe_output_index(function, i)];
+            (
+                p.sub(&in_tangent.mul(handle_length)),
+                p.add(&out_tangent.mul(handle_length)),
+            )
+        })
+        .collect()
+}
+
+/// Calculate the Frenet frame of a 3D curve, i.e. the unit tangent, normal and binormal.
+///
+/// The tangent is the direction of the first derivative, the normal is the second derivative made
+/// perpendicular to the tangent, and the binormal is the cross product of the two.
+///
+/// On straight parts of the curve and at inflection points the second derivative is parallel to
+/// the tangent (or zero), and the normal is undefined. In that case the normal falls back to the
+/// cross product of the tangent and the world axis least aligned with it, so the frame stays
+/// orthonormal but may flip compared to the neighbouring frames. Where the velocity of the curve
+/// is zero, the tangent falls back to the direction of the second derivative, or the x axis.
+///
+/// ## Parameters:
+///
+/// - `input`: the input value to the function
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+///
+/// ## Returns
+///
+/// `(tangent, normal, binormal)`
+pub fn frenet_frame<T>(
+    input: f32,
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+) -> (T, T, T)
+where
+    T: CrossProduct + Clone + From<[f32; 3]>,
+{
+    let velocity = derivative(input, inputs, outputs, function);
+    let acceleration = second_derivative(input, inputs, outputs, function);
+    let tangent = velocity
+        .try_normalize()
+        .or_else(|| acceleration.try_normalize())
+        .unwrap_or_else(|| T::from([1., 0., 0.]));
+    let normal = acceleration
+        .sub(&tangent.mul(acceleration.dot(&tangent)))
+        .try_normalize()
+        .unwrap_or_else(|| tangent.cross(&least_aligned_axis(&tangent)).normalize());
+    let binormal = tangent.cross(&normal);
+    (tangent, normal, binormal)
+}
+
+/// Sample the position on a 3D curve, and the rotation that looks from there towards a target.
+///
+/// The rotation turns the local `-Z` axis towards `target`, and the local `Y` axis as close to
+/// `up` as possible, which is the usual convention for cameras. If `up` is parallel to the view
+/// direction, the world axis least aligned with the view direction is used instead. If the
+/// position is at the target the view direction is undefined, and the identity rotation is
+/// returned.
+///
+/// ## Parameters:
+///
+/// - `input`: the input value to the function
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of positions, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `target`: the point to look at
+/// - `up`: the up direction of the viewer
+///
+/// ## Returns
+///
+/// `(position, rotation)`
+pub fn sample_with_lookat(
+    input: f32,
+    inputs: &[f32],
+    outputs: &[Vector3<f32>],
+    function: &InterpolationFunction<Vector3<f32>>,
+    target: Vector3<f32>,
+    up: Vector3<f32>,
+) -> (Vector3<f32>, Quaternion<f32>) {
+    let position = function.interpolate(input, inputs, outputs, false);
+    let back = match position.sub(&target).try_normalize() {
+        Some(back) => back,
+        None => {
+            let identity = Quaternion::from([0., 0., 0., 1.]);
+            return (position, identity);
+        }
+    };
+    let right = up
+        .cross(&back)
+        .try_normalize()
+        .unwrap_or_else(|| least_aligned_axis(&back).cross(&back).normalize());
+    let up = back.cross(&right);
+    (position, quaternion_from_basis(&right, &up, &back))
+}
+
+// Rotation that maps the x, y and z axes to the given orthonormal basis vectors.
+fn quaternion_from_basis(x: &Vector3<f32>, y: &Vector3<f32>, z: &Vector3<f32>) -> Quaternion<f32> {
+    let trace = x.x + y.y + z.z;
+    let (qx, qy, qz, w) = if trace > 0. {
+        let s = (trace + 1.).sqrt() * 2.;
+        ((y.z - z.y) / s, (z.x - x.z) / s, (x.y - y.x) / s, s / 4.)
+    } else if x.x > y.y && x.x > z.z {
+        let s = (1. + x.x - y.y - z.z).sqrt() * 2.;
+        (s / 4., (y.x + x.y) / s, (z.x + x.z) / s, (y.z - z.y) / s)
+    } else if y.y > z.z {
+        let s = (1. + y.y - x.x - z.z).sqrt() * 2.;
+        ((y.x + x.y) / s, s / 4., (z.y + y.z) / s, (z.x - x.z) / s)
+    } else {
+        let s = (1. + z.z - x.x - y.y).sqrt() * 2.;
+        ((z.x + x.z) / s, (z.y + y.z) / s, s / 4., (x.y - y.x) / s)
+    };
+    Quaternion::from([qx, qy, qz, w])
+}
+
+/// Calculate rotation minimizing frames along a 3D curve, using the double reflection method.
+///
+/// Unlike the Frenet frame, a rotation minimizing frame does not flip at inflection points, which
+/// makes it suitable for sweeping geometry like ribbons and tubes along a curve. Each frame is
+/// propagated from the previous one, so the whole sequence is computed at once, at `samples`
+/// evenly spaced input values covering the range of `inputs`. The first frame is the Frenet frame
+/// at the start of the curve.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `samples`: number of frames to generate
+///
+/// ## Returns
+///
+/// `(tangent, normal, binormal)` for each sample
+pub fn rotation_minimizing_frames<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    samples: usize,
+) -> Vec<(T, T, T)>
+where
+    T: CrossProduct + Clone + From<[f32; 3]>,
+{
+    let times = sample_range(inputs[0], inputs[inputs.len() - 1], samples);
+    let mut frames = Vec::with_capacity(times.len());
+    if times.is_empty() {
+        return frames;
+    }
+    let mut point = function.interpolate(times[0], inputs, outputs, false);
+    let (mut tangent, mut normal, binormal) = frenet_frame(times[0], inputs, outputs, function);
+    frames.push((tangent.clone(), normal.clone(), binormal));
+    for &t in &times[1..] {
+        let next_point = function.interpolate(t, inputs, outputs, false);
+        let velocity = derivative(t, inputs, outputs, function);
+        let next_tangent = velocity.try_normalize().unwrap_or_else(|| tangent.clone());
+        // Reflect the frame in the plane bisecting the two points
+        let v1 = next_point.sub(&point);
+        let (reflected_normal, reflected_tangent) = reflect(&v1, &normal, &tangent);
+        // Reflect again to align the tangents
+        let v2 = next_tangent.sub(&reflected_tangent);
+        let (next_normal, _) = reflect(&v2, &reflected_normal, &reflected_tangent);
+        let next_binormal = next_tangent.cross(&next_normal);
+        frames.push((next_tangent.clone(), next_normal.clone(), next_binormal));
+        point = next_point;
+        tangent = next_tangent;
+        normal = next_normal;
+    }
+    frames
+}
+
+// Number of power iterations used by `principal_direction`.
+const POWER_ITERATIONS: usize = 64;
+
+/// Calculate the principal direction of a set of points, i.e. the direction in which the points
+/// are spread the most.
+///
+/// This is the dominant eigenvector of the covariance matrix of the points, found using power
+/// iteration. The direction is oriented to point from the first towards the last point where
+/// possible. If all points are equal, there is no principal direction and the zero vector is
+/// returned.
+///
+/// ## Parameters:
+///
+/// - `outputs`: the points, e.g. the keyframe positions of a track
+pub fn principal_direction(outputs: &[Vector3<f32>]) -> Vector3<f32> {
+    let zero = Vector3::from([0., 0., 0.]);
+    if outputs.is_empty() {
+        return zero;
+    }
+    let mean = outputs
+        .iter()
+        .fold(zero, |sum, p| sum.add(p))
+        .mul(1. / outputs.len() as f32);
+    let centered = outputs.iter().map(|p| p.sub(&mean)).collect::<Vec<_>>();
+    // Start from the point furthest from the mean, which is rarely orthogonal to the result
+    let mut direction = centered.iter().fold(zero, |best, p| {
+        if p.magnitude2() > best.magnitude2() {
+            *p
+        } else {
+            best
+        }
+    });
+    if direction.magnitude2() < 1e-12 {
+        return zero;
+    }
+    for _ in 0..POWER_ITERATIONS {
+        // Multiply by the covariance matrix, without building it
+        let next = centered
+            .iter()
+            .fold(zero, |sum, p| sum.add(&p.mul(p.dot(&direction))));
+        if next.magnitude2() < 1e-24 {
+            break;
+        }
+        direction = next.normalize();
+    }
+    let direction = direction.normalize();
+    if direction.dot(&outputs[outputs.len() - 1].sub(&outputs[0])) < 0. {
+        direction.mul(-1.)
+    } else {
+        direction
+    }
+}
+
+/// Compute a bounding sphere of a curve, e.g. for culling an object following the curve.
+///
+/// The curve is sampled at `samples` evenly spaced input values covering the range of `inputs`,
+/// and a sphere enclosing all samples is found using Ritter's algorithm. The sphere is not the
+/// smallest possible, but usually only a few percent larger. Distances are measured using
+/// `sub().magnitude2().sqrt()`, so this works for scalar curves too.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `samples`: number of points to sample, more samples give a tighter fit between keyframes
+///
+/// ## Returns
+///
+/// `(center, radius)` of the sphere.
+pub fn bounding_sphere<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    samples: usize,
+) -> (T, f32)
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let points = sample_range(inputs[0], inputs[inputs.len() - 1], samples.max(1))
+        .into_iter()
+        .map(|t| function.interpolate(t, inputs, outputs, false))
+        .collect::<Vec<_>>();
+    let distance = |a: &T, b: &T| a.sub(b).magnitude2().sqrt();
+    let furthest = |from: usize| {
+        (0..points.len()).fold(from, |best, i| {
+            if distance(&points[from], &points[i]) > distance(&points[from], &points[best]) {
+                i
+            } else {
+                best
+            }
+        })
+    };
+    let a = furthest(0);
+    let b = furthest(a);
+    let mut center = points[a].add(&points[b]).mul(0.5);
+    let mut radius = distance(&points[a], &points[b]) / 2.;
+    for p in &points {
+        let d = distance(&center, p);
+        if d > radius {
+            // Grow the sphere just enough to touch `p` on the far side
+            let new_radius = (radius + d) / 2.;
+            center = center.add(&p.sub(&center).mul((new_radius - radius) / d));
+            radius = new_radius;
+        }
+    }
+    (center, radius)
+}
+
+/// Compute the centroid of a curve, i.e. its average position weighted by arc length.
+///
+/// Unlike the average of the keyframe values, or of samples evenly spaced in time, the centroid
+/// only depends on the shape of the curve and not on how fast the curve moves along it, which
+/// makes it a good pivot point for an object following the curve. The integral of the position
+/// times the speed, `|derivative|`, is computed with the trapezoidal rule over `samples` evenly
+/// spaced input values, and divided by the length of the curve. If the curve has no length, the
+/// value at the first keyframe is returned.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `samples`: number of samples, at least 2 are always used
+pub fn centroid<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    samples: usize,
+) -> T
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let points = sample_range(inputs[0], inputs[inputs.len() - 1], samples.max(2))
+        .into_iter()
+        .map(|t| {
+            let speed = derivative(t, inputs, outputs, function).magnitude2().sqrt();
+            (t, function.interpolate(t, inputs, outputs, false), speed)
+        })
+        .collect::<Vec<_>>();
+    let mut sum = points[0].1.mul(0.);
+    let mut length = 0.;
+    for w in points.windows(2) {
+        let dt = (w[1].0 - w[0].0) / 2.;
+        sum = sum
+            .add(&w[0].1.mul(w[0].2 * dt))
+            .add(&w[1].1.mul(w[1].2 * dt));
+        length += (w[0].2 + w[1].2) * dt;
+    }
+    if length <= f32::EPSILON {
+        points[0].1.clone()
+    } else {
+        sum.mul(1. / length)
+    }
+}
+
+// Reflect `a` and `b` in the plane with normal `v`.
+fn reflect<T>(v: &T, a: &T, b: &T) -> (T, T)
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let c = v.dot(v);
+    if c < 1e-12 {
+        (a.clone(), b.clone())
+    } else {
+        (
+            a.sub(&v.mul(2. / c * v.dot(a))),
+            b.sub(&v.mul(2. / c * v.dot(b))),
+        )
+    }
+}
+
+// World axis with the smallest absolute dot product with `v`.
+fn least_aligned_axis<T>(v: &T) -> T
+where
+    T: InterpolationPrimitive + From<[f32; 3]>,
+{
+    let axes = [[1., 0., 0.], [0., 1., 0.], [0., 0., 1.]];
+    let mut best = T::from(axes[0]);
+    let mut best_dot = v.dot(&best).abs();
+    for axis in &axes[1..] {
+        let axis = T::from(*axis);
+        let dot = v.dot(&axis).abs();
+        if dot < best_dot {
+            best = axis;
+            best_dot = dot;
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mint::{Vector2, Vector3};
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+
+    #[test]
+    fn test_offset_line() {
+        let input = vec![0., 1., 2.];
+        let output = vec![
+            Vector2::from([0., 0.]),
+            Vector2::from([1., 1.]),
+            Vector2::from([2., 2.]),
+        ];
+        let offset = offset_curve_2d(&input, &output, &InterpolationFunction::Linear, 2., 9);
+        assert_eq!(9, offset.len());
+        let shift = [-2. * 0.5f32.sqrt(), 2. * 0.5f32.sqrt()];
+        for (i, point) in offset.iter().enumerate() {
+            let t = i as f32 * 0.25;
+            assert!((point[0] - (t + shift[0])).abs() < 1e-5);
+            assert!((point[1] - (t + shift[1])).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_plane_intersections() {
+        let input = vec![0., 1., 2.];
+        let output = vec![
+            Vector3::from([0., 0., -1.]),
+            Vector3::from([1., 0., 1.]),
+            Vector3::from([2., 0., -1.]),
+        ];
+        let function = InterpolationFunction::Linear;
+        let normal = Vector3::from([0., 0., 2.]);
+        let crossings = plane_intersections(&input, &output, &function, normal, 0., 10);
+        assert_eq!(2, crossings.len());
+        assert!((crossings[0] - 0.5).abs() < 1e-5);
+        assert!((crossings[1] - 1.5).abs() < 1e-5);
+        let crossings = plane_intersections(&input, &output, &function, normal, 4., 10);
+        assert!(crossings.is_empty());
+    }
+
+    #[test]
+    fn test_self_intersections_figure_eight() {
+        let input = vec![0., 1., 2., 3., 4., 5., 6.];
+        let output = vec![
+            Vector2::from([-2., -2.]),
+            Vector2::from([-1., -1.]),
+            Vector2::from([1., 1.]),
+            Vector2::from([2., 0.]),
+            Vector2::from([1., -1.]),
+            Vector2::from([-1., 1.]),
+            Vector2::from([-2., 0.]),
+            Vector2::from([-1.2, -0.8]),
+            Vector2::from([0., 0.]),
+        ];
+        let function = InterpolationFunction::CatmullRomSpline;
+        let intersections = self_intersections_2d(&input, &output, &function, 200);
+        assert_eq!(1, intersections.len());
+        let (a, b) = intersections[0];
+        assert!(a > 0. && a < 1. && b > 3. && b < 4.);
+        let point_a = function.interpolate(a, &input, &output, false);
+        let point_b = function.interpolate(b, &input, &output, false);
+        assert!(point_a.sub(&point_b).magnitude() < 1e-3);
+        assert!(point_a.magnitude() < 0.2);
+    }
+
+    #[test]
+    fn test_frenet_frame_helix() {
+        let input = (0..9).map(|i| i as f32).collect::<Vec<_>>();
+        let mut output = input
+            .iter()
+            .map(|&t| Vector3::from([(t * 0.8).cos(), (t * 0.8).sin(), t * 0.3]))
+            .collect::<Vec<_>>();
+        let first = output[0];
+        let last = output[output.len() - 1];
+        output.insert(0, first);
+        output.push(last);
+        let function = InterpolationFunction::CatmullRomSpline;
+        for &t in &[0.5, 2.25, 4., 6.7] {
+            let (tangent, normal, binormal) = frenet_frame(t, &input, &output, &function);
+            for v in &[tangent, normal, binormal] {
+                assert!((v.magnitude() - 1.).abs() < 1e-4);
+            }
+            assert!(tangent.dot(&normal).abs() < 1e-4);
+            assert!(tangent.dot(&binormal).abs() < 1e-4);
+            assert!(normal.dot(&binormal).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_rotation_minimizing_frames_inflection() {
+        let input = vec![0., 1., 2., 3., 4.];
+        let output = vec![
+            Vector3::from([1., 1., 0.]),
+            Vector3::from([0., 0., 0.]),
+            Vector3::from([1., 1., 0.]),
+            Vector3::from([2., 0., 0.]),
+            Vector3::from([3., -1., 0.]),
+            Vector3::from([4., 0., 0.]),
+            Vector3::from([1., 1., 0.]),
+        ];
+        let frames = rotation_minimizing_frames(
+            &input,
+            &output,
+            &InterpolationFunction::CatmullRomSpline,
+            64,
+        );
+        assert_eq!(64, frames.len());
+        for pair in frames.windows(2) {
+            assert!(pair[0].1.dot(&pair[1].1) > 0.95);
+            assert!(pair[0].2.dot(&pair[1].2) > 0.95);
+        }
+        for (tangent, normal, binormal) in &frames {
+            assert!(tangent.dot(normal).abs() < 1e-3);
+            assert!((binormal.magnitude() - 1.).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_principal_direction() {
+        let points = vec![
+            Vector3::from([-4., 0.2, 0.]),
+            Vector3::from([-2., -0.3, 0.1]),
+            Vector3::from([0., 0.1, -0.2]),
+            Vector3::from([1., -0.1, 0.3]),
+            Vector3::from([3., 0.2, -0.1]),
+            Vector3::from([5., -0.1, 0.]),
+        ];
+        let direction = principal_direction(&points);
+        assert!(direction.sub(&Vector3::from([1., 0., 0.])).magnitude() < 0.05);
+        assert_eq!(
+            Vector3::from([0., 0., 0.]),
+            principal_direction(&points[..1])
+        );
+    }
+
+    #[test]
+    fn test_tangent_handles() {
+        let input = vec![0., 1., 2.5, 3.];
+        let output = vec![
+            Vector2::from([1., 0.]),
+            Vector2::from([0., 0.]),
+            Vector2::from([1., 2.]),
+            Vector2::from([3., 1.]),
+            Vector2::from([4., 4.]),
+            Vector2::from([0., 1.]),
+        ];
+        let function = InterpolationFunction::CatmullRomSpline;
+        let handles = tangent_handles(&input, &output, &function, 0.5);
+        assert_eq!(4, handles.len());
+        for (i, &(in_handle, out_handle)) in handles.iter().enumerate() {
+            let p = &output[i + 1];
+            let tangent = derivative(input[i], &input, &output, &function);
+            let expected = tangent.mul(0.5);
+            assert!(out_handle.sub(p).sub(&expected).magnitude() < 1e-5);
+            assert!(p.sub(&in_handle).sub(&expected).magnitude() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_enclosed_area_circle() {
+        let tangent = [0., ::std::f32::consts::SQRT_2];
+        let input = (0..9).map(|i| i as f32).collect::<Vec<_>>();
+        let mut output = vec![tangent];
+        output.extend(input.iter().map(|&t| {
+            let angle = t * ::std::f32::consts::FRAC_PI_4;
+            [2. * angle.cos(), 2. * angle.sin()]
+        }));
+        output.push(tangent);
+        let function = InterpolationFunction::CatmullRomSpline;
+        let area = enclosed_area_2d(&input, &output, &function, 200);
+        let expected = ::std::f32::consts::PI * 4.;
+        assert!((area - expected).abs() < expected * 0.02);
+        let mut reversed = output.iter().rev().cloned().collect::<Vec<_>>();
+        reversed[0] = tangent.mul(-1.);
+        reversed[10] = tangent.mul(-1.);
+        assert!((enclosed_area_2d(&input, &reversed, &function, 200) + area).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_heading_track_half_turn() {
+        let input = (0..5).map(|i| i as f32).collect::<Vec<_>>();
+        let mut output = vec![[0., FRAC_PI_4]];
+        output.extend(input.iter().map(|&t| {
+            let angle = t * FRAC_PI_4;
+            [angle.cos(), angle.sin()]
+        }));
+        output.push([0., -FRAC_PI_4]);
+        let function = InterpolationFunction::CatmullRomSpline;
+        let headings = heading_track_2d(&input, &output, &function, 41);
+        assert_eq!(41, headings.len());
+        assert!((headings[0].1 - FRAC_PI_2).abs() < 1e-5);
+        assert!(headings.windows(2).all(|w| w[1].1 > w[0].1));
+        assert!((headings[40].1 - 3. * FRAC_PI_2).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_osculating_circle_arc() {
+        let step = FRAC_PI_4 / 4.;
+        let input = (0..9).map(|i| i as f32).collect::<Vec<_>>();
+        let mut output = vec![Vector2::from([0., 2. * step])];
+        output.extend(input.iter().map(|&t| {
+            let angle = t * step;
+            Vector2::from([2. * angle.cos(), 2. * angle.sin()])
+        }));
+        output.push(Vector2::from([-2. * step, 0.]));
+        let function = InterpolationFunction::CatmullRomSpline;
+        let (center, radius) = osculating_circle(4.5, &input, &output, &function);
+        assert!((radius - 2.).abs() < 0.05);
+        assert!(center.magnitude() < 0.05);
+        let line = vec![Vector2::from([0., 0.]), Vector2::from([1., 1.])];
+        let (center, radius) =
+            osculating_circle(0.5, &[0., 1.], &line, &InterpolationFunction::Linear);
+        assert_eq!(f32::INFINITY, radius);
+        assert_eq!(Vector2::from([0.5, 0.5]), center);
+    }
+
+    #[test]
+    fn test_sample_with_lookat() {
+        // Rotate `v` by the unit quaternion `q`
+        let rotate = |q: Quaternion<f32>, v: Vector3<f32>| {
+            let t = q.v.cross(&v).mul(2.);
+            v.add(&t.mul(q.s)).add(&q.v.cross(&t))
+        };
+        let input = vec![0., 1.];
+        let output = vec![Vector3::from([0., 0., 5.]), Vector3::from([4., 2., 1.])];
+        let function = InterpolationFunction::Linear;
+        let target = Vector3::from([1., -1., 0.]);
+        let up = Vector3::from([0., 1., 0.]);
+        for &t in &[0., 0.3, 1.] {
+            let (position, rotation) =
+                sample_with_lookat(t, &input, &output, &function, target, up);
+            assert_eq!(function.interpolate(t, &input, &output, false), position);
+            let forward = rotate(rotation, Vector3::from([0., 0., -1.]));
+            let expected = target.sub(&position).normalize();
+            assert!(forward.sub(&expected).magnitude() < 1e-5);
+            // The local up axis is in the plane of the up vector and the view direction
+            let local_up = rotate(rotation, up);
+            assert!(local_up.dot(&up) > 0.);
+            assert!(local_up.dot(&up.cross(&expected)).abs() < 1e-5);
+        }
+        let (_, rotation) = sample_with_lookat(0., &input, &output, &function, output[0], up);
+        assert_eq!(Quaternion::from([0., 0., 0., 1.]), rotation);
+        let (_, rotation) = sample_with_lookat(
+            0.,
+            &input,
+            &output,
+            &function,
+            Vector3::from([0., 3., 5.]),
+            up,
+        );
+        let forward = rotate(rotation, Vector3::from([0., 0., -1.]));
+        assert!(forward.sub(&up).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn test_winding_number_loop() {
+        let tangent = [0., ::std::f32::consts::SQRT_2];
+        let input = (0..9).map(|i| i as f32).collect::<Vec<_>>();
+        let mut output = vec![tangent];
+        output.extend(input.iter().map(|&t| {
+            let angle = t * FRAC_PI_4;
+            [2. + 2. * angle.cos(), 1. + 2. * angle.sin()]
+        }));
+        output.push(tangent);
+        let function = InterpolationFunction::CatmullRomSpline;
+        assert_eq!(
+            1,
+            winding_number_2d([2., 1.], &input, &output, &function, 100)
+        );
+        assert_eq!(
+            1,
+            winding_number_2d([3.5, 0.], &input, &output, &function, 100)
+        );
+        assert_eq!(
+            0,
+            winding_number_2d([5., 1.], &input, &output, &function, 100)
+        );
+        assert_eq!(
+            0,
+            winding_number_2d([0., 4.], &input, &output, &function, 100)
+        );
+        let mut reversed = output.iter().rev().cloned().collect::<Vec<_>>();
+        reversed[0] = tangent.mul(-1.);
+        reversed[10] = tangent.mul(-1.);
+        assert_eq!(
+            -1,
+            winding_number_2d([2., 1.], &input, &reversed, &function, 100)
+        );
+    }
+
+    #[test]
+    fn test_bounding_sphere() {
+        let input = vec![0., 1., 2., 3., 4., 5.];
+        let output = vec![
+            Vector3::from([3., 1., 0.]),
+            Vector3::from([1., 3., 0.]),
+            Vector3::from([-1., 1., 0.]),
+            Vector3::from([1., 1., 2.]),
+            Vector3::from([1., -1., 0.]),
+            Vector3::from([3., 1., 0.]),
+        ];
+        let function = InterpolationFunction::Linear;
+        let (center, radius) = bounding_sphere(&input, &output, &function, 101);
+        assert!(radius >= 2. - 1e-5);
+        assert!(radius < 2.2);
+        for p in &output {
+            assert!(p.sub(&center).magnitude() <= radius + 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_inflection_points_s_curve() {
+        let input = vec![0., 1., 2., 3.];
+        let output = vec![[1., 2.], [0., 0.], [1., 1.], [2., -1.], [3., 0.], [1., 2.]];
+        let function = InterpolationFunction::CatmullRomSpline;
+        let inflections = inflection_points_2d(&input, &output, &function, 100);
+        assert_eq!(1, inflections.len());
+        assert!((inflections[0] - 1.5).abs() < 1e-3);
+        let arc = vec![[0., 1.], [0., 0.], [1., 1.], [2., 0.], [0., -1.]];
+        let inflections = inflection_points_2d(&input[..3], &arc, &function, 100);
+        assert!(inflections.is_empty());
+    }
+
+    #[test]
+    fn test_centroid_ignores_timing() {
+        // Slow on the first part of the line, fast on the rest
+        let input = vec![0., 3., 4.];
+        let output = vec![[0., 1.], [1., 1.], [4., 1.]];
+        let function = InterpolationFunction::Linear;
+        let center = centroid(&input, &output, &function, 1001);
+        assert!((center[0] - 2.).abs() < 1e-2);
+        assert!((center[1] - 1.).abs() < 1e-4);
+    }
+}
@@ -28,13 +28,72 @@ extern crate num;
 #[macro_use]
 extern crate serde;
 
-pub use catmull_rom_spline::catmull_rom_spline_interpolate;
-pub use cubic_spline::cubic_spline_interpolate;
-pub use linear::linear_interpolate;
-pub use primitive::InterpolationPrimitive;
+#[cfg(feature = "derive")]
+extern crate minterpolate_derive;
+
+#[cfg(test)]
+#[macro_use]
+extern crate proptest;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
+
+pub use analysis::{
+    arc_length_between, average_over, compare_functions, extrema, instance_transforms_along,
+    invert_monotone, precision_warning, rebase_time, segment_nonlinearity, speed_extrema,
+    time_at_length_fraction, total_curvature, total_variation, CurvePoint,
+};
+pub use bspline::{nurbs_interpolate, nurbs_like_interpolate};
+pub use catmull_rom_spline::{
+    auto_smooth_interpolate, catmull_rom_spline_interpolate,
+    catmull_rom_spline_interpolate_uniform, eased_spline_interpolate, smooth_interpolate,
+};
+pub use cubic_spline::{
+    cubic_spline_interpolate, elevate_to_cubic, enforce_c1, periodic_cubic_spline, segment_data,
+    weighted_spline, weighted_tangent_interpolate,
+};
+pub use curve::{
+    blend_with_curve, concat, convert_mode, curves_approx_eq, dedup_close_keyframes,
+    derivative_curve, enforce_min_spacing, insert_keyframe, merge_channels_vec3, offset_outputs,
+    retime, scale_outputs, split_at, Curve,
+};
+pub use derivative::{
+    derivative, derivative_many_into, sample_kinematics, second_derivative, third_derivative,
+};
+pub use easing::{EaseKind, Easing, LutEasing};
+pub use error::InterpolationError;
+pub use extrapolation::Extrapolation;
+pub use fixed_time::interpolate_fixed;
+pub use geometry::{
+    bounding_sphere, centroid, curvature, enclosed_area_2d, frenet_frame, heading_track_2d,
+    inflection_points_2d, offset_curve_2d, osculating_circle, plane_intersections,
+    principal_direction, rotation_minimizing_frames, sample_with_lookat, self_intersections_2d,
+    tangent_handles, winding_number_2d,
+};
+pub use linear::{lerp_pair, lerp_unclamped, linear_interpolate, linear_interpolate_uniform};
+#[cfg(feature = "derive")]
+pub use minterpolate_derive::InterpolationPrimitive;
+pub use polynomial::{to_coeff_buffer, to_polynomial_coeffs};
+pub use primitive::{CrossProduct, InterpolationPrimitive};
+pub use quantize::{Quantize, QuantizedTrack};
 pub use quasi_spherical_linear::quasi_spherical_linear_interpolate;
-pub use spherical_linear::spherical_linear_interpolate;
-pub use step::step_interpolate;
+pub use registry::{CustomFunction, FunctionRegistry};
+pub use resample::{preview_polyline, resample_with_tolerance};
+pub use rotation_curve::{validate_unit_quaternions, RotationCurve};
+pub use run_length::RunLengthTrack;
+pub use sample::{
+    accumulate_sample, bake, bake_frames, sample_clamped_flag, sample_cow, sample_debug,
+    sample_many, sample_many_into, sample_or, sample_segment_local, sample_snap_to_keyframes,
+    sample_snapped, sample_with_hold, SampleInfo,
+};
+pub use sampler::{DeltaSampler, Sampler};
+#[cfg(feature = "simd")]
+pub use simd::cubic_spline_interpolate_simd;
+pub use simplify::{find_plateaus, remove_collinear};
+pub use spherical_linear::{
+    slerp_with_metric, spherical_linear_interpolate, spherical_linear_interpolate_deterministic,
+};
+pub use step::{step_interpolate, step_interpolate_uniform};
+pub use time_remap::TimeRemap;
 
 mod primitive;
 mod linear;
@@ -43,7 +102,35 @@ mod step;
 mod cubic_spline;
 mod catmull_rom_spline;
 mod quasi_spherical_linear;
+mod curve;
+mod derivative;
+mod geometry;
+mod analysis;
+mod polynomial;
+mod resample;
+mod sample;
+mod simplify;
+mod error;
+mod sampler;
+mod quantize;
+mod extrapolation;
+#[cfg(feature = "simd")]
+mod simd;
+mod registry;
+mod time_remap;
+mod rotation_curve;
+mod bspline;
+mod fixed_time;
+mod easing;
+mod run_length;
 
+use catmull_rom_spline::catmull_rom_spline_segment;
+use cubic_spline::cubic_spline_segment;
+use curve::keyframe_output_index;
+use linear::linear_segment;
+use quasi_spherical_linear::quasi_spherical_linear_segment;
+use spherical_linear::slerp_segment;
+use std::cmp::Ordering;
 use std::fmt;
 
 /// Calculate the keyframe index in the input collection
@@ -63,8 +150,8 @@ pub fn get_input_index(input: f32, inputs: &[f32]) -> Option<usize> {
     } else {
         Some(
             inputs
-                .binary_search_by(|v| v.partial_cmp(&input).unwrap())
-                .unwrap_or_else(|index| index - 1),
+                .binary_search_by(|v| v.partial_cmp(&input).unwrap_or(Ordering::Less))
+                .unwrap_or_else(|index| index.saturating_sub(1)),
         )
     }
 }
@@ -95,14 +182,98 @@ pub fn get_interpolation_factor(input: f32, inputs: &[f32]) -> Option<(usize, f3
     })
 }
 
+/// Position of an input value relative to the keyframes of a curve, see `resolve_segment`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Segment {
+    /// Before the first keyframe
+    Before,
+    /// Inside the segment starting at keyframe `index`, at `t_local` from 0 at the start of the
+    /// segment towards 1 at its end
+    In { index: usize, t_local: f32 },
+    /// At or after the last keyframe, or a NaN input value
+    After,
+}
+
+// Find the segment containing `input`. This defines the boundary behavior of all interpolation
+// functions: inputs before the first keyframe give `Before`, inputs at or after the last keyframe
+// give `After`, and everything else is inside a segment, including the first keyframe.
+pub(crate) fn resolve_segment(input: f32, inputs: &[f32]) -> Segment {
+    segment_at(get_input_index(input, inputs), input, inputs)
+}
+
+// Same as `resolve_segment`, using an already known keyframe index, as returned by
+// `get_input_index`.
+pub(crate) fn segment_at(index: Option<usize>, input: f32, inputs: &[f32]) -> Segment {
+    match index {
+        None => Segment::Before,
+        Some(index) if index >= inputs.len() - 1 => Segment::After,
+        Some(index) => Segment::In {
+            index,
+            t_local: (input - inputs[index]) / (inputs[index + 1] - inputs[index]),
+        },
+    }
+}
+
+/// Snap an input value to the nearest keyframe, if it is close enough.
+///
+/// ### Parameters
+///
+/// - `input`: the input value to snap
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `threshold`: maximum distance from a keyframe for `input` to snap to it
+///
+/// ### Returns
+///
+/// The input value of the nearest keyframe if it is within `threshold` of `input`, else `input`
+/// unchanged.
+///
+pub fn snap_to_keyframe(input: f32, inputs: &[f32], threshold: f32) -> f32 {
+    // The nearest keyframe is one of the keyframes bracketing `input`
+    let candidates = match get_input_index(input, inputs) {
+        Some(index) => &inputs[index..(index + 2).min(inputs.len())],
+        None => &inputs[..1],
+    };
+    let distance = |t: f32| (t - input).abs();
+    let nearest = candidates.iter().cloned().min_by(|&a, &b| {
+        distance(a)
+            .partial_cmp(&distance(b))
+            .unwrap_or(Ordering::Equal)
+    });
+    match nearest {
+        Some(t) if distance(t) <= threshold => t,
+        _ => input,
+    }
+}
+
+// Same as `get_input_index`, for `count` keyframes with input values `i * spacing`.
+pub(crate) fn get_uniform_input_index(input: f32, spacing: f32, count: usize) -> Option<usize> {
+    if input < 0. {
+        None
+    } else {
+        Some(((input / spacing) as usize).min(count - 1))
+    }
+}
+
+// Evenly spaced input values from `start` to `end`, including both end points.
+pub(crate) fn sample_range(start: f32, end: f32, samples: usize) -> Vec<f32> {
+    match samples {
+        0 => vec![],
+        1 => vec![start],
+        _ => (0..samples)
+            .map(|i| start + (end - start) * i as f32 / (samples - 1) as f32)
+            .collect(),
+    }
+}
+
 /// Supported interpolation functions
-#[derive(Clone)]
+#[derive(Clone, Default)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum InterpolationFunction<T>
 where
     T: InterpolationPrimitive,
 {
     /// Linear interpolation
+    #[default]
     Linear,
     /// Spherical linear interpolation
     SphericalLinear,
@@ -123,6 +294,11 @@ impl<T> InterpolationFunction<T>
 where
     T: InterpolationPrimitive + Clone,
 {
+    /// Interpolate the keyframe data at `input`.
+    ///
+    /// The data is assumed to be valid, see `validate`, this panics if `inputs` is empty or
+    /// `outputs` does not have the length expected by the function. Unsorted inputs and non finite
+    /// values do not panic, but give meaningless results.
     pub fn interpolate(&self, input: f32, inputs: &[f32], outputs: &[T], normalize: bool) -> T {
         match *self {
             InterpolationFunction::Linear => linear_interpolate(input, inputs, outputs, normalize),
@@ -142,6 +318,99 @@ where
             InterpolationFunction::Function(ref f) => f(input, inputs, outputs, normalize),
         }
     }
+
+    /// Same as `interpolate`, but the data is validated first, see `validate`.
+    pub fn try_interpolate(
+        &self,
+        input: f32,
+        inputs: &[f32],
+        outputs: &[T],
+        normalize: bool,
+    ) -> Result<T, InterpolationError> {
+        self.validate(inputs, outputs)?;
+        Ok(self.interpolate(input, inputs, outputs, normalize))
+    }
+
+    /// Check that keyframe data can be interpolated using this function.
+    ///
+    /// `inputs` must not be empty, must not contain NaN and must be sorted in ascending order, and
+    /// `outputs` must have the length expected by the function. The length of the outputs of a
+    /// generic function is not checked.
+    pub fn validate(&self, inputs: &[f32], outputs: &[T]) -> Result<(), InterpolationError> {
+        if inputs.is_empty() {
+            return Err(InterpolationError::EmptyInput);
+        }
+        let expected = match *self {
+            InterpolationFunction::CubicSpline => Some(inputs.len() * 3),
+            InterpolationFunction::CatmullRomSpline => Some(inputs.len() + 2),
+            InterpolationFunction::Function(_) => None,
+            _ => Some(inputs.len()),
+        };
+        match expected {
+            Some(expected) if expected != outputs.len() => {
+                Err(InterpolationError::OutputLengthMismatch {
+                    expected,
+                    found: outputs.len(),
+                })
+            }
+            _ if inputs.iter().any(|t| t.is_nan()) || inputs.windows(2).any(|w| w[0] > w[1]) => {
+                Err(InterpolationError::NotSorted)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Interpolate using an already known keyframe index, as returned by `get_input_index`.
+    pub(crate) fn interpolate_at(
+        &self,
+        index: Option<usize>,
+        input: f32,
+        inputs: &[f32],
+        outputs: &[T],
+        normalize: bool,
+    ) -> T {
+        if let InterpolationFunction::Function(ref f) = *self {
+            return f(input, inputs, outputs, normalize);
+        }
+        self.interpolate_segment(segment_at(index, input, inputs), inputs, outputs, normalize)
+    }
+
+    // Interpolate at an already resolved segment, this panics for generic functions, which can
+    // only be called with the input value.
+    pub(crate) fn interpolate_segment(
+        &self,
+        segment: Segment,
+        inputs: &[f32],
+        outputs: &[T],
+        normalize: bool,
+    ) -> T {
+        let (index, d) = match segment {
+            Segment::Before => return outputs[keyframe_output_index(self, 0)].clone(),
+            Segment::After => {
+                return outputs[keyframe_output_index(self, inputs.len() - 1)].clone()
+            }
+            Segment::In { index, t_local } => (index, t_local),
+        };
+        match *self {
+            InterpolationFunction::Linear => linear_segment(d, index, outputs, normalize),
+            InterpolationFunction::SphericalLinear => {
+                slerp_segment(d, index, outputs, |a, b| a.dot(b), normalize)
+            }
+            InterpolationFunction::QuasiSphericalLinear => {
+                quasi_spherical_linear_segment(d, index, outputs, normalize)
+            }
+            InterpolationFunction::Step => outputs[index].clone(),
+            InterpolationFunction::CubicSpline => {
+                cubic_spline_segment(d, index, inputs, outputs, normalize)
+            }
+            InterpolationFunction::CatmullRomSpline => {
+                catmull_rom_spline_segment(d, index, inputs, outputs, normalize)
+            }
+            InterpolationFunction::Function(_) => {
+                panic!("Generic functions can't be interpolated at a segment")
+            }
+        }
+    }
 }
 
 impl<T> fmt::Debug for InterpolationFunction<T>
@@ -178,3 +447,232 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use curve::keyframe_output_index;
+    use mint::Quaternion;
+
+    #[test]
+    fn test_default_function() {
+        assert_eq!(
+            InterpolationFunction::Linear,
+            InterpolationFunction::<f32>::default()
+        );
+    }
+
+    #[test]
+    fn test_snap_to_keyframe() {
+        let inputs = [0., 1., 2., 4.];
+        assert_eq!(1., snap_to_keyframe(1.05, &inputs, 0.1));
+        assert_eq!(2., snap_to_keyframe(1.95, &inputs, 0.1));
+        assert_eq!(0., snap_to_keyframe(-0.05, &inputs, 0.1));
+        assert_eq!(4., snap_to_keyframe(4.05, &inputs, 0.1));
+        assert_eq!(3., snap_to_keyframe(3., &inputs, 0.1));
+        assert_eq!(1.5, snap_to_keyframe(1.5, &inputs, 0.1));
+    }
+
+    #[test]
+    fn test_validate() {
+        let function = InterpolationFunction::CatmullRomSpline;
+        assert_eq!(
+            Err(InterpolationError::EmptyInput),
+            function.validate(&[], &[0., 1.])
+        );
+        assert_eq!(
+            Err(InterpolationError::OutputLengthMismatch {
+                expected: 4,
+                found: 2
+            }),
+            function.try_interpolate(0.5, &[0., 1.], &[0., 1.], false)
+        );
+        assert_eq!(
+            Err(InterpolationError::NotSorted),
+            function.validate(&[1., 0.], &[0., 0., 1., 0.])
+        );
+        assert_eq!(Ok(()), function.validate(&[0., 1.], &[0., 0., 1., 0.]));
+    }
+
+    #[test]
+    fn test_resolve_segment() {
+        let inputs = [0., 1., 3.];
+        assert_eq!(Segment::Before, resolve_segment(-0.5, &inputs));
+        assert_eq!(
+            Segment::In {
+                index: 0,
+                t_local: 0.
+            },
+            resolve_segment(0., &inputs)
+        );
+        assert_eq!(
+            Segment::In {
+                index: 1,
+                t_local: 0.25
+            },
+            resolve_segment(1.5, &inputs)
+        );
+        assert_eq!(Segment::After, resolve_segment(3., &inputs));
+        assert_eq!(Segment::After, resolve_segment(4., &inputs));
+        assert_eq!(Segment::After, resolve_segment(f32::NAN, &inputs));
+        assert_eq!(Segment::After, resolve_segment(0., &[0.]));
+    }
+
+    #[test]
+    fn test_boundaries_all_functions() {
+        let inputs = [0., 0.5, 2.];
+        let keys = [[0., 0., 0., 1.], [0., 0.6, 0., 0.8], [0.8, 0., 0., 0.6]];
+        let zero = [0.; 4];
+        for function_index in 0..6 {
+            let function = function::<[f32; 4]>(function_index);
+            let outputs = match function {
+                InterpolationFunction::CubicSpline => keys
+                    .iter()
+                    .flat_map(|&key| vec![zero, key, zero])
+                    .collect::<Vec<_>>(),
+                InterpolationFunction::CatmullRomSpline => {
+                    vec![zero, keys[0], keys[1], keys[2], zero]
+                }
+                _ => keys.to_vec(),
+            };
+            let sample = |input| function.interpolate(input, &inputs, &outputs, false);
+            assert_eq!(keys[0], sample(-1.));
+            assert_eq!(keys[2], sample(2.));
+            assert_eq!(keys[2], sample(3.));
+            assert_eq!(keys[2], sample(f32::NAN));
+            for (&input, key) in inputs.iter().zip(&keys) {
+                assert!(sample(input).sub(key).magnitude() < 1e-6);
+            }
+            for &input in &[-1., 0., 0.25, 0.5, 1.9, 2., 3.] {
+                let index = get_input_index(input, &inputs);
+                assert_eq!(
+                    sample(input),
+                    function.interpolate_at(index, input, &inputs, &outputs, false)
+                );
+            }
+        }
+    }
+
+    fn function<T: InterpolationPrimitive>(index: usize) -> InterpolationFunction<T> {
+        match index {
+            0 => InterpolationFunction::Linear,
+            1 => InterpolationFunction::SphericalLinear,
+            2 => InterpolationFunction::QuasiSphericalLinear,
+            3 => InterpolationFunction::Step,
+            4 => InterpolationFunction::CatmullRomSpline,
+            _ => InterpolationFunction::CubicSpline,
+        }
+    }
+
+    fn any_f32() -> impl proptest::strategy::Strategy<Value = f32> {
+        prop_oneof![proptest::num::f32::ANY, -10f32..10., -1e6f32..1e6]
+    }
+
+    proptest! {
+        #[test]
+        fn prop_interpolate_never_panics(
+            function_index in 0..6usize,
+            inputs in proptest::collection::vec(any_f32(), 0..6),
+            values in proptest::collection::vec(any_f32(), 0..20),
+            input in any_f32(),
+            normalize in proptest::bool::ANY,
+        ) {
+            let function = function::<f32>(function_index);
+            match function.try_interpolate(input, &inputs, &values, normalize) {
+                Ok(_) => prop_assert!(function.validate(&inputs, &values).is_ok()),
+                Err(_) => prop_assert!(function.validate(&inputs, &values).is_err()),
+            }
+        }
+
+        #[test]
+        fn prop_interpolate_invariants(
+            function_index in 0..6usize,
+            start in -1e3f32..1e3,
+            steps in proptest::collection::vec(1e-2f32..1e2, 1..6),
+            values in proptest::collection::vec(-1e3f32..1e3, 18),
+            queries in proptest::collection::vec(-1e4f32..1e4, 1..8),
+        ) {
+            let function = function::<f32>(function_index);
+            let mut inputs = vec![start];
+            for step in steps {
+                let last = inputs[inputs.len() - 1];
+                inputs.push(last + step);
+            }
+            let len = match function {
+                InterpolationFunction::CubicSpline => inputs.len() * 3,
+                InterpolationFunction::CatmullRomSpline => inputs.len() + 2,
+                _ => inputs.len(),
+            };
+            let outputs = &values[..len];
+            // Keyframe hits return the keyframe values
+            for (i, &input) in inputs.iter().enumerate() {
+                let expected = outputs[keyframe_output_index(&function, i)];
+                let value = function.interpolate(input, &inputs, outputs, false);
+                prop_assert!((value - expected).abs() <= 1e-3, "{} != {}", value, expected);
+            }
+            // Linear and step interpolation stay within the hull of the values, also for queries
+            // outside of the keyframes
+            if matches!(function, InterpolationFunction::Linear | InterpolationFunction::Step) {
+                let min = outputs.iter().cloned().fold(f32::INFINITY, f32::min);
+                let max = outputs.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+                for &input in &queries {
+                    let value = function.interpolate(input, &inputs, outputs, false);
+                    prop_assert!(value >= min - 1e-3 && value <= max + 1e-3);
+                }
+            }
+        }
+
+        #[test]
+        fn prop_interpolate_unchecked_never_panics(
+            function_index in 0..6usize,
+            inputs in proptest::collection::vec(any_f32(), 1..6),
+            values in proptest::collection::vec(any_f32(), 18),
+            queries in proptest::collection::vec(any_f32(), 0..6),
+            spacing in any_f32(),
+            normalize in proptest::bool::ANY,
+        ) {
+            // Output lengths match the function, everything else may be invalid
+            let function = function::<f32>(function_index);
+            let len = match function {
+                InterpolationFunction::CubicSpline => inputs.len() * 3,
+                InterpolationFunction::CatmullRomSpline => inputs.len() + 2,
+                _ => inputs.len(),
+            };
+            let outputs = &values[..len];
+            for &input in &queries {
+                function.interpolate(input, &inputs, outputs, normalize);
+                smooth_interpolate(input, &inputs, &values[..inputs.len()], normalize);
+                linear_interpolate_uniform(input, &values[..inputs.len()], spacing, normalize);
+                step_interpolate_uniform(input, &values[..inputs.len()], spacing, normalize);
+                catmull_rom_spline_interpolate_uniform(
+                    input,
+                    &values[..inputs.len() + 2],
+                    spacing,
+                    normalize,
+                );
+            }
+            let samples = sample_many(&inputs, outputs, &function, &queries, normalize);
+            prop_assert_eq!(queries.len(), samples.len());
+        }
+
+        #[test]
+        fn prop_quaternion_interpolate_never_panics(
+            function_index in 0..6usize,
+            values in proptest::collection::vec(any_f32(), 40),
+            input in any_f32(),
+        ) {
+            let inputs = [0., 0.5, 2.];
+            let outputs = values
+                .chunks(4)
+                .map(|c| Quaternion::from([c[0], c[1], c[2], c[3]]))
+                .collect::<Vec<_>>();
+            let function = function(function_index);
+            let len = match function {
+                InterpolationFunction::CubicSpline => 9,
+                InterpolationFunction::CatmullRomSpline => 5,
+                _ => 3,
+            };
+            prop_assert!(function.try_interpolate(input, &inputs, &outputs[..len], true).is_ok());
+        }
+    }
+}
@@ -1,5 +1,6 @@
-use get_input_index;
+use get_uniform_input_index;
 use primitive::InterpolationPrimitive;
+use {resolve_segment, Segment};
 
 /// Do linear interpolation.
 ///
@@ -15,23 +16,99 @@ use primitive::InterpolationPrimitive;
 /// - `input`: the input value to the function
 /// - `inputs`: list of discrete input values for each keyframe
 /// - `outputs`: list of output values to interpolate between, for linear interpolation this should
-///              be the same size as `inputs`
+///   be the same size as `inputs`
 /// - `normalize`: if true, normalize the interpolated value before returning it
 pub fn linear_interpolate<T>(input: f32, inputs: &[f32], outputs: &[T], normalize: bool) -> T
 where
     T: InterpolationPrimitive + Clone,
 {
-    let input_index = match get_input_index(input, inputs) {
+    match resolve_segment(input, inputs) {
+        Segment::Before => outputs[0].clone(),
+        Segment::After => outputs[outputs.len() - 1].clone(),
+        Segment::In { index, t_local } => linear_segment(t_local, index, outputs, normalize),
+    }
+}
+
+// Interpolate at `d` inside the segment starting at keyframe `index`.
+pub(crate) fn linear_segment<T>(d: f32, index: usize, outputs: &[T], normalize: bool) -> T
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let left = &outputs[index];
+    let right = &outputs[index + 1];
+    let v = left.add(&right.sub(left).mul(d));
+    if normalize {
+        v.normalize()
+    } else {
+        v
+    }
+}
+
+/// Linearly interpolate between two values, with `t` clamped to `[0, 1]`.
+///
+/// `f(t) = a + (b - a) * t`
+///
+/// ## Parameters:
+///
+/// - `a`: the value at `t = 0`
+/// - `b`: the value at `t = 1`
+/// - `t`: the interpolation factor, clamped to `[0, 1]`
+/// - `normalize`: if true, normalize the interpolated value before returning it
+pub fn lerp_pair<T>(a: &T, b: &T, t: f32, normalize: bool) -> T
+where
+    T: InterpolationPrimitive,
+{
+    lerp_unclamped(a, b, t.clamp(0., 1.), normalize)
+}
+
+/// Linearly interpolate between two values, extrapolating past them for `t` outside of `[0, 1]`.
+///
+/// `f(t) = a + (b - a) * t`
+///
+/// ## Parameters:
+///
+/// - `a`: the value at `t = 0`
+/// - `b`: the value at `t = 1`
+/// - `t`: the interpolation factor
+/// - `normalize`: if true, normalize the interpolated value before returning it
+pub fn lerp_unclamped<T>(a: &T, b: &T, t: f32, normalize: bool) -> T
+where
+    T: InterpolationPrimitive,
+{
+    let v = a.add(&b.sub(a).mul(t));
+    if normalize {
+        v.normalize()
+    } else {
+        v
+    }
+}
+
+/// Do linear interpolation, for keyframes with uniformly spaced input values.
+///
+/// Same as `linear_interpolate`, with the input value of keyframe `i` being `i * spacing`, without
+/// the need for an `inputs` list.
+///
+/// ## Parameters:
+///
+/// - `input`: the input value to the function
+/// - `outputs`: list of output values to interpolate between, one for each keyframe
+/// - `spacing`: distance between the input values of two consecutive keyframes
+/// - `normalize`: if true, normalize the interpolated value before returning it
+pub fn linear_interpolate_uniform<T>(input: f32, outputs: &[T], spacing: f32, normalize: bool) -> T
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let index = match get_uniform_input_index(input, spacing, outputs.len()) {
         Some(index) => index,
         None => return outputs[0].clone(),
     };
-    if input_index >= (inputs.len() - 1) {
+    if index >= (outputs.len() - 1) {
         outputs[outputs.len() - 1].clone()
     } else {
-        let d = (input - inputs[input_index]) / (inputs[input_index + 1] - inputs[input_index]);
-        let left = &outputs[input_index];
-        let right = &outputs[input_index + 1];
-        let v = left.add(&right.sub(&left).mul(d));
+        let d = input / spacing - index as f32;
+        let left = &outputs[index];
+        let right = &outputs[index + 1];
+        let v = left.add(&right.sub(left).mul(d));
         if normalize {
             v.normalize()
         } else {
@@ -108,4 +185,47 @@ mod tests {
             linear_interpolate(0.5, &input, &output, true)
         );
     }
+
+    #[test]
+    fn test_linear_uniform() {
+        let input = vec![0., 0.5, 1., 1.5, 2.];
+        let output = vec![
+            Vector3::from([0., 0., 0.]),
+            Vector3::from([1., 0., 0.]),
+            Vector3::from([0., 2., 0.]),
+            Vector3::from([-1., 0., 0.]),
+            Vector3::from([0., 0., 3.]),
+        ];
+        for &t in &[-1., 0., 0.2, 0.5, 0.8, 1.3, 1.9, 2., 3.] {
+            let expected = linear_interpolate(t, &input, &output, false);
+            let actual = linear_interpolate_uniform(t, &output, 0.5, false);
+            assert!(expected.sub(&actual).magnitude() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_lerp_unclamped() {
+        let a = Vector3::from([1., 0., 0.]);
+        let b = Vector3::from([3., 2., 0.]);
+        assert_eq!(
+            Vector3::from([0., -1., 0.]),
+            lerp_unclamped(&a, &b, -0.5, false)
+        );
+        assert_eq!(
+            Vector3::from([2., 1., 0.]),
+            lerp_unclamped(&a, &b, 0.5, false)
+        );
+        assert_eq!(
+            Vector3::from([4., 3., 0.]),
+            lerp_unclamped(&a, &b, 1.5, false)
+        );
+    }
+
+    #[test]
+    fn test_lerp_pair_clamped() {
+        let (a, b) = (1f32, 3.);
+        assert_eq!(1., lerp_pair(&a, &b, -0.5, false));
+        assert_eq!(2., lerp_pair(&a, &b, 0.5, false));
+        assert_eq!(3., lerp_pair(&a, &b, 1.5, false));
+    }
 }
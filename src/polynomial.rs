@@ -0,0 +1,201 @@
+use cubic_spline::hermite_segment;
+use curve::keyframe_output_index;
+use primitive::InterpolationPrimitive;
+use InterpolationFunction;
+
+/// Convert a curve to piecewise cubic polynomials.
+///
+/// Each segment is converted to the coefficients `[a, b, c, d]` of the polynomial
+/// `f(u) = a + b*u + c*u^2 + d*u^3`, where `u = (t - t0) / (t1 - t0)` is the local parameter of
+/// the segment, so the curve can be evaluated using Horner's scheme without any tangent
+/// computations.
+///
+/// Spline, linear and step interpolation convert exactly. Spherical linear and custom functions
+/// are not polynomial, and are approximated by linear interpolation between the keyframes.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+///
+/// ## Returns
+///
+/// The coefficients for each segment, `inputs.len() - 1` in total.
+pub fn to_polynomial_coeffs<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+) -> Vec<[T; 4]>
+where
+    T: InterpolationPrimitive + Clone,
+{
+    (0..inputs.len().saturating_sub(1))
+        .map(|index| segment_coeffs(index, inputs, outputs, function))
+        .collect()
+}
+
+/// Convert a curve to piecewise cubic polynomials, in flat buffers suitable for uploading to the
+/// GPU.
+///
+/// The coefficients are the same as for `to_polynomial_coeffs`, with each coefficient written as
+/// its `component_count()` components, see `InterpolationPrimitive::write_components`. Segment `i`
+/// occupies `4 * component_count()` values starting at `i * 4 * component_count()`, in the order
+/// `a, b, c, d`. Panics if `T` does not expose its components.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+///
+/// ## Returns
+///
+/// `(coefficients, times)`, where `times` are the input values at the segment boundaries, so
+/// segment `i` covers the range `[times[i], times[i + 1]]`.
+pub fn to_coeff_buffer<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+) -> (Vec<f32>, Vec<f32>)
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let components = T::component_count();
+    assert!(components > 0, "Can not write a type without components");
+    let coeffs = to_polynomial_coeffs(inputs, outputs, function);
+    let mut buffer = vec![0.; coeffs.len() * 4 * components];
+    for (coeff, out) in coeffs
+        .iter()
+        .flat_map(|c| c.iter())
+        .zip(buffer.chunks_mut(components))
+    {
+        coeff.write_components(out);
+    }
+    (buffer, inputs.to_vec())
+}
+
+fn segment_coeffs<T>(
+    index: usize,
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+) -> [T; 4]
+where
+    T: InterpolationPrimitive + Clone,
+{
+    match hermite_segment(index, inputs, outputs, function) {
+        Some((p0, p1, m0, m1)) => [
+            p0.clone(),
+            m0.clone(),
+            p0.mul(-3.).sub(&m0.mul(2.)).add(&p1.mul(3.)).sub(&m1),
+            p0.mul(2.).add(&m0).sub(&p1.mul(2.)).add(&m1),
+        ],
+        None => {
+            let p0 = outputs[keyframe_output_index(function, index)].clone();
+            let p1 = outputs[keyframe_output_index(function, index + 1)].clone();
+            let zero = p0.mul(0.);
+            let slope = if *function == InterpolationFunction::Step {
+                zero.clone()
+            } else {
+                p1.sub(&p0)
+            };
+            [p0, slope, zero.clone(), zero]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mint::Vector3;
+
+    fn horner(coeffs: &[Vector3<f32>; 4], u: f32) -> Vector3<f32> {
+        coeffs[3]
+            .mul(u)
+            .add(&coeffs[2])
+            .mul(u)
+            .add(&coeffs[1])
+            .mul(u)
+            .add(&coeffs[0])
+    }
+
+    fn assert_reproduces(
+        inputs: &[f32],
+        outputs: &[Vector3<f32>],
+        function: &InterpolationFunction<Vector3<f32>>,
+    ) {
+        let coeffs = to_polynomial_coeffs(inputs, outputs, function);
+        assert_eq!(inputs.len() - 1, coeffs.len());
+        for i in 0..100 {
+            let t = inputs[inputs.len() - 1] * i as f32 / 100.;
+            let index = inputs.iter().rposition(|&input| input <= t).unwrap();
+            let u = (t - inputs[index]) / (inputs[index + 1] - inputs[index]);
+            let expected = function.interpolate(t, inputs, outputs, false);
+            assert!(horner(&coeffs[index], u).sub(&expected).magnitude() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_coeffs_catmull_rom() {
+        let input = vec![0., 1., 2.5, 3.];
+        let output = vec![
+            Vector3::from([1., 0., 0.]),
+            Vector3::from([0., 0., 0.]),
+            Vector3::from([1., 2., 0.]),
+            Vector3::from([0., 1., 3.]),
+            Vector3::from([-1., 0., 0.]),
+            Vector3::from([0., 0., 1.]),
+        ];
+        assert_reproduces(&input, &output, &InterpolationFunction::CatmullRomSpline);
+    }
+
+    #[test]
+    fn test_coeffs_cubic_and_linear() {
+        let input = vec![0., 1., 3.];
+        let output = vec![
+            Vector3::from([0., 0., 0.]),
+            Vector3::from([0., 0., 0.]),
+            Vector3::from([1., 0., 1.]),
+            Vector3::from([0., 1., 0.]),
+            Vector3::from([1., 1., 1.]),
+            Vector3::from([-2., 0., 0.]),
+            Vector3::from([0., 0., 0.]),
+            Vector3::from([3., 0., -1.]),
+            Vector3::from([0., 0., 0.]),
+        ];
+        assert_reproduces(&input, &output, &InterpolationFunction::CubicSpline);
+        assert_reproduces(&input, &output[..3], &InterpolationFunction::Linear);
+    }
+
+    #[test]
+    fn test_coeff_buffer() {
+        let input = vec![0., 0.5, 2.];
+        let output = vec![
+            Vector3::from([1., 0., 0.]),
+            Vector3::from([0., 0., 0.]),
+            Vector3::from([1., 2., 0.]),
+            Vector3::from([0., 1., 3.]),
+            Vector3::from([-1., 0., 0.]),
+        ];
+        let function = InterpolationFunction::CatmullRomSpline;
+        let (buffer, times) = to_coeff_buffer(&input, &output, &function);
+        assert_eq!(input, times);
+        assert_eq!(2 * 4 * 3, buffer.len());
+        for i in 0..100 {
+            let t = 2. * i as f32 / 100.;
+            let index = if t < times[1] { 0 } else { 1 };
+            let u = (t - times[index]) / (times[index + 1] - times[index]);
+            let segment = &buffer[index * 12..(index + 1) * 12];
+            let value = (0..3)
+                .map(|c| {
+                    let coeff = |k: usize| segment[k * 3 + c];
+                    ((coeff(3) * u + coeff(2)) * u + coeff(1)) * u + coeff(0)
+                })
+                .collect::<Vec<_>>();
+            let expected = function.interpolate(t, &input, &output, false);
+            let value = Vector3::from([value[0], value[1], value[2]]);
+            assert!(value.sub(&expected).magnitude() < 1e-4);
+        }
+    }
+}
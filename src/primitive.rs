@@ -1,18 +1,77 @@
-use mint::{Quaternion, Vector3};
+use mint::{Quaternion, Vector2, Vector3};
 
 /// Interpolation primitive, defines basic arithmetic needed for interpolation.
 pub trait InterpolationPrimitive: Sized {
+    /// Magnitude below which a value is considered to be zero, e.g. when normalizing
+    ///
+    /// Integer types use `0.5`, as their values change in whole steps, so anything smaller than
+    /// one step is zero, while a fixed small threshold would be meaningless after rounding.
+    const EPSILON: f32 = 1e-6;
     fn add(&self, other: &Self) -> Self;
     fn sub(&self, other: &Self) -> Self;
     fn mul(&self, scalar: f32) -> Self;
     fn dot(&self, other: &Self) -> f32;
     fn magnitude2(&self) -> f32;
+    /// Number of `f32` components used to represent a value
+    ///
+    /// The default is 0, for types that do not expose their components. Such types can't be
+    /// rounded with `round_to`, encoded with `Curve::to_bytes` or written with `to_coeff_buffer`.
+    fn component_count() -> usize {
+        0
+    }
+    /// Write the components of the value to the start of `out`
+    fn write_components(&self, _out: &mut [f32]) {}
+    /// Create a value from the components at the start of `src`
+    ///
+    /// Panics by default, see `component_count`.
+    fn from_components(_src: &[f32]) -> Self {
+        panic!("Type does not expose its components")
+    }
+    /// If true, `v` and `-v` represent the same value, as for quaternion rotations, so spherical
+    /// linear interpolation negates one end point when needed to take the shortest path
+    fn antipodal_equivalent() -> bool {
+        true
+    }
+    /// If true, all components of the value are finite, i.e. neither infinite nor NaN
+    fn is_finite(&self) -> bool {
+        if Self::component_count() == 0 {
+            // Infinite and NaN components stay NaN when multiplied by zero
+            return self.mul(0.).magnitude2() == 0.;
+        }
+        let mut components = vec![0.; Self::component_count()];
+        self.write_components(&mut components);
+        components.iter().all(|&c| c.is_finite())
+    }
+    /// Round each component of the value to the nearest multiple of `grid`
+    ///
+    /// Panics if the type does not expose its components, see `component_count`.
+    fn round_to(&self, grid: f32) -> Self {
+        let mut components = vec![0.; Self::component_count()];
+        self.write_components(&mut components);
+        for c in &mut components {
+            *c = (*c / grid).round() * grid;
+        }
+        Self::from_components(&components)
+    }
     fn magnitude(&self) -> f32 {
         self.magnitude2().sqrt()
     }
     fn normalize(&self) -> Self {
         self.mul(1. / self.magnitude())
     }
+    /// Normalize the value, or `None` if its magnitude is not above `EPSILON`
+    fn try_normalize(&self) -> Option<Self> {
+        if self.magnitude2() <= Self::EPSILON * Self::EPSILON {
+            None
+        } else {
+            Some(self.normalize())
+        }
+    }
+}
+
+/// Cross product, for three dimensional interpolation primitives.
+pub trait CrossProduct: InterpolationPrimitive {
+    fn cross(&self, other: &Self) -> Self;
 }
 
 impl InterpolationPrimitive for Vector3<f32> {
@@ -47,6 +106,86 @@ impl InterpolationPrimitive for Vector3<f32> {
     fn magnitude2(&self) -> f32 {
         self.dot(self)
     }
+
+    fn component_count() -> usize {
+        3
+    }
+
+    fn write_components(&self, out: &mut [f32]) {
+        out[0] = self.x;
+        out[1] = self.y;
+        out[2] = self.z;
+    }
+
+    fn from_components(src: &[f32]) -> Self {
+        Vector3 {
+            x: src[0],
+            y: src[1],
+            z: src[2],
+        }
+    }
+}
+
+impl CrossProduct for Vector3<f32> {
+    fn cross(&self, other: &Self) -> Self {
+        Vector3 {
+            x: self.y * other.z - self.z * other.y,
+            y: self.z * other.x - self.x * other.z,
+            z: self.x * other.y - self.y * other.x,
+        }
+    }
+}
+
+impl InterpolationPrimitive for Vector2<f32> {
+    fn add(&self, other: &Self) -> Self {
+        Vector2 {
+            x: self.x + other.x,
+            y: self.y + other.y,
+        }
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        Vector2 {
+            x: self.x - other.x,
+            y: self.y - other.y,
+        }
+    }
+
+    fn mul(&self, other: f32) -> Self {
+        Vector2 {
+            x: self.x * other,
+            y: self.y * other,
+        }
+    }
+
+    fn dot(&self, other: &Self) -> f32 {
+        (self.x * other.x) + (self.y * other.y)
+    }
+
+    fn magnitude2(&self) -> f32 {
+        self.dot(self)
+    }
+
+    fn component_count() -> usize {
+        2
+    }
+
+    fn write_components(&self, out: &mut [f32]) {
+        out[0] = self.x;
+        out[1] = self.y;
+    }
+
+    fn from_components(src: &[f32]) -> Self {
+        Vector2 {
+            x: src[0],
+            y: src[1],
+        }
+    }
+
+    // Unit complex numbers are 2D rotations, as for `[f32; 2]`
+    fn antipodal_equivalent() -> bool {
+        false
+    }
 }
 
 impl InterpolationPrimitive for Quaternion<f32> {
@@ -78,6 +217,24 @@ impl InterpolationPrimitive for Quaternion<f32> {
     fn magnitude2(&self) -> f32 {
         self.dot(self)
     }
+
+    fn component_count() -> usize {
+        4
+    }
+
+    fn write_components(&self, out: &mut [f32]) {
+        out[0] = self.v.x;
+        out[1] = self.v.y;
+        out[2] = self.v.z;
+        out[3] = self.s;
+    }
+
+    fn from_components(src: &[f32]) -> Self {
+        Quaternion {
+            v: Vector3::from_components(src),
+            s: src[3],
+        }
+    }
 }
 
 impl InterpolationPrimitive for [f32; 4] {
@@ -115,6 +272,18 @@ impl InterpolationPrimitive for [f32; 4] {
     fn magnitude2(&self) -> f32 {
         self.dot(self)
     }
+
+    fn component_count() -> usize {
+        4
+    }
+
+    fn write_components(&self, out: &mut [f32]) {
+        out[..4].copy_from_slice(self);
+    }
+
+    fn from_components(src: &[f32]) -> Self {
+        [src[0], src[1], src[2], src[3]]
+    }
 }
 
 impl InterpolationPrimitive for [f32; 3] {
@@ -137,6 +306,68 @@ impl InterpolationPrimitive for [f32; 3] {
     fn magnitude2(&self) -> f32 {
         self.dot(self)
     }
+
+    fn component_count() -> usize {
+        3
+    }
+
+    fn write_components(&self, out: &mut [f32]) {
+        out[..3].copy_from_slice(self);
+    }
+
+    fn from_components(src: &[f32]) -> Self {
+        [src[0], src[1], src[2]]
+    }
+}
+
+impl CrossProduct for [f32; 3] {
+    fn cross(&self, other: &Self) -> Self {
+        [
+            self[1] * other[2] - self[2] * other[1],
+            self[2] * other[0] - self[0] * other[2],
+            self[0] * other[1] - self[1] * other[0],
+        ]
+    }
+}
+
+impl InterpolationPrimitive for [f32; 2] {
+    fn add(&self, other: &Self) -> Self {
+        [self[0] + other[0], self[1] + other[1]]
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        [self[0] - other[0], self[1] - other[1]]
+    }
+
+    fn mul(&self, other: f32) -> Self {
+        [self[0] * other, self[1] * other]
+    }
+
+    fn dot(&self, other: &Self) -> f32 {
+        (self[0] * other[0]) + (self[1] * other[1])
+    }
+
+    fn magnitude2(&self) -> f32 {
+        self.dot(self)
+    }
+
+    fn component_count() -> usize {
+        2
+    }
+
+    fn write_components(&self, out: &mut [f32]) {
+        out[..2].copy_from_slice(self);
+    }
+
+    fn from_components(src: &[f32]) -> Self {
+        [src[0], src[1]]
+    }
+
+    // Unit complex numbers `[cos a, sin a]` are 2D rotations, where `v` and `-v` differ by 180
+    // degrees, and the arc between two rotations is always the shortest one
+    fn antipodal_equivalent() -> bool {
+        false
+    }
 }
 
 impl InterpolationPrimitive for f32 {
@@ -160,6 +391,18 @@ impl InterpolationPrimitive for f32 {
         self.dot(self)
     }
 
+    fn component_count() -> usize {
+        1
+    }
+
+    fn write_components(&self, out: &mut [f32]) {
+        out[0] = *self;
+    }
+
+    fn from_components(src: &[f32]) -> Self {
+        src[0]
+    }
+
     fn magnitude(&self) -> f32 {
         *self
     }
@@ -190,6 +433,18 @@ impl InterpolationPrimitive for f64 {
         self.dot(self)
     }
 
+    fn component_count() -> usize {
+        1
+    }
+
+    fn write_components(&self, out: &mut [f32]) {
+        out[0] = *self as f32;
+    }
+
+    fn from_components(src: &[f32]) -> Self {
+        src[0] as f64
+    }
+
     fn magnitude(&self) -> f32 {
         *self as f32
     }
@@ -200,6 +455,8 @@ impl InterpolationPrimitive for f64 {
 }
 
 impl InterpolationPrimitive for u32 {
+    const EPSILON: f32 = 0.5;
+
     fn add(&self, other: &Self) -> Self {
         *self + other
     }
@@ -220,6 +477,18 @@ impl InterpolationPrimitive for u32 {
         self.dot(self)
     }
 
+    fn component_count() -> usize {
+        1
+    }
+
+    fn write_components(&self, out: &mut [f32]) {
+        out[0] = *self as f32;
+    }
+
+    fn from_components(src: &[f32]) -> Self {
+        src[0] as u32
+    }
+
     fn magnitude(&self) -> f32 {
         *self as f32
     }
@@ -230,6 +499,8 @@ impl InterpolationPrimitive for u32 {
 }
 
 impl InterpolationPrimitive for u64 {
+    const EPSILON: f32 = 0.5;
+
     fn add(&self, other: &Self) -> Self {
         *self + other
     }
@@ -250,6 +521,18 @@ impl InterpolationPrimitive for u64 {
         self.dot(self)
     }
 
+    fn component_count() -> usize {
+        1
+    }
+
+    fn write_components(&self, out: &mut [f32]) {
+        out[0] = *self as f32;
+    }
+
+    fn from_components(src: &[f32]) -> Self {
+        src[0] as u64
+    }
+
     fn magnitude(&self) -> f32 {
         *self as f32
     }
@@ -260,6 +543,8 @@ impl InterpolationPrimitive for u64 {
 }
 
 impl InterpolationPrimitive for usize {
+    const EPSILON: f32 = 0.5;
+
     fn add(&self, other: &Self) -> Self {
         *self + other
     }
@@ -280,6 +565,18 @@ impl InterpolationPrimitive for usize {
         self.dot(self)
     }
 
+    fn component_count() -> usize {
+        1
+    }
+
+    fn write_components(&self, out: &mut [f32]) {
+        out[0] = *self as f32;
+    }
+
+    fn from_components(src: &[f32]) -> Self {
+        src[0] as usize
+    }
+
     fn magnitude(&self) -> f32 {
         *self as f32
     }
@@ -290,6 +587,8 @@ impl InterpolationPrimitive for usize {
 }
 
 impl InterpolationPrimitive for i32 {
+    const EPSILON: f32 = 0.5;
+
     fn add(&self, other: &Self) -> Self {
         *self + other
     }
@@ -310,6 +609,18 @@ impl InterpolationPrimitive for i32 {
         self.dot(self)
     }
 
+    fn component_count() -> usize {
+        1
+    }
+
+    fn write_components(&self, out: &mut [f32]) {
+        out[0] = *self as f32;
+    }
+
+    fn from_components(src: &[f32]) -> Self {
+        src[0] as i32
+    }
+
     fn magnitude(&self) -> f32 {
         *self as f32
     }
@@ -320,6 +631,8 @@ impl InterpolationPrimitive for i32 {
 }
 
 impl InterpolationPrimitive for i64 {
+    const EPSILON: f32 = 0.5;
+
     fn add(&self, other: &Self) -> Self {
         *self + other
     }
@@ -340,6 +653,18 @@ impl InterpolationPrimitive for i64 {
         self.dot(self)
     }
 
+    fn component_count() -> usize {
+        1
+    }
+
+    fn write_components(&self, out: &mut [f32]) {
+        out[0] = *self as f32;
+    }
+
+    fn from_components(src: &[f32]) -> Self {
+        src[0] as i64
+    }
+
     fn magnitude(&self) -> f32 {
         *self as f32
     }
@@ -350,6 +675,8 @@ impl InterpolationPrimitive for i64 {
 }
 
 impl InterpolationPrimitive for isize {
+    const EPSILON: f32 = 0.5;
+
     fn add(&self, other: &Self) -> Self {
         *self + other
     }
@@ -370,6 +697,18 @@ impl InterpolationPrimitive for isize {
         self.dot(self)
     }
 
+    fn component_count() -> usize {
+        1
+    }
+
+    fn write_components(&self, out: &mut [f32]) {
+        out[0] = *self as f32;
+    }
+
+    fn from_components(src: &[f32]) -> Self {
+        src[0] as isize
+    }
+
     fn magnitude(&self) -> f32 {
         *self as f32
     }
@@ -378,3 +717,75 @@ impl InterpolationPrimitive for isize {
         *self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_components_vec3() {
+        let v = Vector3::from([1., -2., 3.5]);
+        let mut buffer = vec![0.; Vector3::<f32>::component_count()];
+        v.write_components(&mut buffer);
+        assert_eq!(vec![1., -2., 3.5], buffer);
+        assert_eq!(v, Vector3::from_components(&buffer));
+    }
+
+    #[test]
+    fn test_components_quat() {
+        let q = Quaternion::from([0.5, -0.5, 0.5, -0.5]);
+        let mut buffer = vec![0.; Quaternion::<f32>::component_count()];
+        q.write_components(&mut buffer);
+        assert_eq!(4, buffer.len());
+        assert_eq!(q, Quaternion::from_components(&buffer));
+    }
+
+    #[test]
+    fn test_try_normalize_epsilon() {
+        let small = Vector3::from([Vector3::<f32>::EPSILON * 0.5, 0., 0.]);
+        assert_eq!(None, small.try_normalize());
+        let large = Vector3::from([Vector3::<f32>::EPSILON * 2., 0., 0.]);
+        assert_eq!(Some(Vector3::from([1., 0., 0.])), large.try_normalize());
+        assert_eq!(None, 0u32.try_normalize());
+        assert_eq!(Some(3), 3u32.try_normalize());
+    }
+
+    #[test]
+    fn test_default_components() {
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        struct Opaque(f32, f32);
+
+        impl InterpolationPrimitive for Opaque {
+            fn add(&self, other: &Self) -> Self {
+                Opaque(self.0 + other.0, self.1 + other.1)
+            }
+
+            fn sub(&self, other: &Self) -> Self {
+                Opaque(self.0 - other.0, self.1 - other.1)
+            }
+
+            fn mul(&self, scalar: f32) -> Self {
+                Opaque(self.0 * scalar, self.1 * scalar)
+            }
+
+            fn dot(&self, other: &Self) -> f32 {
+                self.0 * other.0 + self.1 * other.1
+            }
+
+            fn magnitude2(&self) -> f32 {
+                self.dot(self)
+            }
+        }
+
+        assert_eq!(0, Opaque::component_count());
+        assert!(Opaque(1e30, -2.).is_finite());
+        assert!(!Opaque(f32::INFINITY, 0.).is_finite());
+        assert!(!Opaque(0., f32::NAN).is_finite());
+        let function = ::InterpolationFunction::Linear;
+        let outputs = [Opaque(0., 2.), Opaque(4., 0.)];
+        assert_eq!(
+            Opaque(1., 1.5),
+            function.interpolate(0.25, &[0., 1.], &outputs, false)
+        );
+    }
+}
@@ -0,0 +1,285 @@
+use std::f32::consts::FRAC_1_SQRT_2;
+
+use curve::keyframe_output_index;
+use mint::{Quaternion, Vector3};
+use primitive::InterpolationPrimitive;
+use sampler::Sampler;
+use InterpolationFunction;
+use {resolve_segment, Segment};
+
+// Number of quantization steps in the `i16` range.
+const STEPS: f32 = 65535.;
+
+/// Primitives that can be stored quantized to `i16` components, see `QuantizedTrack`.
+pub trait Quantize: InterpolationPrimitive + Clone {
+    /// Number of `i16` values used to store a single value
+    fn quantized_len() -> usize;
+
+    /// Range `(min, max)` of the quantized components of `values`, used to compute the scale and
+    /// offset of a track.
+    fn quantization_range(values: &[Self]) -> (f32, f32);
+
+    /// Write the quantized representation of `self` to `out`, which has length `quantized_len()`.
+    fn quantize(&self, scale: f32, offset: f32, out: &mut [i16]);
+
+    /// Read a value from its quantized representation, as written by `quantize`.
+    fn dequantize(src: &[i16], scale: f32, offset: f32) -> Self;
+}
+
+fn quantize_component(value: f32, scale: f32, offset: f32) -> i16 {
+    ((value - offset) / scale - 32768.)
+        .round()
+        .clamp(-32768., 32767.) as i16
+}
+
+fn dequantize_component(value: i16, scale: f32, offset: f32) -> f32 {
+    (f32::from(value) + 32768.) * scale + offset
+}
+
+impl Quantize for Vector3<f32> {
+    fn quantized_len() -> usize {
+        3
+    }
+
+    fn quantization_range(values: &[Self]) -> (f32, f32) {
+        values
+            .iter()
+            .flat_map(|v| vec![v.x, v.y, v.z])
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), c| {
+                (min.min(c), max.max(c))
+            })
+    }
+
+    fn quantize(&self, scale: f32, offset: f32, out: &mut [i16]) {
+        out[0] = quantize_component(self.x, scale, offset);
+        out[1] = quantize_component(self.y, scale, offset);
+        out[2] = quantize_component(self.z, scale, offset);
+    }
+
+    fn dequantize(src: &[i16], scale: f32, offset: f32) -> Self {
+        Vector3 {
+            x: dequantize_component(src[0], scale, offset),
+            y: dequantize_component(src[1], scale, offset),
+            z: dequantize_component(src[2], scale, offset),
+        }
+    }
+}
+
+/// Unit quaternions are stored using smallest three compression: the component with the largest
+/// magnitude is dropped and reconstructed from the other three, which all lie in
+/// `[-1/sqrt(2), 1/sqrt(2)]`. The fourth `i16` holds the index and sign of the dropped component.
+///
+/// Only unit quaternions are supported, so this is not suitable for the tangents of spline data.
+impl Quantize for Quaternion<f32> {
+    fn quantized_len() -> usize {
+        4
+    }
+
+    fn quantization_range(_: &[Self]) -> (f32, f32) {
+        (-FRAC_1_SQRT_2, FRAC_1_SQRT_2)
+    }
+
+    fn quantize(&self, scale: f32, offset: f32, out: &mut [i16]) {
+        let components = [self.v.x, self.v.y, self.v.z, self.s];
+        let largest = (1..4).fold(0, |largest, i| {
+            if components[i].abs() > components[largest].abs() {
+                i
+            } else {
+                largest
+            }
+        });
+        let mut j = 0;
+        for (i, &c) in components.iter().enumerate() {
+            if i != largest {
+                out[j] = quantize_component(c, scale, offset);
+                j += 1;
+            }
+        }
+        let negative = if components[largest] < 0. { 4 } else { 0 };
+        out[3] = largest as i16 | negative;
+    }
+
+    fn dequantize(src: &[i16], scale: f32, offset: f32) -> Self {
+        let largest = (src[3] & 3) as usize;
+        let mut components = [0.; 4];
+        let mut j = 0;
+        for (i, c) in components.iter_mut().enumerate() {
+            if i != largest {
+                *c = dequantize_component(src[j], scale, offset);
+                j += 1;
+            }
+        }
+        let sum = components.iter().map(|c| c * c).sum::<f32>();
+        let value = (1. - sum).max(0.).sqrt();
+        components[largest] = if src[3] & 4 != 0 { -value } else { value };
+        Quaternion::from(components)
+    }
+}
+
+/// A keyframe track with output values quantized to `i16` components, using a scale and offset
+/// shared by the whole track.
+///
+/// Values are dequantized when sampling, only the keyframes needed for the sampled segment are
+/// decoded. Each component of a dequantized value is within `scale() / 2` of the original.
+#[derive(Clone, Debug)]
+pub struct QuantizedTrack<T>
+where
+    T: InterpolationPrimitive,
+{
+    inputs: Vec<f32>,
+    data: Vec<i16>,
+    scale: f32,
+    offset: f32,
+    function: InterpolationFunction<T>,
+    normalize: bool,
+}
+
+impl<T> QuantizedTrack<T>
+where
+    T: Quantize,
+{
+    /// Quantize a track.
+    ///
+    /// ## Parameters:
+    ///
+    /// - `inputs`: list of discrete input values for each keyframe
+    /// - `outputs`: list of output values, laid out as expected by `function`
+    /// - `function`: the interpolation function used when sampling
+    /// - `normalize`: if true, normalize sampled values
+    pub fn new(
+        inputs: Vec<f32>,
+        outputs: &[T],
+        function: InterpolationFunction<T>,
+        normalize: bool,
+    ) -> Self {
+        let (min, max) = T::quantization_range(outputs);
+        let scale = if max > min { (max - min) / STEPS } else { 1. };
+        let len = T::quantized_len();
+        let mut data = vec![0; outputs.len() * len];
+        for (value, out) in outputs.iter().zip(data.chunks_mut(len)) {
+            value.quantize(scale, min, out);
+        }
+        QuantizedTrack {
+            inputs,
+            data,
+            scale,
+            offset: min,
+            function,
+            normalize,
+        }
+    }
+
+    /// Size of a single quantization step
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Value of the smallest quantized component
+    pub fn offset(&self) -> f32 {
+        self.offset
+    }
+
+    /// Dequantize the output value at `index`.
+    pub fn output(&self, index: usize) -> T {
+        let len = T::quantized_len();
+        T::dequantize(
+            &self.data[index * len..(index + 1) * len],
+            self.scale,
+            self.offset,
+        )
+    }
+
+    fn outputs(&self, start: usize, end: usize) -> Vec<T> {
+        (start..end).map(|i| self.output(i)).collect()
+    }
+}
+
+impl<T> Sampler<T> for QuantizedTrack<T>
+where
+    T: Quantize,
+{
+    fn sample(&self, input: f32) -> T {
+        let count = self.inputs.len();
+        let index = match resolve_segment(input, &self.inputs) {
+            Segment::Before => return self.output(keyframe_output_index(&self.function, 0)),
+            Segment::After => return self.output(keyframe_output_index(&self.function, count - 1)),
+            Segment::In { index, .. } => index,
+        };
+        // Decode only the keyframes the segment depends on, keeping the output layout of the
+        // function for the shorter list of keyframes.
+        let (inputs, outputs) = match self.function {
+            InterpolationFunction::CubicSpline => (
+                &self.inputs[index..index + 2],
+                self.outputs(index * 3, index * 3 + 6),
+            ),
+            InterpolationFunction::CatmullRomSpline => {
+                // The outputs around the window are either the tangent padding, or positions that
+                // are never used as tangents by the sampled segment.
+                let start = index.saturating_sub(1);
+                let end = (index + 3).min(count);
+                (&self.inputs[start..end], self.outputs(start, end + 2))
+            }
+            InterpolationFunction::Function(_) => (
+                &self.inputs[..],
+                self.outputs(0, self.data.len() / T::quantized_len()),
+            ),
+            _ => (
+                &self.inputs[index..index + 2],
+                self.outputs(index, index + 2),
+            ),
+        };
+        self.function
+            .interpolate(input, inputs, &outputs, self.normalize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quantized_vec3_round_trip() {
+        let input = vec![0., 1., 2., 3., 4.];
+        let output = vec![
+            Vector3::from([1., 0., 0.]),
+            Vector3::from([0., 0., 0.]),
+            Vector3::from([1., 2., 0.]),
+            Vector3::from([0., 0., 1.]),
+            Vector3::from([-1., 0., 0.]),
+            Vector3::from([0., 1., 0.]),
+            Vector3::from([-1., 0., 0.]),
+        ];
+        let function = InterpolationFunction::CatmullRomSpline;
+        let track = QuantizedTrack::new(input.clone(), &output, function.clone(), false);
+        // Sampled values are affine combinations of the keyframes, with absolute weights summing to
+        // at most 4 for a Catmull-Rom segment
+        let bound = track.scale() / 2. * 4. * 3f32.sqrt();
+        for i in 0..=45 {
+            let t = i as f32 / 10. - 0.2;
+            let expected = function.interpolate(t, &input, &output, false);
+            assert!(track.sample(t).sub(&expected).magnitude() <= bound);
+        }
+    }
+
+    #[test]
+    fn test_quantized_quat_round_trip() {
+        let input = vec![0., 1., 2., 3.];
+        let output = vec![
+            Quaternion::from([0., 0., 0., 1.]),
+            Quaternion::from([0.5, 0.5, 0.5, 0.5]),
+            Quaternion::from([0., -0.6, 0., 0.8]),
+            Quaternion::from([0.1, -0.7, 0.7, -0.1]).normalize(),
+        ];
+        let function = InterpolationFunction::SphericalLinear;
+        let track = QuantizedTrack::new(input.clone(), &output, function.clone(), true);
+        for (i, value) in output.iter().enumerate() {
+            let decoded = track.output(i);
+            assert!(decoded.sub(value).magnitude() < 1e-4);
+        }
+        for i in 0..=30 {
+            let t = i as f32 / 10.;
+            let expected = function.interpolate(t, &input, &output, true);
+            assert!(track.sample(t).sub(&expected).magnitude() < 1e-4);
+        }
+    }
+}
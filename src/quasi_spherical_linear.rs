@@ -1,5 +1,5 @@
-use get_input_index;
 use primitive::InterpolationPrimitive;
+use {resolve_segment, Segment};
 
 /// Do quasi spherical linear interpolation.
 ///
@@ -10,9 +10,9 @@ use primitive::InterpolationPrimitive;
 /// Algorithm was created by Jonathan Blow:
 /// [Hacking Quaternions](http://number-none.com/product/Hacking%20Quaternions/)
 ///
-/// `f(d) = d <= 0.5 => lerp(p0, p1, y'(d))`
-/// `f(d) = d > 0.5 => lerp(p0, p1, 1 - y'(1 - d))`
-/// `y'(d) = 2 * k * d^2 - 3 * k * d + k + 1`
+/// `f(d) = d <= 0.5 => lerp(p0, p1, w(d))`
+/// `f(d) = d > 0.5 => lerp(p0, p1, 1 - w(1 - d))`
+/// `w(d) = d * (2 * k * d^2 - 3 * k * d + k + 1)`
 /// `k = worst_case_slope * (1 - attenuation * dot(p0, p1))^2`
 /// `d = (t - t0) / (t1 - t0)`
 /// `p0 = output at left keyframe`
@@ -25,7 +25,7 @@ use primitive::InterpolationPrimitive;
 /// - `input`: the input value to the function
 /// - `inputs`: list of discrete input values for each keyframe
 /// - `outputs`: list of output values to interpolate between, for spherical
-///              linear interpolation this should be the same size as `inputs`
+///   linear interpolation this should be the same size as `inputs`
 /// - `normalize`: if true, normalize the interpolated value before returning it
 pub fn quasi_spherical_linear_interpolate<T>(
     input: f32,
@@ -36,43 +36,58 @@ pub fn quasi_spherical_linear_interpolate<T>(
 where
     T: InterpolationPrimitive + Clone,
 {
-    let input_index = match get_input_index(input, inputs) {
-        Some(index) => index,
-        None => return outputs[0].clone(),
+    match resolve_segment(input, inputs) {
+        Segment::Before => outputs[0].clone(),
+        Segment::After => outputs[outputs.len() - 1].clone(),
+        Segment::In { index, t_local } => {
+            quasi_spherical_linear_segment(t_local, index, outputs, normalize)
+        }
+    }
+}
+
+// Interpolate at `d` inside the segment starting at keyframe `index`.
+pub(crate) fn quasi_spherical_linear_segment<T>(
+    d: f32,
+    index: usize,
+    outputs: &[T],
+    normalize: bool,
+) -> T
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let left = &outputs[index];
+    let right = &outputs[index + 1];
+
+    let dot = left.dot(right);
+    let d_prime = if d <= 0.5 {
+        counter_warp(d, dot)
+    } else {
+        1. - counter_warp(1. - d, dot)
     };
-    if input_index >= (inputs.len() - 1) {
-        outputs[outputs.len() - 1].clone()
+    let v = left.add(&right.sub(left).mul(d_prime));
+    if normalize {
+        fast_normalize(v)
     } else {
-        let d = (input - inputs[input_index]) / (inputs[input_index + 1] - inputs[input_index]);
-        let left = &outputs[input_index];
-        let right = &outputs[input_index + 1];
-
-        let dot = left.dot(&right);
-        let d_prime = if d <= 0.5 {
-            counter_warp(d, dot)
-        } else {
-            1. - counter_warp(1. - d, dot)
-        };
-        let v = left.add(&right.sub(&left).mul(d_prime));
-        if normalize {
-            fast_normalize(v)
-        } else {
-            v
-        }
+        v
     }
 }
 
-const WARP_ATTENUATION: f32 = 0.82279687;
-const WARP_WORST_CASE_SLOPE: f32 = 0.58549219;
+const WARP_ATTENUATION: f32 = 0.8227969;
+const WARP_WORST_CASE_SLOPE: f32 = 0.5854922;
 
-// Helper function used by the quasi interpolation function above
-// f(d) = 2 * k * d^2 - 3 * k * d + k + 1
+// Counter warped interpolation factor used by the quasi interpolation function above, `w(d)`. The
+// reference implementation computes `w(d) / d`, and multiplies by `d` where it is used.
+// w(d) = d * (2 * k * d^2 - 3 * k * d + k + 1)
 fn counter_warp(d: f32, cos_alpha: f32) -> f32 {
     let factor = 1. - WARP_ATTENUATION * cos_alpha;
     let k = WARP_WORST_CASE_SLOPE * factor * factor;
-    2. * k * d * d - 3. * k * d + k + 1.
+    d * (2. * k * d * d - 3. * k * d + k + 1.)
 }
 
+// Squared magnitudes below which `fast_normalize` refines the inverse sqrt a second and third time.
+const NORMALIZE_SECOND_STEP_BELOW: f32 = 0.915212;
+const NORMALIZE_THIRD_STEP_BELOW: f32 = 0.6521197;
+
 // Normalize a value using the inverse sqrt tangent approximation below.
 fn fast_normalize<T>(v: T) -> T
 where
@@ -80,9 +95,9 @@ where
 {
     let s = v.magnitude2();
     let k = isqrt_approx_in_neighbourhood(s);
-    let k = if s <= 0.91521198 {
+    let k = if s <= NORMALIZE_SECOND_STEP_BELOW {
         let k = k * isqrt_approx_in_neighbourhood(k * k * s);
-        if s <= 0.65211970 {
+        if s <= NORMALIZE_THIRD_STEP_BELOW {
             k * isqrt_approx_in_neighbourhood(k * k * s)
         } else {
             k
@@ -108,6 +123,7 @@ fn isqrt_approx_in_neighbourhood(s: f32) -> f32 {
 mod tests {
     use super::*;
     use mint::Quaternion;
+    use spherical_linear::spherical_linear_interpolate;
 
     #[test]
     fn test_linear_arr4() {
@@ -120,7 +136,7 @@ mod tests {
             [0., 0., 0., 0.],
         ];
         assert_eq!(
-            [0.9996371, 0., 0., 0.],
+            [0.98807204, 0., 0., 0.],
             quasi_spherical_linear_interpolate(0.5, &input, &output, true)
         );
     }
@@ -136,8 +152,37 @@ mod tests {
             Quaternion::from([0., 0., 0., 0.]),
         ];
         assert_eq!(
-            Quaternion::from([0.9996371, 0., 0., 0.]),
+            Quaternion::from([0.98807204, 0., 0., 0.]),
             quasi_spherical_linear_interpolate(0.5, &input, &output, true)
         );
     }
+
+    #[test]
+    fn test_constants_match_reference() {
+        // The constants are written with the shortest literals that round to the same `f32` as
+        // the 8 digit values of the reference implementation
+        let reference = [0.82279687f64, 0.58549219, 0.91521198, 0.65211970];
+        let constants = [
+            WARP_ATTENUATION,
+            WARP_WORST_CASE_SLOPE,
+            NORMALIZE_SECOND_STEP_BELOW,
+            NORMALIZE_THIRD_STEP_BELOW,
+        ];
+        for (&r, &c) in reference.iter().zip(&constants) {
+            assert_eq!((r as f32).to_bits(), c.to_bits());
+        }
+    }
+
+    #[test]
+    fn test_close_to_slerp() {
+        let input = vec![0., 1.];
+        let half = 0.5f32.sqrt();
+        let output = vec![[0., 0., 0., 1.], [0., 0., half, half]];
+        for i in 0..=10 {
+            let t = i as f32 / 10.;
+            let quasi = quasi_spherical_linear_interpolate(t, &input, &output, true);
+            let exact = spherical_linear_interpolate(t, &input, &output, true);
+            assert!(quasi.sub(&exact).magnitude() < 1e-3);
+        }
+    }
 }
@@ -0,0 +1,97 @@
+use primitive::InterpolationPrimitive;
+use std::collections::HashMap;
+use InterpolationFunction;
+
+/// Signature of a custom interpolation function, as used by `InterpolationFunction::Function`.
+pub type CustomFunction<T> = fn(f32, &[f32], &[T], bool) -> T;
+
+/// Registry of named custom interpolation functions.
+///
+/// `InterpolationFunction::Function` can't be serialized, so serialized data can refer to custom
+/// functions by name instead, and resolve the name using a registry when the data is loaded.
+#[derive(Clone)]
+pub struct FunctionRegistry<T> {
+    functions: HashMap<String, CustomFunction<T>>,
+}
+
+impl<T> FunctionRegistry<T>
+where
+    T: InterpolationPrimitive,
+{
+    /// Create an empty registry
+    pub fn new() -> Self {
+        FunctionRegistry {
+            functions: HashMap::new(),
+        }
+    }
+
+    /// Register `function` under `name`, returning the function previously registered under that
+    /// name, if any
+    pub fn register<S>(&mut self, name: S, function: CustomFunction<T>) -> Option<CustomFunction<T>>
+    where
+        S: Into<String>,
+    {
+        self.functions.insert(name.into(), function)
+    }
+
+    /// Get the interpolation function registered under `name`
+    pub fn resolve(&self, name: &str) -> Option<InterpolationFunction<T>> {
+        self.functions
+            .get(name)
+            .map(|&function| InterpolationFunction::Function(function))
+    }
+}
+
+impl<T> Default for FunctionRegistry<T>
+where
+    T: InterpolationPrimitive,
+{
+    fn default() -> Self {
+        FunctionRegistry::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn halfway(_: f32, _: &[f32], outputs: &[f32], _: bool) -> f32 {
+        (outputs[0] + outputs[outputs.len() - 1]) / 2.
+    }
+
+    #[test]
+    fn test_resolve_registered_function() {
+        let mut registry = FunctionRegistry::new();
+        assert!(registry.register("halfway", halfway).is_none());
+        let function = registry.resolve("halfway").unwrap();
+        assert_eq!(3., function.interpolate(0.1, &[0., 1.], &[2., 4.], false));
+        assert!(registry.resolve("missing").is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_resolve_serialized_track() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Track {
+            function: String,
+            inputs: Vec<f32>,
+            outputs: Vec<f32>,
+        }
+
+        let mut registry = FunctionRegistry::new();
+        registry.register("halfway", halfway);
+        let track = Track {
+            function: "halfway".to_string(),
+            inputs: vec![0., 1.],
+            outputs: vec![2., 4.],
+        };
+        let data = ::serde_json::to_string(&track).unwrap();
+        let loaded: Track = ::serde_json::from_str(&data).unwrap();
+        assert_eq!(track, loaded);
+        let function = registry.resolve(&loaded.function).unwrap();
+        assert_eq!(
+            3.,
+            function.interpolate(0.1, &loaded.inputs, &loaded.outputs, false)
+        );
+    }
+}
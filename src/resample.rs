@@ -0,0 +1,269 @@
+use primitive::InterpolationPrimitive;
+use sample_range;
+use InterpolationFunction;
+
+// Maximum number of times a single segment is subdivided during adaptive resampling.
+const MAX_SUBDIVISIONS: u32 = 16;
+
+/// Resample a curve into a linear curve that stays within `tolerance` of the original.
+///
+/// Starting from the keyframes of the original curve, each interval is subdivided until linear
+/// interpolation between its end points is within `tolerance` of the original curve at a number of
+/// test points inside the interval, using `sub().magnitude2().sqrt()`. Straight parts of the curve
+/// therefore need few samples, while curved parts get more. Step interpolation can not be
+/// approximated by a linear curve, so its keyframes are returned unchanged.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `tolerance`: maximum allowed distance between the linear curve and the original
+///
+/// ## Returns
+///
+/// `(inputs, outputs)` of the linear curve.
+pub fn resample_with_tolerance<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    tolerance: f32,
+) -> (Vec<f32>, Vec<T>)
+where
+    T: InterpolationPrimitive + Clone,
+{
+    if *function == InterpolationFunction::Step {
+        return (inputs.to_vec(), outputs.to_vec());
+    }
+    let sample = |t: f32| function.interpolate(t, inputs, outputs, false);
+    let mut new_inputs = vec![inputs[0]];
+    let mut new_outputs = vec![sample(inputs[0])];
+    for window in inputs.windows(2) {
+        subdivide(
+            &sample,
+            (window[0], &sample(window[0])),
+            (window[1], sample(window[1])),
+            tolerance,
+            MAX_SUBDIVISIONS,
+            &mut new_inputs,
+            &mut new_outputs,
+        );
+    }
+    (new_inputs, new_outputs)
+}
+
+/// Generate a polyline preview of part of a curve, with more points where the curve bends.
+///
+/// The range is first sampled at `min_samples` evenly spaced input values. Then the interval
+/// where the polyline deviates most from the curve, measured at the middle of the interval using
+/// `sub().magnitude2().sqrt()`, is split in two, until the deviation is within `tolerance`
+/// everywhere, or there are `max_samples` points.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `range`: `(start, end)` input values of the part of the curve to preview
+/// - `min_samples`: minimum number of points, at least 2 are always generated
+/// - `max_samples`: maximum number of points
+/// - `tolerance`: maximum allowed distance between the polyline and the curve
+///
+/// ## Returns
+///
+/// The points of the polyline, in order.
+pub fn preview_polyline<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    range: (f32, f32),
+    min_samples: usize,
+    max_samples: usize,
+    tolerance: f32,
+) -> Vec<T>
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let sample = |t: f32| function.interpolate(t, inputs, outputs, false);
+    let mut points = sample_range(range.0, range.1, min_samples.max(2))
+        .into_iter()
+        .map(|t| (t, sample(t)))
+        .collect::<Vec<_>>();
+    // Midpoint of each interval, and the distance between the curve and the polyline there
+    let split = |left: &(f32, T), right: &(f32, T)| {
+        let t = (left.0 + right.0) / 2.;
+        let value = sample(t);
+        let linear = left.1.add(&right.1).mul(0.5);
+        let error = value.sub(&linear).magnitude2().sqrt();
+        ((t, value), error)
+    };
+    let mut splits = points
+        .windows(2)
+        .map(|w| split(&w[0], &w[1]))
+        .collect::<Vec<_>>();
+    while points.len() < max_samples {
+        let (index, error) = splits
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (i, s.1))
+            .fold((0, 0.), |best, s| if s.1 > best.1 { s } else { best });
+        if error <= tolerance {
+            break;
+        }
+        let middle = splits[index].0.clone();
+        let left = split(&points[index], &middle);
+        let right = split(&middle, &points[index + 1]);
+        points.insert(index + 1, middle);
+        splits[index] = left;
+        splits.insert(index + 1, right);
+    }
+    points.into_iter().map(|(_, value)| value).collect()
+}
+
+// Push samples for the interval from `left` to `right` (exclusive of `left`), subdividing while the
+// linear approximation deviates more than `tolerance` from the curve.
+fn subdivide<T, F>(
+    sample: &F,
+    left: (f32, &T),
+    right: (f32, T),
+    tolerance: f32,
+    depth: u32,
+    inputs: &mut Vec<f32>,
+    outputs: &mut Vec<T>,
+) where
+    T: InterpolationPrimitive + Clone,
+    F: Fn(f32) -> T,
+{
+    let within_tolerance = depth == 0
+        || (1..8).all(|i| {
+            let d = i as f32 / 8.;
+            let t = left.0 + (right.0 - left.0) * d;
+            let linear = left.1.add(&right.1.sub(left.1).mul(d));
+            sample(t).sub(&linear).magnitude2().sqrt() <= tolerance
+        });
+    if within_tolerance {
+        inputs.push(right.0);
+        outputs.push(right.1);
+    } else {
+        let middle_t = (left.0 + right.0) / 2.;
+        let middle = sample(middle_t);
+        subdivide(
+            sample,
+            left,
+            (middle_t, middle.clone()),
+            tolerance,
+            depth - 1,
+            inputs,
+            outputs,
+        );
+        subdivide(
+            sample,
+            (middle_t, &middle),
+            right,
+            tolerance,
+            depth - 1,
+            inputs,
+            outputs,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linear::linear_interpolate;
+
+    #[test]
+    fn test_resample_with_tolerance() {
+        let input = vec![0., 1., 2., 3., 4., 5., 6.];
+        let output = vec![
+            [1., 0.],
+            [0., 0.],
+            [1., 0.],
+            [2., 0.],
+            [3., 0.],
+            [4., 1.],
+            [5., -1.],
+            [6., 1.],
+            [1., 0.],
+        ];
+        let function = InterpolationFunction::CatmullRomSpline;
+        let tolerance = 0.01;
+        let (new_input, new_output) =
+            resample_with_tolerance(&input, &output, &function, tolerance);
+        for i in 0..=600 {
+            let t = i as f32 / 100.;
+            let original = function.interpolate(t, &input, &output, false);
+            let resampled = linear_interpolate(t, &new_input, &new_output, false);
+            assert!(original.sub(&resampled).magnitude() <= tolerance * 1.01);
+        }
+        let straight = new_input.iter().filter(|&&t| t > 0. && t <= 2.).count();
+        let curved = new_input.iter().filter(|&&t| t > 4. && t <= 6.).count();
+        assert_eq!(2, straight);
+        assert!(curved > 10);
+    }
+
+    #[test]
+    fn test_resample_with_tolerance_dip() {
+        let input = vec![0., 1., 2.];
+        let output = vec![0f32, 0., -1., 0., 0.];
+        let function = InterpolationFunction::CatmullRomSpline;
+        let (new_input, new_output) = resample_with_tolerance(&input, &output, &function, 0.01);
+        assert!(new_input.len() > 5);
+        for i in 0..=200 {
+            let t = i as f32 / 100.;
+            let original = function.interpolate(t, &input, &output, false);
+            let resampled = linear_interpolate(t, &new_input, &new_output, false);
+            assert!((original - resampled).abs() <= 0.0101);
+        }
+
+        let output = vec![0., 1., 0.];
+        let step = InterpolationFunction::Step;
+        assert_eq!(
+            (input.clone(), output.clone()),
+            resample_with_tolerance(&input, &output, &step, 0.01)
+        );
+    }
+
+    #[test]
+    fn test_preview_polyline() {
+        let input = vec![0., 1., 2., 3., 4., 5., 6.];
+        let output = vec![
+            [1., 0.],
+            [0., 0.],
+            [1., 0.],
+            [2., 0.],
+            [3., 0.],
+            [4., 1.],
+            [5., -1.],
+            [6., 1.],
+            [1., 0.],
+        ];
+        let function = InterpolationFunction::CatmullRomSpline;
+        let straight = preview_polyline(&input, &output, &function, (0.5, 2.), 4, 50, 0.01);
+        assert_eq!(4, straight.len());
+        assert_eq!(
+            function.interpolate(0.5, &input, &output, false),
+            straight[0]
+        );
+        let curved = preview_polyline(&input, &output, &function, (4., 6.), 4, 50, 0.01);
+        assert!(curved.len() > 10 && curved.len() < 50);
+        let capped = preview_polyline(&input, &output, &function, (4., 6.), 4, 8, 0.0001);
+        assert_eq!(8, capped.len());
+    }
+
+    #[test]
+    fn test_preview_polyline_bump_and_dip() {
+        let input = vec![0., 1., 2.];
+        let function = InterpolationFunction::CatmullRomSpline;
+        let bump = vec![0f32, 0., 1., 0., 0.];
+        let dip = vec![0f32, 0., -1., 0., 0.];
+        let bump_points = preview_polyline(&input, &bump, &function, (0., 2.), 2, 100, 0.01);
+        let dip_points = preview_polyline(&input, &dip, &function, (0., 2.), 2, 100, 0.01);
+        assert!(bump_points.len() > 2);
+        assert_eq!(bump_points.len(), dip_points.len());
+        for (b, d) in bump_points.iter().zip(&dip_points) {
+            assert_eq!(*b, -*d);
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use curve::Curve;
+use mint::Quaternion;
+use primitive::InterpolationPrimitive;
+use sampler::Sampler;
+use InterpolationFunction;
+
+/// A curve of rotations, stored as unit quaternions.
+///
+/// Interpolating quaternions linearly, or without normalizing, gives values that are not unit
+/// quaternions, and so no rotations. A rotation curve always uses spherical linear interpolation
+/// and normalizes the sampled values. The keyframes are normalized, and negated where needed so
+/// each keyframe is in the same hemisphere as the previous one, which makes the interpolation take
+/// the shortest path between keyframes no matter how the rotations were stored.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RotationCurve {
+    curve: Curve<Quaternion<f32>>,
+}
+
+impl RotationCurve {
+    /// Create a new rotation curve, with one rotation for each keyframe
+    pub fn new(inputs: Vec<f32>, rotations: Vec<Quaternion<f32>>) -> Self {
+        let mut outputs = Vec::with_capacity(rotations.len());
+        for rotation in rotations {
+            let rotation = rotation.normalize();
+            let aligned = match outputs.last() {
+                Some(previous) if rotation.dot(previous) < 0. => rotation.mul(-1.),
+                _ => rotation,
+            };
+            outputs.push(aligned);
+        }
+        RotationCurve {
+            curve: Curve::new(
+                inputs,
+                outputs,
+                InterpolationFunction::SphericalLinear,
+                true,
+            ),
+        }
+    }
+
+    /// Sample the rotation at the given input value
+    pub fn sample(&self, input: f32) -> Quaternion<f32> {
+        self.curve.sample(input)
+    }
+
+    /// The underlying curve, with the normalized and aligned keyframes
+    pub fn curve(&self) -> &Curve<Quaternion<f32>> {
+        &self.curve
+    }
+}
+
+impl Sampler<Quaternion<f32>> for RotationCurve {
+    fn sample(&self, input: f32) -> Quaternion<f32> {
+        RotationCurve::sample(self, input)
+    }
+}
+
+/// Check that quaternion keyframes are unit quaternions, as spherical linear interpolation assumes.
+///
+/// ## Parameters:
+///
+/// - `outputs`: list of quaternion keyframes
+/// - `tolerance`: maximum allowed difference between the magnitude of a keyframe and 1
+///
+/// ## Returns
+///
+/// The indices of the keyframes whose magnitude differs from 1 by more than `tolerance`, if any.
+pub fn validate_unit_quaternions(
+    outputs: &[Quaternion<f32>],
+    tolerance: f32,
+) -> Result<(), Vec<usize>> {
+    let invalid = outputs
+        .iter()
+        .enumerate()
+        .filter(|&(_, q)| (q.magnitude() - 1.).abs() > tolerance)
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    if invalid.is_empty() {
+        Ok(())
+    } else {
+        Err(invalid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_samples_are_unit_quaternions() {
+        let curve = RotationCurve::new(
+            vec![0., 1., 2., 4.],
+            vec![
+                Quaternion::from([0., 0., 0., 2.]),
+                Quaternion::from([0., 0.6, 0., -0.8]),
+                Quaternion::from([0.3, 0., 0.4, 0.]),
+                Quaternion::from([0., 0., -1., 0.]),
+            ],
+        );
+        for i in 0..=50 {
+            let rotation = curve.sample(i as f32 / 10.);
+            assert!((rotation.magnitude() - 1.).abs() < 1e-5);
+        }
+        // The second keyframe is negated to be in the same hemisphere as the first
+        assert_eq!(
+            Quaternion::from([0., -0.6, 0., 0.8]),
+            curve.curve().outputs[1]
+        );
+        assert_eq!(Quaternion::from([0., 0., 0., 1.]), curve.sample(-1.));
+    }
+
+    #[test]
+    fn test_validate_unit_quaternions() {
+        let mut rotations = vec![
+            Quaternion::from([0., 0., 0., 1.]),
+            Quaternion::from([0., 0.6, 0., -0.8]),
+            Quaternion::from([0.5, 0.5, 0.5, 0.5]),
+        ];
+        assert_eq!(Ok(()), validate_unit_quaternions(&rotations, 1e-5));
+        rotations[1] = Quaternion::from([0., 0.6, 0., 0.6]);
+        assert_eq!(Err(vec![1]), validate_unit_quaternions(&rotations, 1e-5));
+    }
+}
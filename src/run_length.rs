@@ -0,0 +1,116 @@
+use primitive::InterpolationPrimitive;
+use sampler::Sampler;
+
+/// A baked track with frames at a fixed rate, stored as runs of identical values.
+///
+/// Baked tracks often hold the same value for long stretches, e.g. a bone that doesn't move for a
+/// while. Each run of identical consecutive frames is stored once, together with the index of its
+/// first frame. Sampling finds the runs containing the two frames around the input value with a
+/// binary search, and interpolates linearly between them, which gives the same result as linear
+/// interpolation of the frames. Input values outside of the frames are clamped.
+#[derive(Clone, Debug)]
+pub struct RunLengthTrack<T> {
+    start: f32,
+    fps: f32,
+    frame_count: usize,
+    run_starts: Vec<usize>,
+    values: Vec<T>,
+}
+
+impl<T> RunLengthTrack<T>
+where
+    T: Clone + PartialEq,
+{
+    /// Encode baked frames.
+    ///
+    /// ## Parameters:
+    ///
+    /// - `start`: input value of the first frame
+    /// - `fps`: number of frames per unit of input
+    /// - `frames`: value of each frame, must not be empty
+    pub fn new(start: f32, fps: f32, frames: &[T]) -> Self {
+        assert!(!frames.is_empty(), "A track needs at least one frame");
+        let mut run_starts = Vec::new();
+        let mut values: Vec<T> = Vec::new();
+        for (i, frame) in frames.iter().enumerate() {
+            if values.last() != Some(frame) {
+                run_starts.push(i);
+                values.push(frame.clone());
+            }
+        }
+        RunLengthTrack {
+            start,
+            fps,
+            frame_count: frames.len(),
+            run_starts,
+            values,
+        }
+    }
+
+    /// Number of frames in the track
+    pub fn frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    /// Number of runs of identical frames stored
+    pub fn run_count(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Value of the frame at `index`, panics if `index` is out of bounds
+    pub fn frame(&self, index: usize) -> &T {
+        assert!(index < self.frame_count, "Frame index out of bounds");
+        let run = self.run_starts.partition_point(|&start| start <= index) - 1;
+        &self.values[run]
+    }
+}
+
+impl<T> Sampler<T> for RunLengthTrack<T>
+where
+    T: InterpolationPrimitive + Clone + PartialEq,
+{
+    fn sample(&self, input: f32) -> T {
+        let last = self.frame_count - 1;
+        let position = (input - self.start) * self.fps;
+        if position <= 0. {
+            return self.values[0].clone();
+        }
+        if position >= last as f32 {
+            return self.values[self.values.len() - 1].clone();
+        }
+        let index = position as usize;
+        let left = self.frame(index);
+        let right = self.frame(index + 1);
+        if left == right {
+            return left.clone();
+        }
+        left.add(&right.sub(left).mul(position - index as f32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linear::linear_interpolate;
+    use sample_range;
+
+    #[test]
+    fn test_run_length_round_trip() {
+        let mut frames = vec![[0., 1.]; 40];
+        frames.extend((0..10).map(|i| [i as f32, 1.]));
+        frames.extend(vec![[9., 2.]; 50]);
+        let track = RunLengthTrack::new(1., 10., &frames);
+        assert_eq!(100, track.frame_count());
+        assert_eq!(11, track.run_count());
+        for (i, frame) in frames.iter().enumerate() {
+            assert_eq!(frame, track.frame(i));
+        }
+        let inputs = sample_range(1., 10.9, 100);
+        for i in 0..=130 {
+            let t = i as f32 / 10.;
+            let expected = linear_interpolate(t, &inputs, &frames, false);
+            let value = track.sample(t);
+            assert!(value.sub(&expected).magnitude2() < 1e-8);
+        }
+    }
+}
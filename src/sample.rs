@@ -0,0 +1,987 @@
+use curve::keyframe_output_index;
+use error::InterpolationError;
+use get_input_index;
+use primitive::InterpolationPrimitive;
+use std::borrow::Cow;
+use InterpolationFunction;
+use {segment_at, snap_to_keyframe, Segment};
+
+/// Sample a curve at many input values.
+///
+/// If `query_inputs` is sorted, in either ascending or descending order, the keyframe index is
+/// tracked with a cursor that walks the keyframes in the same direction as the queries, which
+/// makes the whole operation `O(n + m)`. Unsorted queries fall back to a binary search per query.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `query_inputs`: input values to sample the curve at
+/// - `normalize`: if true, normalize the interpolated values
+///
+/// ## Returns
+///
+/// The sampled values, in the same order as `query_inputs`.
+pub fn sample_many<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    query_inputs: &[f32],
+    normalize: bool,
+) -> Vec<T>
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let mut samples = Vec::with_capacity(query_inputs.len());
+    for_each_sample(
+        inputs,
+        outputs,
+        function,
+        query_inputs,
+        normalize,
+        |_, value| samples.push(value),
+    );
+    samples
+}
+
+/// Sample a curve at many input values, writing the values to `out`.
+///
+/// Same as `sample_many`, without allocating.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `query_inputs`: input values to sample the curve at
+/// - `normalize`: if true, normalize the interpolated values
+/// - `out`: buffer for the sampled values, must be the same size as `query_inputs`
+pub fn sample_many_into<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    query_inputs: &[f32],
+    normalize: bool,
+    out: &mut [T],
+) where
+    T: InterpolationPrimitive + Clone,
+{
+    assert_eq!(
+        query_inputs.len(),
+        out.len(),
+        "Output buffer must be the same size as the query inputs"
+    );
+    for_each_sample(
+        inputs,
+        outputs,
+        function,
+        query_inputs,
+        normalize,
+        |i, value| out[i] = value,
+    );
+}
+
+// Call `f` with the index and sampled value for each query input, see `sample_many`.
+fn for_each_sample<T, F>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    query_inputs: &[f32],
+    normalize: bool,
+    mut f: F,
+) where
+    T: InterpolationPrimitive + Clone,
+    F: FnMut(usize, T),
+{
+    for_each_input_index(inputs, query_inputs, |i, index, input| {
+        f(
+            i,
+            function.interpolate_at(index, input, inputs, outputs, normalize),
+        )
+    });
+}
+
+// Call `f` with the index, keyframe index as returned by `get_input_index`, and input value for
+// each query input. Sorted queries are tracked with a cursor, see `sample_many`.
+pub(crate) fn for_each_input_index<F>(inputs: &[f32], query_inputs: &[f32], mut f: F)
+where
+    F: FnMut(usize, Option<usize>, f32),
+{
+    if query_inputs.windows(2).all(|w| w[0] <= w[1]) {
+        let mut cursor = 0;
+        for (i, &input) in query_inputs.iter().enumerate() {
+            while cursor + 1 < inputs.len() && inputs[cursor + 1] <= input {
+                cursor += 1;
+            }
+            f(i, start_index(input, inputs, cursor), input);
+        }
+    } else if query_inputs.windows(2).all(|w| w[0] >= w[1]) {
+        let mut cursor = inputs.len() - 1;
+        for (i, &input) in query_inputs.iter().enumerate() {
+            while cursor > 0 && inputs[cursor] > input {
+                cursor -= 1;
+            }
+            f(i, start_index(input, inputs, cursor), input);
+        }
+    } else {
+        for (i, &input) in query_inputs.iter().enumerate() {
+            f(i, get_input_index(input, inputs), input);
+        }
+    }
+}
+
+/// Sample a segment of a curve at a local parameter, without searching for the segment.
+///
+/// The segment is sampled at `t0 + u * (t1 - t0)`, where `t0` and `t1` are the input values of the
+/// keyframes at the start and end of the segment.
+///
+/// ## Parameters:
+///
+/// - `segment_index`: index of the segment, i.e. of the keyframe the segment starts at
+/// - `u`: local parameter inside the segment, in `[0, 1]`
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `normalize`: if true, normalize the interpolated value
+///
+/// ## Returns
+///
+/// The sampled value, or an error if there is no segment `segment_index`, or `u` is outside of
+/// `[0, 1]`.
+pub fn sample_segment_local<T>(
+    segment_index: usize,
+    u: f32,
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    normalize: bool,
+) -> Result<T, InterpolationError>
+where
+    T: InterpolationPrimitive + Clone,
+{
+    if segment_index + 1 >= inputs.len() {
+        return Err(InterpolationError::SegmentOutOfRange);
+    }
+    if !(0. ..=1.).contains(&u) {
+        return Err(InterpolationError::ParameterOutOfRange);
+    }
+    let (t0, t1) = (inputs[segment_index], inputs[segment_index + 1]);
+    let input = t0 + u * (t1 - t0);
+    Ok(function.interpolate_at(Some(segment_index), input, inputs, outputs, normalize))
+}
+
+/// Sample a curve, and report whether the input value was outside the range of the keyframes.
+///
+/// Outside of the range of `inputs` the value of the first or last keyframe is returned, as for
+/// `InterpolationFunction::interpolate`.
+///
+/// ## Parameters:
+///
+/// - `input`: the input value to the function
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `normalize`: if true, normalize the interpolated value
+///
+/// ## Returns
+///
+/// The sampled value, and true if `input` is before the first or after the last keyframe.
+pub fn sample_clamped_flag<T>(
+    input: f32,
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    normalize: bool,
+) -> (T, bool)
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let clamped = input < inputs[0] || input > inputs[inputs.len() - 1];
+    (
+        function.interpolate(input, inputs, outputs, normalize),
+        clamped,
+    )
+}
+
+/// Full description of a sample of a curve, see `sample_debug`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SampleInfo<T> {
+    /// The sampled value
+    pub value: T,
+    /// Index of the keyframe at or before the input value
+    pub left_index: usize,
+    /// Index of the keyframe after the input value, equal to `left_index` outside of the range of
+    /// the keyframes and at the last keyframe
+    pub right_index: usize,
+    /// Interpolation factor between the left and right keyframes, in `[0, 1)`
+    pub factor: f32,
+    /// True if the input value is before the first or after the last keyframe
+    pub clamped: bool,
+}
+
+/// Sample a curve, and report which keyframes and interpolation factor were used.
+///
+/// Intended for debugging and visualization. Before the first keyframe both indices are 0, and at
+/// or after the last keyframe both are the index of the last keyframe, with a factor of 0, as the
+/// value of that keyframe is returned.
+///
+/// ## Parameters:
+///
+/// - `input`: the input value to the function
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `normalize`: if true, normalize the interpolated value
+pub fn sample_debug<T>(
+    input: f32,
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    normalize: bool,
+) -> SampleInfo<T>
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let index = get_input_index(input, inputs);
+    let (left_index, right_index, factor) = match segment_at(index, input, inputs) {
+        Segment::Before => (0, 0, 0.),
+        Segment::After => (inputs.len() - 1, inputs.len() - 1, 0.),
+        Segment::In { index, t_local } => (index, index + 1, t_local),
+    };
+    SampleInfo {
+        value: function.interpolate_at(index, input, inputs, outputs, normalize),
+        left_index,
+        right_index,
+        factor,
+        clamped: input < inputs[0] || input > inputs[inputs.len() - 1],
+    }
+}
+
+/// Sample a curve, borrowing the stored value instead of cloning it when possible.
+///
+/// When `input` is exactly at a keyframe, or outside of the range of the keyframes, the value of
+/// that keyframe is borrowed from `outputs`, as is the value of the current keyframe for step
+/// interpolation. Only samples between keyframes are computed, which avoids copying large
+/// primitives for queries that land on keyframes. Generic functions are always called. The
+/// sampled value is not normalized.
+///
+/// ## Parameters:
+///
+/// - `input`: the input value to the function
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+pub fn sample_cow<'a, T>(
+    input: f32,
+    inputs: &[f32],
+    outputs: &'a [T],
+    function: &InterpolationFunction<T>,
+) -> Cow<'a, T>
+where
+    T: InterpolationPrimitive + Clone,
+{
+    if let InterpolationFunction::Function(ref f) = *function {
+        return Cow::Owned(f(input, inputs, outputs, false));
+    }
+    let index = get_input_index(input, inputs);
+    let keyframe = match segment_at(index, input, inputs) {
+        Segment::Before => Some(0),
+        Segment::After => Some(inputs.len() - 1),
+        Segment::In { index, .. } if matches!(*function, InterpolationFunction::Step) => {
+            Some(index)
+        }
+        Segment::In { index, t_local: 0. } => Some(index),
+        Segment::In { .. } => None,
+    };
+    match keyframe {
+        Some(keyframe) => Cow::Borrowed(&outputs[keyframe_output_index(function, keyframe)]),
+        None => Cow::Owned(function.interpolate_at(index, input, inputs, outputs, false)),
+    }
+}
+
+/// Sample a curve, and snap the value to a grid, e.g. for pixel perfect 2D movement.
+///
+/// Each component of the sampled value is rounded to the nearest multiple of `grid_size`, see
+/// `InterpolationPrimitive::round_to`. Normalization is applied before snapping.
+///
+/// Panics if `T` does not expose its components, see `InterpolationPrimitive::component_count`.
+///
+/// ## Parameters:
+///
+/// - `input`: the input value to the function
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `grid_size`: distance between grid lines, must be positive
+/// - `normalize`: if true, normalize the interpolated value
+pub fn sample_snapped<T>(
+    input: f32,
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    grid_size: f32,
+    normalize: bool,
+) -> T
+where
+    T: InterpolationPrimitive + Clone,
+{
+    function
+        .interpolate(input, inputs, outputs, normalize)
+        .round_to(grid_size)
+}
+
+/// Sample a curve, returning the exact stored value of a keyframe when `input` is close to it.
+///
+/// Query times from a clock rarely land exactly on a keyframe, which makes comparing sampled
+/// values against authored keyframes awkward. If `input` is at most `snap_epsilon` away from the
+/// input of a keyframe, as decided by `snap_to_keyframe`, a clone of the value of the nearest such
+/// keyframe is returned without interpolation or normalization. Otherwise the curve is
+/// interpolated as usual. Generic functions are always called.
+///
+/// ## Parameters:
+///
+/// - `input`: the input value to the function
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `snap_epsilon`: maximum distance to a keyframe input for the keyframe value to be returned
+/// - `normalize`: if true, normalize the interpolated value
+pub fn sample_snap_to_keyframes<T>(
+    input: f32,
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    snap_epsilon: f32,
+    normalize: bool,
+) -> T
+where
+    T: InterpolationPrimitive + Clone,
+{
+    if !matches!(*function, InterpolationFunction::Function(_)) {
+        let snapped = snap_to_keyframe(input, inputs, snap_epsilon);
+        let index = inputs.partition_point(|&t| t < snapped);
+        if index < inputs.len() && inputs[index] == snapped {
+            return outputs[keyframe_output_index(function, index)].clone();
+        }
+    }
+    function.interpolate(input, inputs, outputs, normalize)
+}
+
+/// Sample a curve that holds still for a while at both ends, e.g. for a looping idle animation.
+///
+/// The curve keeps the range of its keyframes, but the value is pinned to the first keyframe for
+/// `hold_start` after the first keyframe, and to the last keyframe for `hold_end` before the last
+/// keyframe, with the keyframes compressed into the remaining time in between. If the holds are
+/// longer than the curve, the value jumps from the first to the last keyframe at the end of
+/// `hold_start`.
+///
+/// ## Parameters:
+///
+/// - `input`: the input value to the function
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `hold_start`: duration the value is pinned to the first keyframe
+/// - `hold_end`: duration the value is pinned to the last keyframe
+/// - `normalize`: if true, normalize the interpolated value
+pub fn sample_with_hold<T>(
+    input: f32,
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    hold_start: f32,
+    hold_end: f32,
+    normalize: bool,
+) -> T
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let start = inputs[0];
+    let end = inputs[inputs.len() - 1];
+    let active_start = start + hold_start.max(0.);
+    let active = end - active_start - hold_end.max(0.);
+    let input = if active > 0. {
+        start + (input - active_start) * (end - start) / active
+    } else if input < active_start {
+        start - 1.
+    } else {
+        end + 1.
+    };
+    function.interpolate(input, inputs, outputs, normalize)
+}
+
+/// Sample a curve, returning `fallback` instead of a non finite result.
+///
+/// Bad data, like keyframes with the same input value or zero length quaternions, can make the
+/// interpolation functions return infinite or NaN values, see `InterpolationPrimitive::is_finite`.
+/// This returns `fallback` in that case, e.g. the value of the nearest keyframe, so bad data never
+/// spreads further.
+///
+/// ## Parameters:
+///
+/// - `input`: the input value to the function
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `normalize`: if true, normalize the interpolated value
+/// - `fallback`: value returned if the interpolated value is not finite
+pub fn sample_or<T>(
+    input: f32,
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    normalize: bool,
+    fallback: T,
+) -> T
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let value = function.interpolate(input, inputs, outputs, normalize);
+    if value.is_finite() {
+        value
+    } else {
+        fallback
+    }
+}
+
+/// Sample a curve and add the value, scaled by `weight`, to `acc`.
+///
+/// Used to blend several curves, e.g. animation layers, by accumulating the weighted samples of
+/// each curve into a single value. `normalize` applies to the sample before it is weighted, the
+/// accumulated value is never normalized.
+///
+/// ## Parameters:
+///
+/// - `acc`: value the weighted sample is added to
+/// - `input`: the input value to the function
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `weight`: factor the sample is scaled by
+/// - `normalize`: if true, normalize the interpolated value
+pub fn accumulate_sample<T>(
+    acc: &mut T,
+    input: f32,
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    weight: f32,
+    normalize: bool,
+) where
+    T: InterpolationPrimitive + Clone,
+{
+    let value = function.interpolate(input, inputs, outputs, normalize);
+    *acc = acc.add(&value.mul(weight));
+}
+
+/// Sample a curve at a fixed frame rate, pairing each sample with its frame index.
+///
+/// Frame `i` is sampled at `inputs[0] + i / fps`, for every frame up to the last keyframe. If the
+/// duration of the curve is not a whole number of frames, a final frame is added at the last
+/// keyframe, holding the end value of the curve.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `fps`: number of frames per unit of input
+/// - `normalize`: if true, normalize the interpolated values
+///
+/// ## Returns
+///
+/// `(frame, value)` for each frame.
+pub fn bake_frames<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    fps: f32,
+    normalize: bool,
+) -> Vec<(u32, T)>
+where
+    T: InterpolationPrimitive + Clone,
+{
+    sample_many(
+        inputs,
+        outputs,
+        function,
+        &frame_times(inputs, fps),
+        normalize,
+    )
+    .into_iter()
+    .enumerate()
+    .map(|(i, value)| (i as u32, value))
+    .collect()
+}
+
+/// Bake a curve into a linear curve, sampled at a fixed frame rate.
+///
+/// The curve is sampled at the same input values as in `bake_frames`. With `preserve_keyframes`,
+/// the input values of the original keyframes are added as well, so the values at the keyframes
+/// are kept exactly, with the frames filling the gaps. Frames within a thousandth of a frame of a
+/// keyframe are replaced by the keyframe.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, laid out as expected by `function`
+/// - `function`: the interpolation function used for the data
+/// - `fps`: number of frames per unit of input
+/// - `preserve_keyframes`: if true, also sample at the input values of the keyframes
+/// - `normalize`: if true, normalize the interpolated values
+///
+/// ## Returns
+///
+/// `(inputs, outputs)` of the linear curve.
+pub fn bake<T>(
+    inputs: &[f32],
+    outputs: &[T],
+    function: &InterpolationFunction<T>,
+    fps: f32,
+    preserve_keyframes: bool,
+    normalize: bool,
+) -> (Vec<f32>, Vec<T>)
+where
+    T: InterpolationPrimitive + Clone,
+{
+    let frames = frame_times(inputs, fps);
+    let times = if preserve_keyframes {
+        let mut times: Vec<f32> = Vec::with_capacity(frames.len() + inputs.len());
+        let mut keyframes = inputs.iter().peekable();
+        for frame in frames {
+            while let Some(&&keyframe) = keyframes.peek() {
+                if keyframe > frame + 1e-3 / fps {
+                    break;
+                }
+                if times.last().is_none_or(|&t| t < keyframe) {
+                    times.push(keyframe);
+                }
+                keyframes.next();
+            }
+            if times.last().is_none_or(|&t| frame - t > 1e-3 / fps) {
+                times.push(frame);
+            }
+        }
+        times
+    } else {
+        frames
+    };
+    let values = sample_many(inputs, outputs, function, &times, normalize);
+    (times, values)
+}
+
+// Frame times from the first to the last keyframe, see `bake_frames`.
+fn frame_times(inputs: &[f32], fps: f32) -> Vec<f32> {
+    let start = inputs[0];
+    let end = inputs[inputs.len() - 1];
+    // Allow for rounding errors, so a whole number of frames does not get an extra frame
+    let frames = ((end - start) * fps + 1e-3).floor() as u32;
+    let mut times = (0..=frames)
+        .map(|i| start + i as f32 / fps)
+        .collect::<Vec<_>>();
+    if times[frames as usize] < end - 1e-3 / fps {
+        times.push(end);
+    } else {
+        times[frames as usize] = end;
+    }
+    times
+}
+
+// Keyframe index for `input`, given the cursor position, matching `get_input_index`.
+fn start_index(input: f32, inputs: &[f32], cursor: usize) -> Option<usize> {
+    if input < inputs[0] {
+        None
+    } else {
+        Some(cursor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mint::Vector3;
+    use std::cell::Cell;
+
+    thread_local! {
+        static CLONES: Cell<usize> = const { Cell::new(0) };
+    }
+
+    // Scalar that counts how many times it is cloned
+    #[derive(Debug, PartialEq)]
+    struct Counted(f32);
+
+    impl Clone for Counted {
+        fn clone(&self) -> Self {
+            CLONES.with(|c| c.set(c.get() + 1));
+            Counted(self.0)
+        }
+    }
+
+    impl InterpolationPrimitive for Counted {
+        fn add(&self, other: &Self) -> Self {
+            Counted(self.0 + other.0)
+        }
+
+        fn sub(&self, other: &Self) -> Self {
+            Counted(self.0 - other.0)
+        }
+
+        fn mul(&self, scalar: f32) -> Self {
+            Counted(self.0 * scalar)
+        }
+
+        fn dot(&self, other: &Self) -> f32 {
+            self.0 * other.0
+        }
+
+        fn magnitude2(&self) -> f32 {
+            self.0 * self.0
+        }
+
+        fn component_count() -> usize {
+            1
+        }
+
+        fn write_components(&self, out: &mut [f32]) {
+            out[0] = self.0;
+        }
+
+        fn from_components(src: &[f32]) -> Self {
+            Counted(src[0])
+        }
+    }
+
+    fn assert_matches_single(query_inputs: &[f32]) {
+        let input = vec![0., 1., 2., 3., 4.];
+        let output = vec![1., 0., 1., 0., -1., 0., -1.];
+        let function = InterpolationFunction::CatmullRomSpline;
+        let samples = sample_many(&input, &output, &function, query_inputs, false);
+        for (&t, &value) in query_inputs.iter().zip(samples.iter()) {
+            assert_eq!(function.interpolate(t, &input, &output, false), value);
+        }
+    }
+
+    #[test]
+    fn test_sample_many_ascending() {
+        assert_matches_single(&[-1., 0., 0.3, 1., 1.5, 1.5, 2.9, 4., 5.]);
+    }
+
+    #[test]
+    fn test_sample_many_descending() {
+        assert_matches_single(&[5., 4., 3.5, 3., 2.2, 2.2, 0.1, 0., -0.5]);
+    }
+
+    #[test]
+    fn test_sample_many_unsorted() {
+        assert_matches_single(&[2.5, -1., 3.7, 0.2, 4.5, 1.]);
+    }
+
+    #[test]
+    fn test_sample_many_into() {
+        let input = vec![0., 1., 2.5];
+        let output = vec![[1., 0.], [0., 0.], [1., 2.], [3., 1.], [0., 1.]];
+        let function = InterpolationFunction::CatmullRomSpline;
+        for query_inputs in &[[-1., 0.5, 1.2, 3.], [2.4, 2., 0.1, -1.], [2., 0.2, 1.5, 0.]] {
+            let mut out = [[0.; 2]; 4];
+            sample_many_into(&input, &output, &function, query_inputs, false, &mut out);
+            assert_eq!(
+                sample_many(&input, &output, &function, query_inputs, false),
+                out.to_vec()
+            );
+        }
+    }
+
+    #[test]
+    fn test_sample_segment_local() {
+        let input = vec![0., 1., 2.5, 3.];
+        let output = vec![1., 0., 1., 0., 2., 1.];
+        let function = InterpolationFunction::CatmullRomSpline;
+        for i in 0..3 {
+            let midpoint = (input[i] + input[i + 1]) / 2.;
+            assert_eq!(
+                Ok(function.interpolate(midpoint, &input, &output, false)),
+                sample_segment_local(i, 0.5, &input, &output, &function, false)
+            );
+        }
+        assert_eq!(
+            Ok(2.),
+            sample_segment_local(2, 1., &input, &output, &function, false)
+        );
+        assert_eq!(
+            Err(InterpolationError::SegmentOutOfRange),
+            sample_segment_local(3, 0.5, &input, &output, &function, false)
+        );
+        assert_eq!(
+            Err(InterpolationError::ParameterOutOfRange),
+            sample_segment_local(0, 1.5, &input, &output, &function, false)
+        );
+    }
+
+    #[test]
+    fn test_sample_clamped_flag() {
+        let input = vec![0., 1., 2.];
+        let output = vec![1., 0., 1., 0., 2.];
+        let function = InterpolationFunction::CatmullRomSpline;
+        assert_eq!(
+            (function.interpolate(1.5, &input, &output, false), false),
+            sample_clamped_flag(1.5, &input, &output, &function, false)
+        );
+        assert_eq!(
+            (0., false),
+            sample_clamped_flag(2., &input, &output, &function, false)
+        );
+        assert_eq!(
+            (0., true),
+            sample_clamped_flag(2.5, &input, &output, &function, false)
+        );
+        assert_eq!(
+            (0., true),
+            sample_clamped_flag(-1., &input, &output, &function, false)
+        );
+    }
+
+    #[test]
+    fn test_sample_cow_borrows_keyframes() {
+        let input = vec![0., 1., 2.];
+        let output = vec![Counted(0.), Counted(2.), Counted(3.)];
+        let function = InterpolationFunction::Linear;
+        CLONES.with(|c| c.set(0));
+        for &t in &[1., -1., 2., 5.] {
+            match sample_cow(t, &input, &output, &function) {
+                Cow::Borrowed(value) => {
+                    assert_eq!(&function.interpolate(t, &input, &output, false), value)
+                }
+                Cow::Owned(_) => panic!("Keyframe value was not borrowed"),
+            }
+        }
+        CLONES.with(|c| c.set(0));
+        let _ = sample_cow(1., &input, &output, &function);
+        assert_eq!(0, CLONES.with(|c| c.get()));
+        match sample_cow(0.5, &input, &output, &function) {
+            Cow::Owned(value) => assert_eq!(Counted(1.), value),
+            Cow::Borrowed(_) => panic!("Interpolated value was borrowed"),
+        }
+        match sample_cow(1.5, &input, &output, &InterpolationFunction::Step) {
+            Cow::Borrowed(value) => assert_eq!(&output[1], value),
+            Cow::Owned(_) => panic!("Step value was not borrowed"),
+        }
+    }
+
+    #[test]
+    fn test_sample_snapped() {
+        let input = vec![0., 1.];
+        let output = vec![Vector3::from([0., 0., 0.]), Vector3::from([10., -5., 3.])];
+        let function = InterpolationFunction::Linear;
+        for i in 0..=20 {
+            let t = i as f32 / 20.;
+            let value = sample_snapped(t, &input, &output, &function, 1., false);
+            let exact = function.interpolate(t, &input, &output, false);
+            for &(c, e) in &[(value.x, exact.x), (value.y, exact.y), (value.z, exact.z)] {
+                assert_eq!(c.round(), c);
+                assert!((c - e).abs() <= 0.5);
+            }
+        }
+        assert_eq!(
+            Vector3::from([3.5, -2., 1.]),
+            sample_snapped(0.35, &input, &output, &function, 0.5, false)
+        );
+    }
+
+    #[test]
+    fn test_sample_snap_to_keyframes() {
+        let input = vec![0., 1., 2.];
+        let output = vec![[0.1, 0.], [0., 0.3], [1., 0.7], [2., 0.2], [3., 0.]];
+        let function = InterpolationFunction::CatmullRomSpline;
+        let sample = |t| sample_snap_to_keyframes(t, &input, &output, &function, 1e-4, false);
+        assert_eq!([1., 0.7], sample(1. - 1e-6));
+        assert_eq!([1., 0.7], sample(1. + 1e-6));
+        assert_eq!([0., 0.3], sample(1e-6));
+        assert_eq!(
+            [1., 0.7],
+            sample_snap_to_keyframes(1.25, &input, &output, &function, 0.25, false)
+        );
+        assert_eq!(
+            function.interpolate(0.5, &input, &output, false),
+            sample(0.5)
+        );
+    }
+
+    #[test]
+    fn test_sample_with_hold() {
+        let input = vec![0., 1., 4.];
+        let output = vec![1., 0., 1., 2., 3.];
+        let function = InterpolationFunction::CatmullRomSpline;
+        let sample = |t| sample_with_hold(t, &input, &output, &function, 1., 0.5, false);
+        for i in 0..100 {
+            assert_eq!(0., sample(i as f32 / 100.));
+        }
+        for i in 0..=50 {
+            assert_eq!(2., sample(3.5 + i as f32 / 100.));
+        }
+        // The keyframes are compressed from 4 to 2.5 time units
+        assert_eq!(
+            function.interpolate(2., &input, &output, false),
+            sample(1. + 2. * 2.5 / 4.)
+        );
+    }
+
+    #[test]
+    fn test_sample_debug() {
+        let input = vec![0., 1., 3.];
+        let output = vec![0., 2., 3.];
+        let function = InterpolationFunction::Linear;
+        assert_eq!(
+            SampleInfo {
+                value: 2.5,
+                left_index: 1,
+                right_index: 2,
+                factor: 0.5,
+                clamped: false,
+            },
+            sample_debug(2., &input, &output, &function, false)
+        );
+        assert_eq!(
+            SampleInfo {
+                value: 2.,
+                left_index: 1,
+                right_index: 2,
+                factor: 0.,
+                clamped: false,
+            },
+            sample_debug(1., &input, &output, &function, false)
+        );
+        assert_eq!(
+            SampleInfo {
+                value: 3.,
+                left_index: 2,
+                right_index: 2,
+                factor: 0.,
+                clamped: false,
+            },
+            sample_debug(3., &input, &output, &function, false)
+        );
+    }
+
+    #[test]
+    fn test_sample_debug_out_of_range() {
+        let input = vec![0., 1., 3.];
+        let output = vec![0., 2., 3.];
+        let function = InterpolationFunction::Linear;
+        let before = sample_debug(-1., &input, &output, &function, false);
+        assert_eq!(
+            (0., 0, 0, true),
+            (
+                before.value,
+                before.left_index,
+                before.right_index,
+                before.clamped
+            )
+        );
+        let after = sample_debug(4., &input, &output, &function, false);
+        assert_eq!(
+            (3., 2, 2, true),
+            (
+                after.value,
+                after.left_index,
+                after.right_index,
+                after.clamped
+            )
+        );
+    }
+
+    #[test]
+    fn test_bake_frames() {
+        let input = vec![0., 0.4, 1.];
+        let output: Vec<f32> = vec![0., 2., 3.];
+        let function = InterpolationFunction::Linear;
+        let frames = bake_frames(&input, &output, &function, 30., false);
+        assert_eq!(31, frames.len());
+        for (i, &(frame, value)) in frames.iter().enumerate() {
+            assert_eq!(i as u32, frame);
+            let t = i as f32 / 30.;
+            assert!((function.interpolate(t, &input, &output, false) - value).abs() < 1e-5);
+        }
+        assert_eq!((30, 3.), frames[30]);
+    }
+
+    #[test]
+    fn test_bake_frames_partial() {
+        let input = vec![0., 0.11];
+        let output = vec![0., 1.];
+        let frames = bake_frames(&input, &output, &InterpolationFunction::Step, 30., false);
+        assert_eq!(
+            vec![0, 1, 2, 3, 4],
+            frames.iter().map(|f| f.0).collect::<Vec<_>>()
+        );
+        assert_eq!((4, 1.), frames[4]);
+    }
+
+    #[test]
+    fn test_bake_preserve_keyframes() {
+        let input = vec![0., 0.35, 0.5, 0.62];
+        let output: Vec<f32> = vec![0., 2., 3., 1.];
+        let function = InterpolationFunction::Linear;
+        let (times, values) = bake(&input, &output, &function, 10., false, false);
+        assert_eq!(8, times.len());
+        assert!(!times.contains(&0.35));
+        let (times, values_preserved) = bake(&input, &output, &function, 10., true, false);
+        for (&t, value) in input.iter().zip(&output) {
+            let index = times.iter().position(|&time| time == t).unwrap();
+            assert_eq!(*value, values_preserved[index]);
+        }
+        assert!(times.windows(2).all(|w| w[0] < w[1]));
+        // 0.5 is both a frame and a keyframe
+        assert_eq!(9, times.len());
+        assert_eq!(values.len() + 1, values_preserved.len());
+    }
+
+    #[test]
+    fn test_accumulate_sample() {
+        let input = vec![0., 1.];
+        let layers = [
+            (
+                vec![Vector3::from([0., 0., 0.]), Vector3::from([2., 0., 0.])],
+                InterpolationFunction::Linear,
+                0.5,
+            ),
+            (
+                vec![Vector3::from([0., 1., 0.]), Vector3::from([0., 3., 0.])],
+                InterpolationFunction::Step,
+                0.25,
+            ),
+            (
+                vec![Vector3::from([0., 0., 4.]), Vector3::from([4., 0., 0.])],
+                InterpolationFunction::SphericalLinear,
+                2.,
+            ),
+        ];
+        let mut acc = Vector3::from([1., 1., 1.]);
+        let mut expected = acc;
+        for (output, function, weight) in &layers {
+            accumulate_sample(&mut acc, 0.5, &input, output, function, *weight, false);
+            let value = function.interpolate(0.5, &input, output, false);
+            expected = expected.add(&value.mul(*weight));
+        }
+        assert_eq!(expected, acc);
+        assert!((acc.x - (1. + 0.5 + 2. * 8f32.sqrt())).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_sample_or() {
+        let input = vec![0., 1.];
+        let output = vec![Vector3::from([1., 0., 0.]), Vector3::from([-1., 0., 0.])];
+        let function = InterpolationFunction::Linear;
+        let fallback = Vector3::from([0., 1., 0.]);
+        // The value in the middle has zero length, and can't be normalized
+        assert!(!function.interpolate(0.5, &input, &output, true).is_finite());
+        assert_eq!(
+            fallback,
+            sample_or(0.5, &input, &output, &function, true, fallback)
+        );
+        assert_eq!(
+            Vector3::from([1., 0., 0.]),
+            sample_or(0.25, &input, &output, &function, true, fallback)
+        );
+    }
+}
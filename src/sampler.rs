@@ -0,0 +1,99 @@
+use curve::Curve;
+use primitive::InterpolationPrimitive;
+
+/// Anything that can be sampled at an input value, producing an output value.
+///
+/// Implemented by `Curve`, and by alternative keyframe storage such as `QuantizedTrack` and
+/// `RunLengthTrack`.
+pub trait Sampler<T> {
+    /// Sample the value at the given input value
+    fn sample(&self, input: f32) -> T;
+}
+
+impl<T> Sampler<T> for Curve<T>
+where
+    T: InterpolationPrimitive + Clone,
+{
+    fn sample(&self, input: f32) -> T {
+        Curve::sample(self, input)
+    }
+}
+
+/// Sampler wrapper that remembers the previous sample, for transmitting changes between samples.
+///
+/// Each sample returns the value together with the difference from the previous sample. The first
+/// sample, and the first after `reset`, has no previous sample, and its delta is the value itself,
+/// so the sum of all deltas is always the latest value.
+#[derive(Clone, Debug)]
+pub struct DeltaSampler<T, S> {
+    sampler: S,
+    previous: Option<T>,
+}
+
+impl<T, S> DeltaSampler<T, S>
+where
+    T: InterpolationPrimitive + Clone,
+    S: Sampler<T>,
+{
+    /// Create a new delta sampler, without a previous sample
+    pub fn new(sampler: S) -> Self {
+        DeltaSampler {
+            sampler,
+            previous: None,
+        }
+    }
+
+    /// Sample the value at the given input value, returning `(value, value - previous)`
+    pub fn sample(&mut self, input: f32) -> (T, T) {
+        let value = self.sampler.sample(input);
+        let delta = match self.previous {
+            Some(ref previous) => value.sub(previous),
+            None => value.clone(),
+        };
+        self.previous = Some(value.clone());
+        (value, delta)
+    }
+
+    /// Forget the previous sample, e.g. when a full value is transmitted
+    pub fn reset(&mut self) {
+        self.previous = None;
+    }
+
+    /// The underlying sampler
+    pub fn sampler(&self) -> &S {
+        &self.sampler
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mint::Vector3;
+    use InterpolationFunction;
+
+    #[test]
+    fn test_delta_sampler_sums_to_values() {
+        let curve = Curve::new(
+            vec![0., 1., 2.],
+            vec![
+                Vector3::from([0., 0., 0.]),
+                Vector3::from([1., 2., 0.]),
+                Vector3::from([3., -1., 4.]),
+            ],
+            InterpolationFunction::Linear,
+            false,
+        );
+        let mut sampler = DeltaSampler::new(curve.clone());
+        let mut sum = Vector3::from([0., 0., 0.]);
+        for i in 0..=25 {
+            let t = i as f32 / 10.;
+            let (value, delta) = sampler.sample(t);
+            sum = sum.add(&delta);
+            assert_eq!(curve.sample(t), value);
+            assert!(sum.sub(&value).magnitude() < 1e-5);
+        }
+        sampler.reset();
+        let (value, delta) = sampler.sample(1.5);
+        assert_eq!(value, delta);
+    }
+}
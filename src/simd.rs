@@ -0,0 +1,104 @@
+use primitive::InterpolationPrimitive;
+use {resolve_segment, Segment};
+
+/// Cubic Hermite spline interpolation of `[f32; 4]` values, evaluating all four components at
+/// once using SIMD instructions.
+///
+/// Same as `cubic_spline_interpolate`, the result matches it within floating point tolerance. On
+/// targets without SIMD support the components are evaluated one at a time.
+///
+/// ## Parameters:
+///
+/// - `input`: the input value to the function
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values to interpolate between, laid out as for
+///   `cubic_spline_interpolate`
+/// - `normalize`: if true, normalize the interpolated value before returning it
+pub fn cubic_spline_interpolate_simd(
+    input: f32,
+    inputs: &[f32],
+    outputs: &[[f32; 4]],
+    normalize: bool,
+) -> [f32; 4] {
+    let (index, t) = match resolve_segment(input, inputs) {
+        Segment::Before => return outputs[1],
+        Segment::After => return outputs[outputs.len() - 2],
+        Segment::In { index, t_local } => (index, t_local),
+    };
+    let t_diff = inputs[index + 1] - inputs[index];
+    let t2 = t * t;
+    let t3 = t2 * t;
+    // The tangents are scaled by the length of the segment through their weights
+    let weights = [
+        2. * t3 - 3. * t2 + 1.,
+        -2. * t3 + 3. * t2,
+        (t3 - 2. * t2 + t) * t_diff,
+        (t3 - t2) * t_diff,
+    ];
+    let left = index * 3;
+    let right = (index + 1) * 3;
+    let v = weighted_sum(
+        &weights,
+        [
+            &outputs[left + 1],
+            &outputs[right + 1],
+            &outputs[left + 2],
+            &outputs[right],
+        ],
+    );
+    if normalize {
+        v.normalize()
+    } else {
+        v
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn weighted_sum(weights: &[f32; 4], values: [&[f32; 4]; 4]) -> [f32; 4] {
+    use std::arch::x86_64::*;
+
+    let mut out = [0.; 4];
+    // SSE is part of the x86_64 baseline, and all pointers are to four valid `f32`s
+    unsafe {
+        let mut sum = _mm_setzero_ps();
+        for (&weight, value) in weights.iter().zip(values.iter()) {
+            let term = _mm_mul_ps(_mm_loadu_ps(value.as_ptr()), _mm_set1_ps(weight));
+            sum = _mm_add_ps(sum, term);
+        }
+        _mm_storeu_ps(out.as_mut_ptr(), sum);
+    }
+    out
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn weighted_sum(weights: &[f32; 4], values: [&[f32; 4]; 4]) -> [f32; 4] {
+    weights
+        .iter()
+        .zip(values.iter())
+        .fold([0.; 4], |sum, (&weight, value)| sum.add(&value.mul(weight)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cubic_spline::cubic_spline_interpolate;
+
+    proptest! {
+        #[test]
+        fn prop_simd_matches_scalar(
+            values in proptest::collection::vec(-10f32..10., 36),
+            spacing in proptest::collection::vec(0.1f32..2., 2),
+            input in -1f32..5.,
+            normalize in proptest::bool::ANY,
+        ) {
+            let inputs = [0., spacing[0], spacing[0] + spacing[1]];
+            let outputs = values
+                .chunks(4)
+                .map(|c| [c[0], c[1], c[2], c[3]])
+                .collect::<Vec<_>>();
+            let expected = cubic_spline_interpolate(input, &inputs, &outputs, normalize);
+            let actual = cubic_spline_interpolate_simd(input, &inputs, &outputs, normalize);
+            prop_assert!(actual.sub(&expected).magnitude() <= 1e-4 * (1. + expected.magnitude()));
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use primitive::InterpolationPrimitive;
+
+/// Remove redundant keyframes from a linear curve.
+///
+/// A keyframe is removed if it lies within `tolerance` of the straight line between its
+/// neighbouring keyframes, in (input, value) space. The distance is measured from the value of the
+/// keyframe to the linearly interpolated value at its input, using `sub().magnitude2().sqrt()`.
+/// When several consecutive keyframes are removed, all of them are kept within `tolerance` of the
+/// resulting segment, so the result never deviates more than `tolerance` from the original curve.
+///
+/// ## Parameters:
+///
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values, the same size as `inputs`
+/// - `tolerance`: maximum distance from the line for a keyframe to be removed
+///
+/// ## Returns
+///
+/// `(inputs, outputs)` of the simplified linear curve.
+pub fn remove_collinear<T>(inputs: &[f32], outputs: &[T], tolerance: f32) -> (Vec<f32>, Vec<T>)
+where
+    T: InterpolationPrimitive + Clone,
+{
+    if inputs.len() < 3 {
+        return (inputs.to_vec(), outputs.to_vec());
+    }
+    let mut new_inputs = vec![inputs[0]];
+    let mut new_outputs = vec![outputs[0].clone()];
+    let mut anchor = 0;
+    for i in 1..inputs.len() - 1 {
+        let next = i + 1;
+        let collinear = (anchor + 1..next).all(|j| {
+            let d = (inputs[j] - inputs[anchor]) / (inputs[next] - inputs[anchor]);
+            let line = outputs[anchor].add(&outputs[next].sub(&outputs[anchor]).mul(d));
+            outputs[j].sub(&line).magnitude2().sqrt() <= tolerance
+        });
+        if !collinear {
+            new_inputs.push(inputs[i]);
+            new_outputs.push(outputs[i].clone());
+            anchor = i;
+        }
+    }
+    new_inputs.push(inputs[inputs.len() - 1]);
+    new_outputs.push(outputs[outputs.len() - 1].clone());
+    (new_inputs, new_outputs)
+}
+
+/// Find runs of consecutive keyframes with (nearly) identical values.
+///
+/// Two consecutive keyframes belong to the same run if the distance between their values, using
+/// `sub().magnitude2().sqrt()`, is within `tolerance`. Such runs are candidates for being collapsed
+/// to a single step keyframe, or removed entirely. Only the values are compared, so for spline
+/// interpolation the tangents should be checked separately before collapsing a run.
+///
+/// ## Parameters:
+///
+/// - `outputs`: list of keyframe values
+/// - `tolerance`: maximum distance between consecutive values in a run
+///
+/// ## Returns
+///
+/// The `(start, end)` indices of each run of at least two keyframes, both inclusive, in order.
+pub fn find_plateaus<T>(outputs: &[T], tolerance: f32) -> Vec<(usize, usize)>
+where
+    T: InterpolationPrimitive,
+{
+    let mut plateaus = Vec::new();
+    let mut start = 0;
+    for i in 1..=outputs.len() {
+        if i == outputs.len() || outputs[i].sub(&outputs[i - 1]).magnitude2().sqrt() > tolerance {
+            if i - start > 1 {
+                plateaus.push((start, i - 1));
+            }
+            start = i;
+        }
+    }
+    plateaus
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mint::Vector3;
+
+    #[test]
+    fn test_remove_collinear() {
+        let input = vec![0., 1., 2., 4., 5.];
+        let output = vec![
+            Vector3::from([0., 0., 0.]),
+            Vector3::from([1., 2., 0.]),
+            Vector3::from([2., 4., 0.]),
+            Vector3::from([4., 8.0001, 0.]),
+            Vector3::from([5., 10., 0.]),
+        ];
+        let (new_input, new_output) = remove_collinear(&input, &output, 1e-3);
+        assert_eq!(vec![0., 5.], new_input);
+        assert_eq!(vec![output[0], output[4]], new_output);
+    }
+
+    #[test]
+    fn test_remove_collinear_keeps_corners() {
+        let input = vec![0., 1., 2., 3., 4.];
+        let output = vec![0., 1., 2., 1., 0.];
+        let (new_input, new_output) = remove_collinear(&input, &output, 1e-3);
+        assert_eq!(vec![0., 2., 4.], new_input);
+        assert_eq!(vec![0., 2., 0.], new_output);
+    }
+
+    #[test]
+    fn test_remove_collinear_keeps_dips() {
+        let input = vec![0., 1., 2., 3., 4.];
+        let output = vec![0., -1., -2., -1., 0.];
+        let (new_input, new_output) = remove_collinear(&input, &output, 1e-3);
+        assert_eq!(vec![0., 2., 4.], new_input);
+        assert_eq!(vec![0., -2., 0.], new_output);
+    }
+
+    #[test]
+    fn test_find_plateaus() {
+        let output = vec![0., 1., 2., 2.0001, 2., 2., 3., 4., 4.];
+        assert_eq!(vec![(2, 5), (7, 8)], find_plateaus(&output, 1e-3));
+        assert_eq!(
+            Vec::<(usize, usize)>::new(),
+            find_plateaus::<f32>(&[], 1e-3)
+        );
+    }
+
+    #[test]
+    fn test_find_plateaus_descending() {
+        assert_eq!(
+            Vec::<(usize, usize)>::new(),
+            find_plateaus(&[3., 2., 1., 0.], 1e-3)
+        );
+        assert_eq!(vec![(1, 2)], find_plateaus(&[3., 2., 2., 1.], 1e-3));
+    }
+}
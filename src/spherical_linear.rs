@@ -1,6 +1,7 @@
-use get_input_index;
 use num::cast;
 use primitive::InterpolationPrimitive;
+use std::f32::consts::{FRAC_PI_2, PI};
+use {resolve_segment, Segment};
 
 /// Do spherical linear interpolation.
 ///
@@ -12,12 +13,20 @@ use primitive::InterpolationPrimitive;
 /// `t0 = input at left keyframe`
 /// `t1 = input at right keyframe`
 ///
+/// If `p0 . p1` is negative, `p1` is negated first when the type considers `v` and `-v` equal, see
+/// `InterpolationPrimitive::antipodal_equivalent`, which for quaternions means the rotation takes
+/// the shortest path.
+///
+/// Unit complex numbers `[cos a, sin a]` stored as `[f32; 2]` or `Vector2<f32>` are interpolated as
+/// 2D rotations, along the shortest arc of the unit circle, which interpolates the angle `a` at
+/// constant speed.
+///
 /// ## Parameters:
 ///
 /// - `input`: the input value to the function
 /// - `inputs`: list of discrete input values for each keyframe
 /// - `outputs`: list of output values to interpolate between, for spherical
-///              linear interpolation this should be the same size as `inputs`
+///   linear interpolation this should be the same size as `inputs`
 /// - `normalize`: if true, normalize the interpolated value before returning it
 pub fn spherical_linear_interpolate<T>(
     input: f32,
@@ -28,54 +37,198 @@ pub fn spherical_linear_interpolate<T>(
 where
     T: InterpolationPrimitive + Clone,
 {
-    let input_index = match get_input_index(input, inputs) {
-        Some(index) => index,
-        None => return outputs[0].clone(),
-    };
-    if input_index >= (inputs.len() - 1) {
-        outputs[outputs.len() - 1].clone()
-    } else {
-        let d = (input - inputs[input_index]) / (inputs[input_index + 1] - inputs[input_index]);
-        let left = &outputs[input_index];
-        let mut right = outputs[input_index + 1].clone();
-
-        let mut dot = left.dot(&right);
-        if dot < 0. {
-            dot = -dot;
-            right = right.mul(-1.);
+    slerp_with_metric(input, inputs, outputs, |a, b| a.dot(b), normalize)
+}
+
+/// Do spherical linear interpolation, using a custom inner product.
+///
+/// Same as `spherical_linear_interpolate`, but the angle between the keyframes is computed using
+/// `metric` instead of `InterpolationPrimitive::dot`.
+///
+/// `f(t) = sin((1 - d) * a) / sin (a) * p0 + sin(d * a) / sin (a) * p1`
+/// `a = acos(metric(p0, p1))`
+///
+/// ## Parameters:
+///
+/// - `input`: the input value to the function
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values to interpolate between, for spherical
+///   linear interpolation this should be the same size as `inputs`
+/// - `metric`: inner product used to compute the angle between two outputs
+/// - `normalize`: if true, normalize the interpolated value before returning it
+pub fn slerp_with_metric<T, F>(
+    input: f32,
+    inputs: &[f32],
+    outputs: &[T],
+    metric: F,
+    normalize: bool,
+) -> T
+where
+    T: InterpolationPrimitive + Clone,
+    F: Fn(&T, &T) -> f32,
+{
+    match resolve_segment(input, inputs) {
+        Segment::Before => outputs[0].clone(),
+        Segment::After => outputs[outputs.len() - 1].clone(),
+        Segment::In { index, t_local } => slerp_segment(t_local, index, outputs, metric, normalize),
+    }
+}
+
+/// Do spherical linear interpolation, with results that are the same on all platforms.
+///
+/// Same as `spherical_linear_interpolate`, but `acos` and `sin` are replaced by polynomial
+/// approximations, which only use basic arithmetic and `sqrt`. These are exactly specified by IEEE
+/// 754, while the results of `acos` and `sin` depend on the platform and the math library, so this
+/// gives bit for bit identical results everywhere, e.g. for golden image tests.
+///
+/// The trade-off is accuracy: the interpolated values differ from `spherical_linear_interpolate`
+/// by at most around `1e-6` for unit length keyframes, a few ulps more than the error of the
+/// standard library functions. Unlike `quasi_spherical_linear_interpolate`, the interpolation still
+/// has constant angular velocity.
+///
+/// ## Parameters:
+///
+/// - `input`: the input value to the function
+/// - `inputs`: list of discrete input values for each keyframe
+/// - `outputs`: list of output values to interpolate between, for spherical
+///   linear interpolation this should be the same size as `inputs`
+/// - `normalize`: if true, normalize the interpolated value before returning it
+pub fn spherical_linear_interpolate_deterministic<T>(
+    input: f32,
+    inputs: &[f32],
+    outputs: &[T],
+    normalize: bool,
+) -> T
+where
+    T: InterpolationPrimitive + Clone,
+{
+    match resolve_segment(input, inputs) {
+        Segment::Before => outputs[0].clone(),
+        Segment::After => outputs[outputs.len() - 1].clone(),
+        Segment::In { index, t_local } => {
+            slerp_segment_with(t_local, index, outputs, |a, b| a.dot(b), normalize, true)
         }
-        let dot_threshold = cast(0.9995f32).unwrap();
-        let v = if dot > dot_threshold {
-            left.add(&right.sub(&left).mul(d))
+    }
+}
+
+// Interpolate at `d` inside the segment starting at keyframe `index`.
+pub(crate) fn slerp_segment<T, F>(
+    d: f32,
+    index: usize,
+    outputs: &[T],
+    metric: F,
+    normalize: bool,
+) -> T
+where
+    T: InterpolationPrimitive + Clone,
+    F: Fn(&T, &T) -> f32,
+{
+    slerp_segment_with(d, index, outputs, metric, normalize, false)
+}
+
+// Interpolate at `d` inside the segment starting at keyframe `index`, using the polynomial
+// approximations of the trigonometric functions if `deterministic` is true.
+fn slerp_segment_with<T, F>(
+    d: f32,
+    index: usize,
+    outputs: &[T],
+    metric: F,
+    normalize: bool,
+    deterministic: bool,
+) -> T
+where
+    T: InterpolationPrimitive + Clone,
+    F: Fn(&T, &T) -> f32,
+{
+    let left = &outputs[index];
+    let mut right = outputs[index + 1].clone();
+
+    let mut dot = metric(left, &right);
+    if dot < 0. && T::antipodal_equivalent() {
+        dot = -dot;
+        right = right.mul(-1.);
+    }
+    let dot_threshold = cast(0.9995f32).unwrap();
+    let v = if dot > dot_threshold {
+        left.add(&right.sub(left).mul(d))
+    } else {
+        let dot = dot.clamp(-1., 1.);
+        let theta = if deterministic {
+            acos_approx(dot)
         } else {
-            let r_dot = if dot > 1. {
-                1.
-            } else if dot < -1. {
-                -1.
+            dot.acos()
+        };
+        let sin = |x: f32| {
+            if deterministic {
+                sin_approx(x)
             } else {
-                dot
-            };
+                x.sin()
+            }
+        };
 
-            let theta = r_dot.acos();
+        let scale1 = sin(theta * (1. - d));
+        let scale2 = sin(theta * d);
+        left.mul(scale1)
+            .add(&right.mul(scale2))
+            .mul(sin(theta).recip())
+    };
+    if normalize {
+        v.normalize()
+    } else {
+        v
+    }
+}
 
-            let scale1 = (theta * (1. - d)).sin();
-            let scale2 = (theta * d).sin();
-            left.mul(scale1)
-                .add(&right.mul(scale2))
-                .mul(theta.sin().recip())
-        };
-        if normalize {
-            v.normalize()
-        } else {
-            v
-        }
+// Coefficients of the approximation of `acos(x) / sqrt(1 - x)` for `x` in `[0, 1]`, from
+// Abramowitz and Stegun 4.4.46, with an absolute error below 2e-8.
+const ACOS_COEFFICIENTS: [f32; 8] = [
+    FRAC_PI_2,
+    -0.214_598_8,
+    0.088_978_99,
+    -0.050_174_303,
+    0.030_891_88,
+    -0.017_088_126,
+    0.006_670_09,
+    -0.001_262_491_1,
+];
+
+// Arc cosine for `x` in `[-1, 1]`, using only basic arithmetic and `sqrt`.
+fn acos_approx(x: f32) -> f32 {
+    let a = x.abs();
+    let polynomial = ACOS_COEFFICIENTS
+        .iter()
+        .rev()
+        .fold(0., |sum, c| sum * a + c);
+    let result = (1. - a).sqrt() * polynomial;
+    if x < 0. {
+        PI - result
+    } else {
+        result
     }
 }
 
+// Sine for `x` in `[0, PI]`, using the Taylor series up to `x^11` around 0 after mapping `x` to
+// `[0, PI / 2]`, with a truncation error below 1e-7.
+fn sin_approx(x: f32) -> f32 {
+    let x = if x > FRAC_PI_2 { PI - x } else { x };
+    let x2 = x * x;
+    let polynomial = [
+        -1. / 39_916_800.,
+        1. / 362_880.,
+        -1. / 5_040.,
+        1. / 120.,
+        -1. / 6.,
+        1.,
+    ]
+    .iter()
+    .fold(0., |sum, c| sum * x2 + c);
+    x * polynomial
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use mint::{Quaternion, Vector3};
+    use mint::{Quaternion, Vector2, Vector3};
     use std;
 
     #[test]
@@ -141,4 +294,68 @@ mod tests {
             spherical_linear_interpolate(0.5, &input, &output, true)
         );
     }
+
+    #[test]
+    fn test_slerp_with_dot_metric() {
+        let input = vec![0., 1., 2.];
+        let output = vec![
+            Quaternion::from([0., 0., 0., 1.]),
+            Quaternion::from([0., 0., 0.70710677, 0.70710677]),
+            Quaternion::from([0., 0., 1., 0.]),
+        ];
+        for &t in &[0.25, 0.5, 1.3, 1.9] {
+            assert_eq!(
+                spherical_linear_interpolate(t, &input, &output, true),
+                slerp_with_metric(t, &input, &output, |a, b| a.dot(b), true)
+            );
+        }
+    }
+
+    #[test]
+    fn test_slerp_complex_rotation() {
+        let rotation = |degrees: f32| [degrees.to_radians().cos(), degrees.to_radians().sin()];
+        let angle = |v: [f32; 2]| v[1].atan2(v[0]).to_degrees();
+        let input = vec![0., 1.];
+        // Short way through 0 degrees
+        let output = vec![rotation(10.), rotation(350.)];
+        let v = spherical_linear_interpolate(0.25, &input, &output, false);
+        assert!((angle(v) - 5.).abs() < 1e-3);
+        assert!((v.magnitude() - 1.).abs() < 1e-5);
+        // More than 90 degrees apart, must not flip to the opposite rotation
+        let output = vec![rotation(0.), rotation(170.)];
+        let v = spherical_linear_interpolate(0.5, &input, &output, false);
+        assert!((angle(v) - 85.).abs() < 1e-3);
+        let output = output.into_iter().map(Vector2::from).collect::<Vec<_>>();
+        let v = spherical_linear_interpolate(0.5, &input, &output, false);
+        assert!((angle([v.x, v.y]) - 85.).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_slerp_deterministic() {
+        let input = vec![0., 1., 2.];
+        let output = vec![
+            Quaternion::from([0., 0., 0., 1.]),
+            Quaternion::from([0.6, 0., 0.8, 0.]),
+            Quaternion::from([0., -0.28, 0., 0.96]),
+        ];
+        for i in 0..=200 {
+            let t = i as f32 / 100.;
+            let v = spherical_linear_interpolate_deterministic(t, &input, &output, false);
+            let again = spherical_linear_interpolate_deterministic(t, &input, &output, false);
+            let bits = |q: Quaternion<f32>| [q.v.x, q.v.y, q.v.z, q.s].map(f32::to_bits);
+            assert_eq!(bits(v), bits(again));
+            let libm = spherical_linear_interpolate(t, &input, &output, false);
+            assert!(v.sub(&libm).magnitude() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_trig_approx() {
+        for i in 0..=1000 {
+            let x = i as f32 / 500. - 1.;
+            assert!((acos_approx(x) - x.acos()).abs() < 1e-6);
+            let x = (x + 1.) * FRAC_PI_2;
+            assert!((sin_approx(x) - x.sin()).abs() < 1e-6);
+        }
+    }
 }
@@ -1,5 +1,6 @@
-use get_input_index;
+use get_uniform_input_index;
 use primitive::InterpolationPrimitive;
+use {resolve_segment, Segment};
 
 /// Do step interpolation.
 ///
@@ -11,20 +12,37 @@ use primitive::InterpolationPrimitive;
 /// - `input`: the input value to the function
 /// - `inputs`: list of discrete input values for each keyframe
 /// - `outputs`: list of output values to interpolate between, for step interpolation this should
-///              be the same size as `inputs`
+///   be the same size as `inputs`
 /// - `normalize`: if true, normalize the interpolated value before returning it
 pub fn step_interpolate<T>(input: f32, inputs: &[f32], outputs: &[T], _: bool) -> T
 where
     T: InterpolationPrimitive + Clone,
 {
-    let input_index = match get_input_index(input, inputs) {
-        Some(index) => index,
-        None => return outputs[0].clone(),
-    };
-    if input_index >= (inputs.len() - 1) {
-        outputs[outputs.len() - 1].clone()
-    } else {
-        outputs[input_index].clone()
+    match resolve_segment(input, inputs) {
+        Segment::Before => outputs[0].clone(),
+        Segment::After => outputs[outputs.len() - 1].clone(),
+        Segment::In { index, .. } => outputs[index].clone(),
+    }
+}
+
+/// Do step interpolation, for keyframes with uniformly spaced input values.
+///
+/// Same as `step_interpolate`, with the input value of keyframe `i` being `i * spacing`, without
+/// the need for an `inputs` list.
+///
+/// ## Parameters:
+///
+/// - `input`: the input value to the function
+/// - `outputs`: list of output values to interpolate between, one for each keyframe
+/// - `spacing`: distance between the input values of two consecutive keyframes
+/// - `normalize`: if true, normalize the interpolated value before returning it
+pub fn step_interpolate_uniform<T>(input: f32, outputs: &[T], spacing: f32, _: bool) -> T
+where
+    T: InterpolationPrimitive + Clone,
+{
+    match get_uniform_input_index(input, spacing, outputs.len()) {
+        Some(index) => outputs[index].clone(),
+        None => outputs[0].clone(),
     }
 }
 
@@ -93,4 +111,16 @@ mod tests {
             step_interpolate(0.5, &input, &output, true)
         );
     }
+
+    #[test]
+    fn test_step_uniform() {
+        let input = vec![0., 2., 4., 6.];
+        let output = vec![0, 1, 5, -3];
+        for &t in &[-1., 0., 1.9, 2., 3., 5.9, 6., 7.] {
+            assert_eq!(
+                step_interpolate(t, &input, &output, false),
+                step_interpolate_uniform(t, &output, 2., false)
+            );
+        }
+    }
 }
@@ -0,0 +1,43 @@
+/// Affine mapping of input values, applied by a `Curve` before sampling.
+///
+/// Sampling a curve at `input` samples its keyframes at `input * scale + offset`, so several
+/// instances of the same animation can play at different rates and start times while sharing the
+/// keyframe data.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TimeRemap {
+    /// Playback rate, 2 plays the curve twice as fast
+    pub scale: f32,
+    /// Input value of the keyframes that is sampled at input value 0
+    pub offset: f32,
+}
+
+impl TimeRemap {
+    /// Create a new time remap
+    pub fn new(scale: f32, offset: f32) -> Self {
+        TimeRemap { scale, offset }
+    }
+
+    /// Map an input value to the input value of the keyframes
+    pub fn apply(&self, input: f32) -> f32 {
+        input * self.scale + self.offset
+    }
+}
+
+impl Default for TimeRemap {
+    /// The identity mapping
+    fn default() -> Self {
+        TimeRemap::new(1., 0.)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        assert_eq!(1.5, TimeRemap::default().apply(1.5));
+        assert_eq!(4., TimeRemap::new(2., 1.).apply(1.5));
+    }
+}